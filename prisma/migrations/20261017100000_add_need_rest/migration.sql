@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "NPC" ADD COLUMN "needRest" INTEGER NOT NULL DEFAULT 100;
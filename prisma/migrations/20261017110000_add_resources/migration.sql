@@ -0,0 +1,56 @@
+-- CreateTable
+CREATE TABLE "Storage" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "worldId" TEXT NOT NULL,
+    "key" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "emoji" TEXT NOT NULL DEFAULT '📦',
+    "capacity" INTEGER NOT NULL DEFAULT 100,
+    "x" REAL NOT NULL DEFAULT 0,
+    "y" REAL NOT NULL DEFAULT 0,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "Storage_worldId_fkey" FOREIGN KEY ("worldId") REFERENCES "World" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE "StorageItem" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "storageId" TEXT NOT NULL,
+    "resource" TEXT NOT NULL,
+    "amount" INTEGER NOT NULL DEFAULT 0,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "StorageItem_storageId_fkey" FOREIGN KEY ("storageId") REFERENCES "Storage" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE "ProductionTask" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "npcId" TEXT NOT NULL,
+    "storageId" TEXT NOT NULL,
+    "occupation" TEXT NOT NULL,
+    "resource" TEXT NOT NULL,
+    "amount" INTEGER NOT NULL,
+    "startHour" INTEGER NOT NULL,
+    "endHour" INTEGER NOT NULL,
+    "completed" BOOLEAN NOT NULL DEFAULT false,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "ProductionTask_npcId_fkey" FOREIGN KEY ("npcId") REFERENCES "NPC" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "ProductionTask_storageId_fkey" FOREIGN KEY ("storageId") REFERENCES "Storage" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "Storage_worldId_idx" ON "Storage"("worldId");
+
+-- CreateIndex
+CREATE UNIQUE INDEX "Storage_worldId_key_key" ON "Storage"("worldId", "key");
+
+-- CreateIndex
+CREATE UNIQUE INDEX "StorageItem_storageId_resource_key" ON "StorageItem"("storageId", "resource");
+
+-- CreateIndex
+CREATE INDEX "ProductionTask_npcId_idx" ON "ProductionTask"("npcId");
+
+-- CreateIndex
+CREATE INDEX "ProductionTask_storageId_idx" ON "ProductionTask"("storageId");
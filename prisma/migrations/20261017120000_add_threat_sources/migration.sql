@@ -0,0 +1,33 @@
+-- AlterTable
+ALTER TABLE "Location" ADD COLUMN "x" REAL;
+ALTER TABLE "Location" ADD COLUMN "y" REAL;
+
+-- Place the seeded locations on the map (positions from src/location-system.ts)
+UPDATE "Location" SET "x" = 600, "y" = 400 WHERE "name" = 'The Drunken Dragon Tavern';
+UPDATE "Location" SET "x" = 400, "y" = 450 WHERE "name" = 'Marcus''s Forge';
+UPDATE "Location" SET "x" = 180, "y" = 430 WHERE "name" = 'Dark Woods';
+
+-- CreateTable
+CREATE TABLE "ThreatSource" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "worldId" TEXT NOT NULL,
+    "key" TEXT NOT NULL,
+    "type" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "description" TEXT NOT NULL DEFAULT '',
+    "x" REAL NOT NULL,
+    "y" REAL NOT NULL,
+    "radius" REAL NOT NULL,
+    "severity" INTEGER NOT NULL,
+    "active" BOOLEAN NOT NULL DEFAULT true,
+    "mobile" BOOLEAN NOT NULL DEFAULT false,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "ThreatSource_worldId_fkey" FOREIGN KEY ("worldId") REFERENCES "World" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "ThreatSource_worldId_idx" ON "ThreatSource"("worldId");
+
+-- CreateIndex
+CREATE UNIQUE INDEX "ThreatSource_worldId_key_key" ON "ThreatSource"("worldId", "key");
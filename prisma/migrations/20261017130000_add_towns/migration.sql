@@ -0,0 +1,22 @@
+-- CreateTable
+CREATE TABLE "Town" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "worldId" TEXT NOT NULL,
+    "key" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "x" REAL NOT NULL,
+    "y" REAL NOT NULL,
+    "radius" REAL NOT NULL,
+    "population" INTEGER NOT NULL DEFAULT 0,
+    "baseSafety" REAL NOT NULL DEFAULT 80,
+    "currentSafety" REAL NOT NULL DEFAULT 80,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "Town_worldId_fkey" FOREIGN KEY ("worldId") REFERENCES "World" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "Town_worldId_idx" ON "Town"("worldId");
+
+-- CreateIndex
+CREATE UNIQUE INDEX "Town_worldId_key_key" ON "Town"("worldId", "key");
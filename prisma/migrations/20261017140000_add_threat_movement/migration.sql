@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "ThreatSource" ADD COLUMN "movement" TEXT NOT NULL DEFAULT 'stationary';
+ALTER TABLE "ThreatSource" ADD COLUMN "route" TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE "ThreatSource" ADD COLUMN "routeIndex" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "ThreatSource" ADD COLUMN "speed" REAL NOT NULL DEFAULT 0;
+ALTER TABLE "ThreatSource" ADD COLUMN "wanderRadius" REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,27 @@
+-- CreateTable
+CREATE TABLE "LocationCrisis" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "worldId" TEXT NOT NULL,
+    "locationId" TEXT NOT NULL,
+    "type" TEXT NOT NULL,
+    "severity" REAL NOT NULL,
+    "sourceId" TEXT,
+    "startedDay" INTEGER NOT NULL,
+    "startedHour" INTEGER NOT NULL,
+    "resolved" BOOLEAN NOT NULL DEFAULT false,
+    "resolvedDay" INTEGER,
+    "baseHasFood" BOOLEAN NOT NULL,
+    "baseHasShelter" BOOLEAN NOT NULL,
+    "baseIsDangerous" BOOLEAN NOT NULL,
+    "baseDangerLevel" INTEGER NOT NULL,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "LocationCrisis_worldId_fkey" FOREIGN KEY ("worldId") REFERENCES "World" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "LocationCrisis_locationId_fkey" FOREIGN KEY ("locationId") REFERENCES "Location" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "LocationCrisis_worldId_idx" ON "LocationCrisis"("worldId");
+
+-- CreateIndex
+CREATE INDEX "LocationCrisis_locationId_idx" ON "LocationCrisis"("locationId");
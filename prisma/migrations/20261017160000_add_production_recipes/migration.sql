@@ -0,0 +1,19 @@
+-- CreateTable
+CREATE TABLE "ProductionRecipe" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "worldId" TEXT NOT NULL,
+    "occupation" TEXT NOT NULL,
+    "produces" TEXT NOT NULL,
+    "amount" INTEGER NOT NULL,
+    "timeHours" INTEGER NOT NULL,
+    "requires" TEXT NOT NULL DEFAULT '{}',
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    CONSTRAINT "ProductionRecipe_worldId_fkey" FOREIGN KEY ("worldId") REFERENCES "World" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "ProductionRecipe_worldId_idx" ON "ProductionRecipe"("worldId");
+
+-- CreateIndex
+CREATE UNIQUE INDEX "ProductionRecipe_worldId_occupation_key" ON "ProductionRecipe"("worldId", "occupation");
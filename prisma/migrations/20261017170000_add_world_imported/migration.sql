@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "World" ADD COLUMN "imported" BOOLEAN NOT NULL DEFAULT false;
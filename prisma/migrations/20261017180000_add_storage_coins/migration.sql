@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "Storage" ADD COLUMN "coins" INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,63 @@
+// Designer-editable data files (data/*.json)
+//
+// Each file ships built into the binary; a copy found on disk takes
+// precedence, so values can be tuned without a rebuild. Files are looked up
+// the same way as the database: an absolute path from an environment
+// variable, else relative to the working directory or its parent (`tauri
+// dev` runs from src-tauri/). World definitions (data/worlds/) are only ever
+// read from disk.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{AppError, AppResult};
+
+/// Where the data file is on disk, if anywhere. `env_var` may point to it
+/// directly; otherwise `relative` (data/...) is searched for.
+pub fn locate(env_var: &str, relative: &str) -> Option<PathBuf> {
+    if let Ok(configured) = env::var(env_var) {
+        return Some(PathBuf::from(configured));
+    }
+    find(relative)
+}
+
+/// `relative` (data/...) under the working directory or its parent
+pub fn find(relative: &str) -> Option<PathBuf> {
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut bases = vec![cwd.clone()];
+    if let Some(parent) = cwd.parent() {
+        bases.push(parent.to_path_buf());
+    }
+    bases
+        .into_iter()
+        .map(|base| base.join(relative))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Read and parse the data file at `path`, or `built_in` when there is none
+/// on disk
+pub fn load<T: DeserializeOwned>(path: Option<&Path>, built_in: &str) -> AppResult<T> {
+    match path {
+        Some(path) => read(path),
+        None => parse(built_in, Path::new("(built in)")),
+    }
+}
+
+/// Read and parse the data file at `path`
+pub fn read<T: DeserializeOwned>(path: &Path) -> AppResult<T> {
+    let json = fs::read_to_string(path).map_err(|e| AppError::InvalidData {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    parse(&json, path)
+}
+
+pub fn parse<T: DeserializeOwned>(json: &str, path: &Path) -> AppResult<T> {
+    serde_json::from_str(json).map_err(|e| AppError::InvalidData {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
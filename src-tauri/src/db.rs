@@ -0,0 +1,1380 @@
+// SQLite data layer - reads the Prisma database directly
+//
+// Prisma owns the schema (prisma/schema.prisma + migrations); this module only
+// maps its tables onto the types in models.rs.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Event, Faction, Goal, Location, LocationCrisis, Memory, Npc, NpcDetails, NpcState, Player,
+    ProductionRecipe, ProductionTask, Relationship, Schedule, Storage, ThreatSource, Town, World,
+    WorldState,
+};
+
+const DEFAULT_DB_PATH: &str = "prisma/dev.db";
+
+/// Location of the SQLite file. Connections are opened on demand so the
+/// tick thread and commands never share one.
+pub struct Database {
+    path: PathBuf,
+}
+
+impl Database {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Resolve the database from `DATABASE_URL` (as used by Prisma) or fall
+    /// back to prisma/dev.db. `tauri dev` runs from src-tauri/, so the parent
+    /// directory is searched as well.
+    pub fn locate() -> Self {
+        let configured = env::var("DATABASE_URL")
+            .ok()
+            .map(|url| url.trim_start_matches("file:").to_string());
+
+        let mut relatives: Vec<PathBuf> = Vec::new();
+        if let Some(configured) = &configured {
+            let configured = Path::new(configured);
+            if configured.is_absolute() {
+                return Self::new(configured);
+            }
+            // Prisma resolves relative URLs against the schema directory
+            relatives.push(configured.to_path_buf());
+            relatives.push(Path::new("prisma").join(configured));
+        }
+        relatives.push(PathBuf::from(DEFAULT_DB_PATH));
+
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let mut bases = vec![cwd.clone()];
+        if let Some(parent) = cwd.parent() {
+            bases.push(parent.to_path_buf());
+        }
+
+        for base in &bases {
+            for relative in &relatives {
+                let candidate = base.join(relative);
+                if candidate.is_file() {
+                    return Self::new(candidate);
+                }
+            }
+        }
+
+        Self::new(cwd.join(&relatives[0]))
+    }
+
+    pub fn connect(&self) -> AppResult<Connection> {
+        if !self.path.is_file() {
+            return Err(AppError::DbNotFound {
+                path: self.path.clone(),
+            });
+        }
+
+        let conn = Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+
+        // The Node tools may hold the file at the same time
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Ok(conn)
+    }
+}
+
+// ============================================================================
+// ROW MAPPING
+// ============================================================================
+
+/// Decode a JSON-in-TEXT column (values, fears, plan, ...)
+fn json_column<T: DeserializeOwned>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        let index = row.as_ref().column_index(column).unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))
+    })
+}
+
+/// Encode a JSON-in-TEXT column for writing
+fn json_text<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Decode an enum stored as its name (ResourceType, ThreatType, ThreatMovement)
+fn enum_column<T: FromStr<Err = String>>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let raw: String = row.get(column)?;
+    raw.parse().map_err(|e: String| {
+        let index = row.as_ref().column_index(column).unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into())
+    })
+}
+
+fn world_from_row(row: &Row) -> rusqlite::Result<World> {
+    Ok(World {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        current_day: row.get("currentDay")?,
+        current_hour: row.get("currentHour")?,
+        imported: row.get("imported")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn npc_from_row(row: &Row) -> rusqlite::Result<Npc> {
+    Ok(Npc {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        name: row.get("name")?,
+        age: row.get("age")?,
+        occupation: row.get("occupation")?,
+        state: row.get("state")?,
+        location_id: row.get("locationId")?,
+        openness: row.get("openness")?,
+        conscientiousness: row.get("conscientiousness")?,
+        extraversion: row.get("extraversion")?,
+        agreeableness: row.get("agreeableness")?,
+        neuroticism: row.get("neuroticism")?,
+        values: json_column(row, "values")?,
+        fears: json_column(row, "fears")?,
+        formality: row.get("formality")?,
+        verbosity: row.get("verbosity")?,
+        emotional_expression: row.get("emotionalExpression")?,
+        dialect: row.get("dialect")?,
+        speech_quirks: json_column(row, "speechQuirks")?,
+        need_food: row.get("needFood")?,
+        need_safety: row.get("needSafety")?,
+        need_wealth: row.get("needWealth")?,
+        need_social: row.get("needSocial")?,
+        need_purpose: row.get("needPurpose")?,
+        need_rest: row.get("needRest")?,
+        emotion_happiness: row.get("emotionHappiness")?,
+        emotion_anger: row.get("emotionAnger")?,
+        emotion_fear: row.get("emotionFear")?,
+        emotion_sadness: row.get("emotionSadness")?,
+        emotion_trust: row.get("emotionTrust")?,
+        emotion_anticipation: row.get("emotionAnticipation")?,
+        emotion_love: row.get("emotionLove")?,
+        emotion_desperation: row.get("emotionDesperation")?,
+        emotion_grief: row.get("emotionGrief")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn goal_from_row(row: &Row) -> rusqlite::Result<Goal> {
+    Ok(Goal {
+        id: row.get("id")?,
+        npc_id: row.get("npcId")?,
+        kind: row.get("type")?,
+        target: row.get("target")?,
+        priority: row.get("priority")?,
+        urgent: row.get("urgent")?,
+        desperate: row.get("desperate")?,
+        deadline: row.get("deadline")?,
+        completed: row.get("completed")?,
+        failed: row.get("failed")?,
+        plan: json_column(row, "plan")?,
+        obstacles: json_column(row, "obstacles")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn relationship_from_row(row: &Row) -> rusqlite::Result<Relationship> {
+    Ok(Relationship {
+        id: row.get("id")?,
+        from_npc_id: row.get("fromNpcId")?,
+        to_npc_id: row.get("toNpcId")?,
+        value: row.get("value")?,
+        trust: row.get("trust")?,
+        affection: row.get("affection")?,
+        respect: row.get("respect")?,
+        grudge: row.get("grudge")?,
+        fear: row.get("fear")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn memory_from_row(row: &Row) -> rusqlite::Result<Memory> {
+    Ok(Memory {
+        id: row.get("id")?,
+        npc_id: row.get("npcId")?,
+        day: row.get("day")?,
+        event: row.get("event")?,
+        emotion: row.get("emotion")?,
+        emotional_impact: row.get("emotionalImpact")?,
+        involved_npcs: json_column(row, "involvedNpcs")?,
+        created_at: row.get("createdAt")?,
+    })
+}
+
+/// Participants live in `_EventParticipants` and are filled in by the caller
+fn event_from_row(row: &Row) -> rusqlite::Result<Event> {
+    Ok(Event {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        day: row.get("day")?,
+        hour: row.get("hour")?,
+        kind: row.get("type")?,
+        description: row.get("description")?,
+        location_id: row.get("locationId")?,
+        participant_ids: Vec::new(),
+        target_id: row.get("targetId")?,
+        resolved: row.get("resolved")?,
+        consequences: json_column(row, "consequences")?,
+        dramatic_value: row.get("dramaticValue")?,
+        created_at: row.get("createdAt")?,
+    })
+}
+
+fn location_from_row(row: &Row) -> rusqlite::Result<Location> {
+    Ok(Location {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        kind: row.get("type")?,
+        has_food: row.get("hasFood")?,
+        has_shelter: row.get("hasShelter")?,
+        is_public: row.get("isPublic")?,
+        is_dangerous: row.get("isDangerous")?,
+        danger_level: row.get("dangerLevel")?,
+        x: row.get("x")?,
+        y: row.get("y")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn location_crisis_from_row(row: &Row) -> rusqlite::Result<LocationCrisis> {
+    Ok(LocationCrisis {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        location_id: row.get("locationId")?,
+        kind: enum_column(row, "type")?,
+        severity: row.get("severity")?,
+        source_id: row.get("sourceId")?,
+        started_day: row.get("startedDay")?,
+        started_hour: row.get("startedHour")?,
+        resolved: row.get("resolved")?,
+        resolved_day: row.get("resolvedDay")?,
+        base_has_food: row.get("baseHasFood")?,
+        base_has_shelter: row.get("baseHasShelter")?,
+        base_is_dangerous: row.get("baseIsDangerous")?,
+        base_danger_level: row.get("baseDangerLevel")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn schedule_from_row(row: &Row) -> rusqlite::Result<Schedule> {
+    Ok(Schedule {
+        id: row.get("id")?,
+        npc_id: row.get("npcId")?,
+        hour: row.get("hour")?,
+        activity: row.get("activity")?,
+        location_id: row.get("locationId")?,
+        created_at: row.get("createdAt")?,
+    })
+}
+
+fn faction_from_row(row: &Row) -> rusqlite::Result<Faction> {
+    Ok(Faction {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        name: row.get("name")?,
+        kind: row.get("type")?,
+        member_ids: json_column(row, "memberIds")?,
+        leader_ids: json_column(row, "leaderIds")?,
+        wealth: row.get("wealth")?,
+        power: row.get("power")?,
+        influence: row.get("influence")?,
+        relationships: json_column(row, "relationships")?,
+        goals: json_column(row, "goals")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+/// Inventory lives in `StorageItem` and is filled in by the caller
+fn storage_from_row(row: &Row) -> rusqlite::Result<Storage> {
+    Ok(Storage {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        key: row.get("key")?,
+        name: row.get("name")?,
+        emoji: row.get("emoji")?,
+        capacity: row.get("capacity")?,
+        x: row.get("x")?,
+        y: row.get("y")?,
+        coins: row.get("coins")?,
+        inventory: Default::default(),
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+/// Expects the NPC's name joined in as `npcName`
+fn production_task_from_row(row: &Row) -> rusqlite::Result<ProductionTask> {
+    Ok(ProductionTask {
+        id: row.get("id")?,
+        npc_id: row.get("npcId")?,
+        npc_name: row.get("npcName")?,
+        storage_id: row.get("storageId")?,
+        occupation: row.get("occupation")?,
+        resource: enum_column(row, "resource")?,
+        amount: row.get("amount")?,
+        start_hour: row.get("startHour")?,
+        end_hour: row.get("endHour")?,
+        completed: row.get("completed")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn production_recipe_from_row(row: &Row) -> rusqlite::Result<ProductionRecipe> {
+    Ok(ProductionRecipe {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        occupation: row.get("occupation")?,
+        produces: enum_column(row, "produces")?,
+        amount: row.get("amount")?,
+        time_hours: row.get("timeHours")?,
+        requires: json_column(row, "requires")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn threat_from_row(row: &Row) -> rusqlite::Result<ThreatSource> {
+    Ok(ThreatSource {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        key: row.get("key")?,
+        kind: enum_column(row, "type")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        x: row.get("x")?,
+        y: row.get("y")?,
+        radius: row.get("radius")?,
+        severity: row.get("severity")?,
+        active: row.get("active")?,
+        mobile: row.get("mobile")?,
+        movement: enum_column(row, "movement")?,
+        route: json_column(row, "route")?,
+        route_index: row.get("routeIndex")?,
+        speed: row.get("speed")?,
+        wander_radius: row.get("wanderRadius")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn town_from_row(row: &Row) -> rusqlite::Result<Town> {
+    Ok(Town {
+        id: row.get("id")?,
+        world_id: row.get("worldId")?,
+        key: row.get("key")?,
+        name: row.get("name")?,
+        x: row.get("x")?,
+        y: row.get("y")?,
+        radius: row.get("radius")?,
+        population: row.get("population")?,
+        base_safety: row.get("baseSafety")?,
+        current_safety: row.get("currentSafety")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn player_from_row(row: &Row) -> rusqlite::Result<Player> {
+    Ok(Player {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        reputation: row.get("reputation")?,
+        relationships: json_column(row, "relationships")?,
+        active_quests: json_column(row, "activeQuests")?,
+        completed_quests: json_column(row, "completedQuests")?,
+        failed_quests: json_column(row, "failedQuests")?,
+        recent_choices: json_column(row, "recentChoices")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+// ============================================================================
+// QUERIES
+// ============================================================================
+
+/// Same world `findFirst()` picks in the Node scripts: the oldest one
+pub fn first_world(conn: &Connection) -> rusqlite::Result<Option<World>> {
+    conn.query_row(
+        r#"SELECT * FROM "World" ORDER BY "createdAt" ASC LIMIT 1"#,
+        [],
+        world_from_row,
+    )
+    .optional()
+}
+
+pub fn get_world(conn: &Connection, world_id: &str) -> rusqlite::Result<Option<World>> {
+    conn.query_row(
+        r#"SELECT * FROM "World" WHERE "id" = ?1"#,
+        params![world_id],
+        world_from_row,
+    )
+    .optional()
+}
+
+pub fn list_npcs(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Npc>> {
+    let mut stmt = conn.prepare(r#"SELECT * FROM "NPC" WHERE "worldId" = ?1 ORDER BY "name""#)?;
+    let rows = stmt.query_map(params![world_id], npc_from_row)?;
+    rows.collect()
+}
+
+pub fn list_locations(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Location>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "Location" WHERE "worldId" = ?1 ORDER BY "name""#)?;
+    let rows = stmt.query_map(params![world_id], location_from_row)?;
+    rows.collect()
+}
+
+/// Oldest first; resolved crises only with `include_resolved`
+pub fn list_location_crises(
+    conn: &Connection,
+    world_id: &str,
+    include_resolved: bool,
+) -> rusqlite::Result<Vec<LocationCrisis>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT * FROM "LocationCrisis"
+           WHERE "worldId" = ?1 AND (?2 OR "resolved" = 0)
+           ORDER BY "startedDay", "startedHour", "createdAt""#,
+    )?;
+    let rows = stmt.query_map(
+        params![world_id, include_resolved],
+        location_crisis_from_row,
+    )?;
+    rows.collect()
+}
+
+pub fn get_npc(conn: &Connection, npc_id: &str) -> rusqlite::Result<Option<Npc>> {
+    conn.query_row(
+        r#"SELECT * FROM "NPC" WHERE "id" = ?1"#,
+        params![npc_id],
+        npc_from_row,
+    )
+    .optional()
+}
+
+pub fn get_location(conn: &Connection, location_id: &str) -> rusqlite::Result<Option<Location>> {
+    conn.query_row(
+        r#"SELECT * FROM "Location" WHERE "id" = ?1"#,
+        params![location_id],
+        location_from_row,
+    )
+    .optional()
+}
+
+/// Goals of every NPC in the world, highest priority first
+pub fn list_goals(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Goal>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT "Goal".* FROM "Goal"
+           JOIN "NPC" ON "NPC"."id" = "Goal"."npcId"
+           WHERE "NPC"."worldId" = ?1
+           ORDER BY "Goal"."priority" DESC"#,
+    )?;
+    let rows = stmt.query_map(params![world_id], goal_from_row)?;
+    rows.collect()
+}
+
+pub fn list_npc_goals(conn: &Connection, npc_id: &str) -> rusqlite::Result<Vec<Goal>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "Goal" WHERE "npcId" = ?1 ORDER BY "priority" DESC"#)?;
+    let rows = stmt.query_map(params![npc_id], goal_from_row)?;
+    rows.collect()
+}
+
+pub fn list_relationships(conn: &Connection, npc_id: &str) -> rusqlite::Result<Vec<Relationship>> {
+    let mut stmt = conn.prepare(r#"SELECT * FROM "Relationship" WHERE "fromNpcId" = ?1"#)?;
+    let rows = stmt.query_map(params![npc_id], relationship_from_row)?;
+    rows.collect()
+}
+
+pub fn list_memories(conn: &Connection, npc_id: &str, limit: u32) -> rusqlite::Result<Vec<Memory>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT * FROM "Memory" WHERE "npcId" = ?1
+           ORDER BY "day" DESC, "createdAt" DESC LIMIT ?2"#,
+    )?;
+    let rows = stmt.query_map(params![npc_id, limit], memory_from_row)?;
+    rows.collect()
+}
+
+/// Most recent events first
+pub fn list_events(conn: &Connection, world_id: &str, limit: u32) -> rusqlite::Result<Vec<Event>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT * FROM "Event" WHERE "worldId" = ?1
+           ORDER BY "day" DESC, "hour" DESC, "createdAt" DESC LIMIT ?2"#,
+    )?;
+    let mut events = stmt
+        .query_map(params![world_id, limit], event_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut participants =
+        conn.prepare(r#"SELECT "B" FROM "_EventParticipants" WHERE "A" = ?1"#)?;
+    for event in &mut events {
+        event.participant_ids = participants
+            .query_map(params![event.id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+    }
+    Ok(events)
+}
+
+pub fn list_schedule(conn: &Connection, npc_id: &str) -> rusqlite::Result<Vec<Schedule>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "Schedule" WHERE "npcId" = ?1 ORDER BY "hour""#)?;
+    let rows = stmt.query_map(params![npc_id], schedule_from_row)?;
+    rows.collect()
+}
+
+pub fn list_factions(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Faction>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "Faction" WHERE "worldId" = ?1 ORDER BY "name""#)?;
+    let rows = stmt.query_map(params![world_id], faction_from_row)?;
+    rows.collect()
+}
+
+fn load_inventory(conn: &Connection, storage: &mut Storage) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r#"SELECT "resource", "amount" FROM "StorageItem" WHERE "storageId" = ?1"#,
+    )?;
+    let rows = stmt.query_map(params![storage.id], |row| {
+        Ok((enum_column(row, "resource")?, row.get("amount")?))
+    })?;
+    storage.inventory = rows.collect::<rusqlite::Result<_>>()?;
+    Ok(())
+}
+
+pub fn list_storages(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Storage>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "Storage" WHERE "worldId" = ?1 ORDER BY "name""#)?;
+    let mut storages = stmt
+        .query_map(params![world_id], storage_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for storage in &mut storages {
+        load_inventory(conn, storage)?;
+    }
+    Ok(storages)
+}
+
+pub fn get_storage(conn: &Connection, storage_id: &str) -> rusqlite::Result<Option<Storage>> {
+    let storage = conn
+        .query_row(
+            r#"SELECT * FROM "Storage" WHERE "id" = ?1"#,
+            params![storage_id],
+            storage_from_row,
+        )
+        .optional()?;
+    match storage {
+        Some(mut storage) => {
+            load_inventory(conn, &mut storage)?;
+            Ok(Some(storage))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Production tasks of every NPC in the world, newest first
+pub fn list_production_tasks(
+    conn: &Connection,
+    world_id: &str,
+    include_completed: bool,
+) -> rusqlite::Result<Vec<ProductionTask>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT "ProductionTask".*, "NPC"."name" AS "npcName" FROM "ProductionTask"
+           JOIN "NPC" ON "NPC"."id" = "ProductionTask"."npcId"
+           WHERE "NPC"."worldId" = ?1 AND (?2 OR NOT "ProductionTask"."completed")
+           ORDER BY "ProductionTask"."startHour" DESC, "ProductionTask"."createdAt" DESC"#,
+    )?;
+    let rows = stmt.query_map(
+        params![world_id, include_completed],
+        production_task_from_row,
+    )?;
+    rows.collect()
+}
+
+pub fn list_production_recipes(
+    conn: &Connection,
+    world_id: &str,
+) -> rusqlite::Result<Vec<ProductionRecipe>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT * FROM "ProductionRecipe" WHERE "worldId" = ?1 ORDER BY "occupation""#,
+    )?;
+    let rows = stmt.query_map(params![world_id], production_recipe_from_row)?;
+    rows.collect()
+}
+
+/// Cleared threats included
+pub fn list_threats(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<ThreatSource>> {
+    let mut stmt =
+        conn.prepare(r#"SELECT * FROM "ThreatSource" WHERE "worldId" = ?1 ORDER BY "key""#)?;
+    let rows = stmt.query_map(params![world_id], threat_from_row)?;
+    rows.collect()
+}
+
+pub fn get_threat(conn: &Connection, id: &str) -> rusqlite::Result<Option<ThreatSource>> {
+    conn.query_row(
+        r#"SELECT * FROM "ThreatSource" WHERE "id" = ?1"#,
+        params![id],
+        threat_from_row,
+    )
+    .optional()
+}
+
+pub fn list_towns(conn: &Connection, world_id: &str) -> rusqlite::Result<Vec<Town>> {
+    let mut stmt = conn.prepare(r#"SELECT * FROM "Town" WHERE "worldId" = ?1 ORDER BY "name""#)?;
+    let rows = stmt.query_map(params![world_id], town_from_row)?;
+    rows.collect()
+}
+
+/// Player rows are not tied to a world; the first one is the active player
+pub fn first_player(conn: &Connection) -> rusqlite::Result<Option<Player>> {
+    conn.query_row(
+        r#"SELECT * FROM "Player" ORDER BY "createdAt" ASC LIMIT 1"#,
+        [],
+        player_from_row,
+    )
+    .optional()
+}
+
+/// Number of events `get_world_state` includes (same as WorldSimulation.getWorldState)
+const WORLD_STATE_EVENT_LIMIT: u32 = 20;
+
+pub fn load_world_state(conn: &Connection, world: World) -> rusqlite::Result<WorldState> {
+    let mut goals = list_goals(conn, &world.id)?;
+    goals.retain(|goal| !goal.completed && !goal.failed);
+
+    let npcs = list_npcs(conn, &world.id)?
+        .into_iter()
+        .map(|npc| NpcState {
+            goals: goals
+                .iter()
+                .filter(|goal| goal.npc_id == npc.id)
+                .cloned()
+                .collect(),
+            npc,
+        })
+        .collect();
+
+    Ok(WorldState {
+        npcs,
+        locations: list_locations(conn, &world.id)?,
+        location_crises: list_location_crises(conn, &world.id, false)?,
+        events: list_events(conn, &world.id, WORLD_STATE_EVENT_LIMIT)?,
+        factions: list_factions(conn, &world.id)?,
+        world,
+    })
+}
+
+/// Number of memories `get_npc_details` includes (same as NPCAgent.getState)
+const NPC_DETAILS_MEMORY_LIMIT: u32 = 10;
+
+pub fn load_npc_details(conn: &Connection, npc: Npc) -> rusqlite::Result<NpcDetails> {
+    Ok(NpcDetails {
+        location: get_location(conn, &npc.location_id)?,
+        goals: list_npc_goals(conn, &npc.id)?,
+        memories: list_memories(conn, &npc.id, NPC_DETAILS_MEMORY_LIMIT)?,
+        relationships: list_relationships(conn, &npc.id)?,
+        schedule: list_schedule(conn, &npc.id)?,
+        npc,
+    })
+}
+
+pub fn save_world_clock(
+    conn: &Connection,
+    world_id: &str,
+    day: i32,
+    hour: i32,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"UPDATE "World" SET "currentDay" = ?2, "currentHour" = ?3, "updatedAt" = ?4 WHERE "id" = ?1"#,
+        params![world_id, day, hour, now_millis()],
+    )?;
+    Ok(())
+}
+
+/// Run `write` in one transaction: everything it saves (savepoints included)
+/// is kept, or nothing if it fails
+pub fn in_transaction<T>(
+    conn: &mut Connection,
+    write: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> rusqlite::Result<T> {
+    conn.execute_batch("BEGIN")?;
+    match write(conn) {
+        Ok(value) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(value)
+        }
+        Err(e) => {
+            // The error that made us roll back is the one worth reporting
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+pub fn insert_world(conn: &Connection, world: &World) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"INSERT INTO "World" ("id", "name", "currentDay", "currentHour", "imported", "createdAt", "updatedAt")
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+        params![
+            world.id,
+            world.name,
+            world.current_day,
+            world.current_hour,
+            world.imported,
+            world.created_at,
+            world.updated_at,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Insert new locations, all rows in one savepoint
+pub fn insert_locations(conn: &mut Connection, locations: &[Location]) -> rusqlite::Result<()> {
+    if locations.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "Location" (
+                "id", "worldId", "name", "description", "type", "hasFood", "hasShelter",
+                "isPublic", "isDangerous", "dangerLevel", "x", "y", "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"#,
+        )?;
+        for location in locations {
+            stmt.execute(params![
+                location.id,
+                location.world_id,
+                location.name,
+                location.description,
+                location.kind,
+                location.has_food,
+                location.has_shelter,
+                location.is_public,
+                location.is_dangerous,
+                location.danger_level,
+                location.x,
+                location.y,
+                location.created_at,
+                location.updated_at,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new NPCs with every column, all rows in one savepoint
+pub fn insert_npcs(conn: &mut Connection, npcs: &[Npc]) -> rusqlite::Result<()> {
+    if npcs.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "NPC" (
+                "id", "worldId", "name", "age", "occupation", "state", "locationId",
+                "openness", "conscientiousness", "extraversion", "agreeableness", "neuroticism",
+                "values", "fears",
+                "formality", "verbosity", "emotionalExpression", "dialect", "speechQuirks",
+                "needFood", "needSafety", "needWealth", "needSocial", "needPurpose", "needRest",
+                "emotionHappiness", "emotionAnger", "emotionFear", "emotionSadness",
+                "emotionTrust", "emotionAnticipation",
+                "emotionLove", "emotionDesperation", "emotionGrief",
+                "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
+                ?31, ?32, ?33, ?34, ?35, ?36)"#,
+        )?;
+        for npc in npcs {
+            stmt.execute(params![
+                npc.id,
+                npc.world_id,
+                npc.name,
+                npc.age,
+                npc.occupation,
+                npc.state,
+                npc.location_id,
+                npc.openness,
+                npc.conscientiousness,
+                npc.extraversion,
+                npc.agreeableness,
+                npc.neuroticism,
+                json_text(&npc.values)?,
+                json_text(&npc.fears)?,
+                npc.formality,
+                npc.verbosity,
+                npc.emotional_expression,
+                npc.dialect,
+                json_text(&npc.speech_quirks)?,
+                npc.need_food,
+                npc.need_safety,
+                npc.need_wealth,
+                npc.need_social,
+                npc.need_purpose,
+                npc.need_rest,
+                npc.emotion_happiness,
+                npc.emotion_anger,
+                npc.emotion_fear,
+                npc.emotion_sadness,
+                npc.emotion_trust,
+                npc.emotion_anticipation,
+                npc.emotion_love,
+                npc.emotion_desperation,
+                npc.emotion_grief,
+                npc.created_at,
+                npc.updated_at,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new schedule entries, all rows in one savepoint
+pub fn insert_schedules(conn: &mut Connection, entries: &[Schedule]) -> rusqlite::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "Schedule" ("id", "npcId", "hour", "activity", "locationId", "createdAt")
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+        )?;
+        for entry in entries {
+            stmt.execute(params![
+                entry.id,
+                entry.npc_id,
+                entry.hour,
+                entry.activity,
+                entry.location_id,
+                entry.created_at,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Write back the columns the simulation changes, all rows in one savepoint
+pub fn save_npcs(conn: &mut Connection, npcs: &[&Npc]) -> rusqlite::Result<()> {
+    if npcs.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"UPDATE "NPC" SET
+                "state" = ?2, "locationId" = ?3,
+                "needFood" = ?4, "needSafety" = ?5, "needWealth" = ?6,
+                "needSocial" = ?7, "needPurpose" = ?8, "needRest" = ?9,
+                "emotionHappiness" = ?10, "emotionAnger" = ?11, "emotionFear" = ?12,
+                "emotionSadness" = ?13, "emotionTrust" = ?14, "emotionAnticipation" = ?15,
+                "emotionLove" = ?16, "emotionDesperation" = ?17, "emotionGrief" = ?18,
+                "updatedAt" = ?19
+               WHERE "id" = ?1"#,
+        )?;
+        for npc in npcs {
+            stmt.execute(params![
+                npc.id,
+                npc.state,
+                npc.location_id,
+                npc.need_food,
+                npc.need_safety,
+                npc.need_wealth,
+                npc.need_social,
+                npc.need_purpose,
+                npc.need_rest,
+                npc.emotion_happiness,
+                npc.emotion_anger,
+                npc.emotion_fear,
+                npc.emotion_sadness,
+                npc.emotion_trust,
+                npc.emotion_anticipation,
+                npc.emotion_love,
+                npc.emotion_desperation,
+                npc.emotion_grief,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new goals and update changed ones, all rows in one savepoint
+pub fn save_goals(conn: &mut Connection, goals: &[Goal]) -> rusqlite::Result<()> {
+    if goals.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "Goal" (
+                "id", "npcId", "type", "target", "priority", "urgent", "desperate",
+                "deadline", "completed", "failed", "plan", "obstacles", "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+               ON CONFLICT ("id") DO UPDATE SET
+                "priority" = excluded."priority", "urgent" = excluded."urgent",
+                "desperate" = excluded."desperate", "deadline" = excluded."deadline",
+                "completed" = excluded."completed", "failed" = excluded."failed",
+                "plan" = excluded."plan", "obstacles" = excluded."obstacles",
+                "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for goal in goals {
+            stmt.execute(params![
+                goal.id,
+                goal.npc_id,
+                goal.kind,
+                goal.target,
+                goal.priority,
+                goal.urgent,
+                goal.desperate,
+                goal.deadline,
+                goal.completed,
+                goal.failed,
+                json_text(&goal.plan)?,
+                json_text(&goal.obstacles)?,
+                goal.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn insert_memories(conn: &mut Connection, memories: &[Memory]) -> rusqlite::Result<()> {
+    if memories.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "Memory" (
+                "id", "npcId", "day", "event", "emotion", "emotionalImpact", "involvedNpcs", "createdAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+        )?;
+        for memory in memories {
+            stmt.execute(params![
+                memory.id,
+                memory.npc_id,
+                memory.day,
+                memory.event,
+                memory.emotion,
+                memory.emotional_impact,
+                json_text(&memory.involved_npcs)?,
+                memory.created_at,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert events with their participants (`_EventParticipants`)
+pub fn insert_events(conn: &mut Connection, events: &[Event]) -> rusqlite::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.savepoint()?;
+    {
+        let mut event_stmt = tx.prepare_cached(
+            r#"INSERT INTO "Event" (
+                "id", "worldId", "day", "hour", "type", "description", "locationId",
+                "targetId", "resolved", "consequences", "dramaticValue", "createdAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
+        )?;
+        let mut participant_stmt = tx.prepare_cached(
+            r#"INSERT OR IGNORE INTO "_EventParticipants" ("A", "B") VALUES (?1, ?2)"#,
+        )?;
+        for event in events {
+            event_stmt.execute(params![
+                event.id,
+                event.world_id,
+                event.day,
+                event.hour,
+                event.kind,
+                event.description,
+                event.location_id,
+                event.target_id,
+                event.resolved,
+                json_text(&event.consequences)?,
+                event.dramatic_value,
+                event.created_at,
+            ])?;
+            for npc_id in &event.participant_ids {
+                participant_stmt.execute(params![event.id, npc_id])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new storages or update existing ones, with their whole inventory,
+/// all rows in one savepoint
+pub fn save_storages(conn: &mut Connection, storages: &[&Storage]) -> rusqlite::Result<()> {
+    if storages.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut storage_stmt = tx.prepare_cached(
+            r#"INSERT INTO "Storage" (
+                "id", "worldId", "key", "name", "emoji", "capacity", "x", "y", "coins",
+                "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+               ON CONFLICT ("id") DO UPDATE SET
+                "name" = excluded."name", "emoji" = excluded."emoji",
+                "capacity" = excluded."capacity", "x" = excluded."x", "y" = excluded."y",
+                "coins" = excluded."coins", "updatedAt" = excluded."updatedAt""#,
+        )?;
+        let mut item_stmt = tx.prepare_cached(
+            r#"INSERT INTO "StorageItem" ("id", "storageId", "resource", "amount", "updatedAt")
+               VALUES (?1, ?2, ?3, ?4, ?5)
+               ON CONFLICT ("storageId", "resource") DO UPDATE SET
+                "amount" = excluded."amount", "updatedAt" = excluded."updatedAt"
+               WHERE "amount" != excluded."amount""#,
+        )?;
+        for storage in storages {
+            storage_stmt.execute(params![
+                storage.id,
+                storage.world_id,
+                storage.key,
+                storage.name,
+                storage.emoji,
+                storage.capacity,
+                storage.x,
+                storage.y,
+                storage.coins,
+                storage.created_at,
+                now,
+            ])?;
+            for (resource, amount) in &storage.inventory {
+                item_stmt.execute(params![
+                    new_id(),
+                    storage.id,
+                    resource.as_str(),
+                    amount,
+                    now
+                ])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new threats or update existing ones, all rows in one savepoint
+pub fn save_threats(conn: &mut Connection, threats: &[&ThreatSource]) -> rusqlite::Result<()> {
+    if threats.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "ThreatSource" (
+                "id", "worldId", "key", "type", "name", "description", "x", "y",
+                "radius", "severity", "active", "mobile", "movement", "route",
+                "routeIndex", "speed", "wanderRadius", "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                ?16, ?17, ?18, ?19)
+               ON CONFLICT ("id") DO UPDATE SET
+                "name" = excluded."name", "description" = excluded."description",
+                "x" = excluded."x", "y" = excluded."y", "radius" = excluded."radius",
+                "severity" = excluded."severity", "active" = excluded."active",
+                "mobile" = excluded."mobile", "movement" = excluded."movement",
+                "route" = excluded."route", "routeIndex" = excluded."routeIndex",
+                "speed" = excluded."speed", "wanderRadius" = excluded."wanderRadius",
+                "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for threat in threats {
+            stmt.execute(params![
+                threat.id,
+                threat.world_id,
+                threat.key,
+                threat.kind.as_str(),
+                threat.name,
+                threat.description,
+                threat.x,
+                threat.y,
+                threat.radius,
+                threat.severity,
+                threat.active,
+                threat.mobile,
+                threat.movement.as_str(),
+                json_text(&threat.route)?,
+                threat.route_index,
+                threat.speed,
+                threat.wander_radius,
+                threat.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new towns and update changed ones, all rows in one savepoint
+pub fn save_towns(conn: &mut Connection, towns: &[&Town]) -> rusqlite::Result<()> {
+    if towns.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "Town" (
+                "id", "worldId", "key", "name", "x", "y", "radius", "population",
+                "baseSafety", "currentSafety", "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+               ON CONFLICT ("id") DO UPDATE SET
+                "name" = excluded."name", "x" = excluded."x", "y" = excluded."y",
+                "radius" = excluded."radius", "population" = excluded."population",
+                "baseSafety" = excluded."baseSafety",
+                "currentSafety" = excluded."currentSafety", "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for town in towns {
+            stmt.execute(params![
+                town.id,
+                town.world_id,
+                town.key,
+                town.name,
+                town.x,
+                town.y,
+                town.radius,
+                town.population,
+                town.base_safety,
+                town.current_safety,
+                town.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Update what a crisis changes about a location, all rows in one savepoint
+pub fn save_location_effects(
+    conn: &mut Connection,
+    locations: &[&Location],
+) -> rusqlite::Result<()> {
+    if locations.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"UPDATE "Location" SET
+                "hasFood" = ?2, "hasShelter" = ?3, "isDangerous" = ?4, "dangerLevel" = ?5,
+                "updatedAt" = ?6
+               WHERE "id" = ?1"#,
+        )?;
+        for location in locations {
+            stmt.execute(params![
+                location.id,
+                location.has_food,
+                location.has_shelter,
+                location.is_dangerous,
+                location.danger_level,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new location crises and update changed ones, all rows in one savepoint
+pub fn save_location_crises(
+    conn: &mut Connection,
+    crises: &[&LocationCrisis],
+) -> rusqlite::Result<()> {
+    if crises.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "LocationCrisis" (
+                "id", "worldId", "locationId", "type", "severity", "sourceId",
+                "startedDay", "startedHour", "resolved", "resolvedDay",
+                "baseHasFood", "baseHasShelter", "baseIsDangerous", "baseDangerLevel",
+                "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+               ON CONFLICT ("id") DO UPDATE SET
+                "severity" = excluded."severity", "resolved" = excluded."resolved",
+                "resolvedDay" = excluded."resolvedDay", "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for crisis in crises {
+            stmt.execute(params![
+                crisis.id,
+                crisis.world_id,
+                crisis.location_id,
+                crisis.kind.as_str(),
+                crisis.severity,
+                crisis.source_id,
+                crisis.started_day,
+                crisis.started_hour,
+                crisis.resolved,
+                crisis.resolved_day,
+                crisis.base_has_food,
+                crisis.base_has_shelter,
+                crisis.base_is_dangerous,
+                crisis.base_danger_level,
+                crisis.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new production tasks and update changed ones, all rows in one savepoint
+pub fn save_production_tasks(
+    conn: &mut Connection,
+    tasks: &[ProductionTask],
+) -> rusqlite::Result<()> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "ProductionTask" (
+                "id", "npcId", "storageId", "occupation", "resource", "amount",
+                "startHour", "endHour", "completed", "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+               ON CONFLICT ("id") DO UPDATE SET
+                "completed" = excluded."completed", "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for task in tasks {
+            stmt.execute(params![
+                task.id,
+                task.npc_id,
+                task.storage_id,
+                task.occupation,
+                task.resource.as_str(),
+                task.amount,
+                task.start_hour,
+                task.end_hour,
+                task.completed,
+                task.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Insert new recipes or update existing ones, all rows in one savepoint
+pub fn save_production_recipes(
+    conn: &mut Connection,
+    recipes: &[&ProductionRecipe],
+) -> rusqlite::Result<()> {
+    if recipes.is_empty() {
+        return Ok(());
+    }
+
+    let now = now_millis();
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO "ProductionRecipe" (
+                "id", "worldId", "occupation", "produces", "amount", "timeHours", "requires",
+                "createdAt", "updatedAt"
+               ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+               ON CONFLICT ("id") DO UPDATE SET
+                "produces" = excluded."produces", "amount" = excluded."amount",
+                "timeHours" = excluded."timeHours", "requires" = excluded."requires",
+                "updatedAt" = excluded."updatedAt""#,
+        )?;
+        for recipe in recipes {
+            stmt.execute(params![
+                recipe.id,
+                recipe.world_id,
+                recipe.occupation,
+                recipe.produces.as_str(),
+                recipe.amount,
+                recipe.time_hours,
+                json_text(&recipe.requires)?,
+                recipe.created_at,
+                now,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Prisma stores DateTime columns as epoch milliseconds
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn base36(mut n: u64, width: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut out = Vec::new();
+    while n > 0 || out.len() < width {
+        out.push(DIGITS[(n % 36) as usize]);
+        n /= 36;
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Ids for rows created on the Rust side, shaped like Prisma's cuid():
+/// 'c' + timestamp + counter + fingerprint, all base36
+pub fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let millis = now_millis() as u64;
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) % 36u64.pow(4);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or_default();
+    let fingerprint = (nanos ^ ((std::process::id() as u64) << 20)) % 36u64.pow(8);
+
+    format!(
+        "c{}{}{}",
+        base36(millis, 8),
+        base36(count, 4),
+        base36(fingerprint, 8)
+    )
+}
@@ -0,0 +1,152 @@
+// Error type returned by every Tauri command
+//
+// Serializes to the frontend as `{ code, message, details? }` so the UI can
+// branch on `code` instead of parsing message text.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+#[derive(Debug)]
+pub enum AppError {
+    /// The SQLite file does not exist where we looked for it
+    DbNotFound {
+        path: PathBuf,
+    },
+    /// A table, column or JSON-in-TEXT value does not match models.rs
+    SchemaMismatch {
+        reason: String,
+    },
+    /// Any other SQLite failure
+    Database {
+        reason: String,
+    },
+    SimulationAlreadyRunning,
+    InvalidArgument {
+        name: &'static str,
+        reason: String,
+    },
+    NotFound {
+        entity: &'static str,
+        id: String,
+    },
+    /// A data file (data/*.json) could not be read or does not validate
+    InvalidData {
+        path: PathBuf,
+        reason: String,
+    },
+    /// A background task failed; not caused by the caller
+    Internal {
+        reason: String,
+    },
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn invalid_argument(name: &'static str, reason: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            name,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn not_found(entity: &'static str, id: impl Into<String>) -> Self {
+        Self::NotFound {
+            entity,
+            id: id.into(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DbNotFound { .. } => "DB_NOT_FOUND",
+            Self::SchemaMismatch { .. } => "SCHEMA_MISMATCH",
+            Self::Database { .. } => "DATABASE_ERROR",
+            Self::SimulationAlreadyRunning => "SIMULATION_ALREADY_RUNNING",
+            Self::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            Self::NotFound { .. } => "NOT_FOUND",
+            Self::InvalidData { .. } => "INVALID_DATA",
+            Self::Internal { .. } => "INTERNAL",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            Self::DbNotFound { path } => Some(json!({ "path": path.display().to_string() })),
+            Self::SchemaMismatch { reason }
+            | Self::Database { reason }
+            | Self::Internal { reason } => Some(json!({ "reason": reason })),
+            Self::SimulationAlreadyRunning => None,
+            Self::InvalidArgument { name, reason } => {
+                Some(json!({ "argument": name, "reason": reason }))
+            }
+            Self::NotFound { entity, id } => Some(json!({ "entity": entity, "id": id })),
+            Self::InvalidData { path, reason } => {
+                Some(json!({ "path": path.display().to_string(), "reason": reason }))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DbNotFound { path } => write!(f, "Database not found at {}", path.display()),
+            Self::SchemaMismatch { reason } => {
+                write!(f, "Database does not match the expected schema: {}", reason)
+            }
+            Self::Database { reason } => write!(f, "Database error: {}", reason),
+            Self::SimulationAlreadyRunning => write!(f, "Simulation is already running"),
+            Self::InvalidArgument { name, reason } => {
+                write!(f, "Invalid argument `{}`: {}", name, reason)
+            }
+            Self::NotFound { entity, id } => write!(f, "{} {} not found", entity, id),
+            Self::InvalidData { path, reason } => {
+                write!(f, "Invalid data file {}: {}", path.display(), reason)
+            }
+            Self::Internal { reason } => write!(f, "Internal error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let details = self.details();
+        let mut state =
+            serializer.serialize_struct("AppError", if details.is_some() { 3 } else { 2 })?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let Some(details) = details {
+            state.serialize_field("details", &details)?;
+        }
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        use rusqlite::Error;
+
+        let reason = err.to_string();
+        match err {
+            Error::InvalidColumnName(_)
+            | Error::InvalidColumnType(..)
+            | Error::FromSqlConversionFailure(..)
+            | Error::IntegralValueOutOfRange(..) => Self::SchemaMismatch { reason },
+            // Missing tables/columns surface as prepare-time SQLite errors
+            Error::SqliteFailure(_, Some(ref message))
+                if message.starts_with("no such table")
+                    || message.starts_with("no such column") =>
+            {
+                Self::SchemaMismatch { reason }
+            }
+            _ => Self::Database { reason },
+        }
+    }
+}
@@ -1,84 +1,483 @@
 // Prevents console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::Command;
-use std::sync::Mutex;
-use std::env;
+mod data;
+mod db;
+mod error;
+mod models;
+mod simulation;
 
-struct SimulationState {
-    running: Mutex<bool>,
-}
+use db::Database;
+use error::{AppError, AppResult};
+use models::{
+    Event, LocationCrisis, LocationCrisisType, NpcDetails, Player, ProductionTask, ResourceType,
+    Storage, ThreatSource, Town, WorldState,
+};
+use simulation::events::{self, WorldDiff};
+use simulation::{
+    BBox, Crisis, EntityKind, MarketPrice, NearbyThreat, NpcDecision, SafetyReport,
+    SimulationConfig, SimulationState, SimulationStatus, SpatialHit, Trade, WorldClock,
+};
+use tauri::{AppHandle, Manager, State};
 
 #[tauri::command]
-fn start_simulation() -> Result<String, String> {
-    println!("Starting simulation...");
-    
-    // For now, just return success
-    // We'll hook up the actual simulation next
+fn start_simulation(
+    app: AppHandle,
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    world_id: Option<String>,
+    tick_speed: Option<u64>,
+) -> AppResult<String> {
+    let config = SimulationConfig {
+        tick_speed_ms: tick_speed.unwrap_or(simulation::DEFAULT_TICK_SPEED_MS),
+    };
+    if config.tick_speed_ms == 0 {
+        return Err(AppError::invalid_argument(
+            "tickSpeed",
+            "must be greater than 0",
+        ));
+    }
+
+    state.start(app, &db, world_id.as_deref(), config)?;
     Ok("Simulation started".to_string())
 }
 
+/// Returns true if a running simulation was stopped, false if none was running
 #[tauri::command]
-fn stop_simulation() -> Result<String, String> {
-    println!("Stopping simulation...");
-    Ok("Simulation stopped".to_string())
-}
-
-#[tauri::command]
-fn get_world_state() -> Result<String, String> {
-    // Try to call the real database
-    let output = Command::new("node")
-        .arg("dist/get-world-state.js")
-        .current_dir(env::current_dir().unwrap().parent().unwrap()) // Go up from src-tauri
-        .output();
-
-    match output {
-        Ok(result) => {
-            let stdout = String::from_utf8(result.stdout)
-                .unwrap_or_else(|_| "{}".to_string());
-            
-            if !stdout.trim().is_empty() {
-                Ok(stdout)
-            } else {
-                // Fallback to mock data if no real data
-                let mock_data = r#"{
-                    "currentDay": 1,
-                    "currentHour": 12,
-                    "npcs": [
-                        {"name": "Marcus", "needFood": 45, "needSafety": 80},
-                        {"name": "Sarah", "needFood": 90, "needSafety": 95},
-                        {"name": "Emma", "needFood": 70, "needSafety": 75}
-                    ]
-                }"#;
-                Ok(mock_data.to_string())
-            }
-        }
-        Err(_) => {
-            // Fallback to mock data if command fails
-            let mock_data = r#"{
-                "currentDay": 1,
-                "currentHour": 12,
-                "npcs": [
-                    {"name": "Marcus", "needFood": 45, "needSafety": 80},
-                    {"name": "Sarah", "needFood": 90, "needSafety": 95},
-                    {"name": "Emma", "needFood": 70, "needSafety": 75}
-                ]
-            }"#;
-            Ok(mock_data.to_string())
+fn stop_simulation(state: State<'_, SimulationState>) -> AppResult<bool> {
+    Ok(state.stop())
+}
+
+/// Returns true if a running simulation was paused
+#[tauri::command]
+fn pause_simulation(state: State<'_, SimulationState>) -> AppResult<bool> {
+    Ok(state.pause())
+}
+
+/// Returns true if a paused simulation was resumed
+#[tauri::command]
+fn resume_simulation(state: State<'_, SimulationState>) -> AppResult<bool> {
+    Ok(state.resume())
+}
+
+/// Most hours a single step_simulation call may run; use fast-forward beyond that
+const MAX_STEP_TICKS: u32 = 24 * 7;
+
+/// Run `n_ticks` hours synchronously (while paused or stopped) and return what changed
+#[tauri::command]
+fn step_simulation(
+    app: AppHandle,
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    n_ticks: Option<u32>,
+) -> AppResult<WorldDiff> {
+    let n_ticks = n_ticks.unwrap_or(1);
+    if !(1..=MAX_STEP_TICKS).contains(&n_ticks) {
+        return Err(AppError::invalid_argument(
+            "nTicks",
+            format!("must be between 1 and {}", MAX_STEP_TICKS),
+        ));
+    }
+
+    let diff = state.step(&db, n_ticks)?;
+    events::emit_diff(&app, &diff);
+    Ok(diff)
+}
+
+/// Bounds for set_time_scale
+const MIN_TIME_SCALE: f64 = 0.1;
+const MAX_TIME_SCALE: f64 = 100.0;
+
+/// Speed up (> 1) or slow down (< 1) the running tick loop
+#[tauri::command]
+fn set_time_scale(state: State<'_, SimulationState>, multiplier: f64) -> AppResult<f64> {
+    if !(MIN_TIME_SCALE..=MAX_TIME_SCALE).contains(&multiplier) {
+        return Err(AppError::invalid_argument(
+            "multiplier",
+            format!("must be between {} and {}", MIN_TIME_SCALE, MAX_TIME_SCALE),
+        ));
+    }
+
+    state.set_time_scale(multiplier);
+    Ok(multiplier)
+}
+
+/// Run the simulation headless until the given day and hour (while paused or
+/// stopped), at most MAX_FAST_FORWARD_DAYS ahead. Only
+/// world://fast-forward-progress is emitted along the way.
+#[tauri::command]
+async fn fast_forward_to(app: AppHandle, day: i32, hour: Option<i32>) -> AppResult<WorldDiff> {
+    let hour = hour.unwrap_or(0);
+    if !(0..24).contains(&hour) {
+        return Err(AppError::invalid_argument(
+            "hour",
+            "must be between 0 and 23",
+        ));
+    }
+
+    // Runs for a while; keep it off the async runtime's worker threads
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SimulationState>();
+        let db = app.state::<Database>();
+        state.fast_forward(&db, WorldClock { day, hour }, |progress| {
+            events::emit_progress(&app, progress)
+        })
+    })
+    .await
+    .map_err(|e| AppError::Internal {
+        reason: format!("fast-forward task failed: {}", e),
+    })?
+}
+
+/// Stop a running fast_forward_to at the next in-game midnight. Returns false
+/// if none is running.
+#[tauri::command]
+fn cancel_fast_forward(state: State<'_, SimulationState>) -> bool {
+    state.cancel_fast_forward()
+}
+
+#[tauri::command]
+fn get_simulation_status(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+) -> AppResult<SimulationStatus> {
+    let mut status = state.status();
+
+    // Nothing loaded yet: report the time stored in the database
+    if status.world_id.is_none() {
+        if let Some(world) = db::first_world(&db.connect()?)? {
+            status.world_id = Some(world.id);
+            status.day = Some(world.current_day);
+            status.hour = Some(world.current_hour);
+            status.next_checkpoint = Some(simulation::next_checkpoint(world.current_hour));
         }
     }
+    Ok(status)
+}
+
+#[tauri::command]
+fn get_world_state(db: State<'_, Database>, world_id: Option<String>) -> AppResult<WorldState> {
+    let conn = db.connect()?;
+
+    let world = match world_id.as_deref() {
+        Some(id) => db::get_world(&conn, id)?,
+        None => db::first_world(&conn)?,
+    }
+    .ok_or_else(|| AppError::not_found("World", world_id.as_deref().unwrap_or("(first)")))?;
+
+    Ok(db::load_world_state(&conn, world)?)
+}
+
+/// Create a new world from a definition file: the name of one in data/worlds
+/// ("riverside") or a path
+#[tauri::command]
+fn import_world(db: State<'_, Database>, path: String) -> AppResult<WorldState> {
+    simulation::import_world(&db, &path)
+}
+
+#[tauri::command]
+fn get_npc_details(db: State<'_, Database>, npc_id: String) -> AppResult<NpcDetails> {
+    let conn = db.connect()?;
+    let npc = db::get_npc(&conn, &npc_id)?.ok_or_else(|| AppError::not_found("NPC", &npc_id))?;
+
+    Ok(db::load_npc_details(&conn, npc)?)
+}
+
+/// Ask the need-based behavior what the NPC would do now, with its reasoning
+#[tauri::command]
+fn decide_npc_activity(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    npc_id: String,
+) -> AppResult<NpcDecision> {
+    state.decide_npc_activity(&db, &npc_id)
+}
+
+/// The given world, or the first one
+fn resolve_world_id(conn: &rusqlite::Connection, world_id: Option<String>) -> AppResult<String> {
+    match world_id {
+        Some(id) => Ok(id),
+        None => db::first_world(conn)?
+            .map(|world| world.id)
+            .ok_or_else(|| AppError::not_found("World", "(first)")),
+    }
+}
+
+#[tauri::command]
+fn list_storages(db: State<'_, Database>, world_id: Option<String>) -> AppResult<Vec<Storage>> {
+    let conn = db.connect()?;
+    let world_id = resolve_world_id(&conn, world_id)?;
+    Ok(db::list_storages(&conn, &world_id)?)
+}
+
+#[tauri::command]
+fn get_storage_inventory(db: State<'_, Database>, storage_id: String) -> AppResult<Storage> {
+    let conn = db.connect()?;
+    db::get_storage(&conn, &storage_id)?.ok_or_else(|| AppError::not_found("Storage", &storage_id))
+}
+
+/// Move resources between two storages; returns the amount actually moved
+#[tauri::command]
+fn transfer_resources(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    from_storage_id: String,
+    to_storage_id: String,
+    resource: ResourceType,
+    amount: i32,
+) -> AppResult<i32> {
+    state.transfer_resources(&db, &from_storage_id, &to_storage_id, resource, amount)
+}
+
+/// Sell resources from one storage to another at market prices; returns
+/// what actually changed hands
+#[tauri::command]
+fn trade_resources(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    seller_storage_id: String,
+    buyer_storage_id: String,
+    resource: ResourceType,
+    amount: i32,
+) -> AppResult<Trade> {
+    state.trade_resources(&db, &seller_storage_id, &buyer_storage_id, resource, amount)
+}
+
+/// Open production tasks (and finished ones if asked), newest first
+#[tauri::command]
+fn list_production_tasks(
+    db: State<'_, Database>,
+    world_id: Option<String>,
+    include_completed: Option<bool>,
+) -> AppResult<Vec<ProductionTask>> {
+    let conn = db.connect()?;
+    let world_id = resolve_world_id(&conn, world_id)?;
+    Ok(db::list_production_tasks(
+        &conn,
+        &world_id,
+        include_completed.unwrap_or(false),
+    )?)
+}
+
+/// Supply/demand price of every resource
+#[tauri::command]
+fn get_market_prices(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    world_id: Option<String>,
+) -> AppResult<Vec<MarketPrice>> {
+    state.market_prices(&db, world_id.as_deref())
+}
+
+/// Map positions come from the frontend; NaN or infinite ones would land
+/// in no cell of the spatial index
+fn check_position(x: f64, y: f64) -> AppResult<()> {
+    if !x.is_finite() {
+        return Err(AppError::invalid_argument("x", "must be a finite number"));
+    }
+    if !y.is_finite() {
+        return Err(AppError::invalid_argument("y", "must be a finite number"));
+    }
+    Ok(())
+}
+
+/// Safety (0-100) at a map position and the threats lowering it
+#[tauri::command]
+fn query_safety(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    x: f64,
+    y: f64,
+    world_id: Option<String>,
+) -> AppResult<SafetyReport> {
+    check_position(x, y)?;
+    state.query_safety(&db, world_id.as_deref(), x, y)
+}
+
+/// Move a mobile threat (like the player); town safety follows
+#[tauri::command]
+fn move_threat(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    threat_id: String,
+    x: f64,
+    y: f64,
+) -> AppResult<ThreatSource> {
+    check_position(x, y)?;
+    state.move_threat(&db, &threat_id, x, y)
+}
+
+/// Clear a threat; `by` is "player" or the id of the NPC who did it.
+/// Returns the recorded event.
+#[tauri::command]
+fn clear_threat(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    threat_id: String,
+    by: String,
+) -> AppResult<Event> {
+    state.clear_threat(&db, &threat_id, &by)
+}
+
+/// Towns with their cached safety
+#[tauri::command]
+fn list_towns(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    world_id: Option<String>,
+) -> AppResult<Vec<Town>> {
+    state.list_towns(&db, world_id.as_deref())
+}
+
+/// Fires, floods and famines, oldest first; resolved ones only with
+/// `include_resolved`
+#[tauri::command]
+fn list_location_crises(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    world_id: Option<String>,
+    include_resolved: Option<bool>,
+) -> AppResult<Vec<LocationCrisis>> {
+    state.list_location_crises(&db, world_id.as_deref(), include_resolved.unwrap_or(false))
+}
+
+/// Start a fire, flood or famine (`crisis_type`) at a location, severity
+/// 0-100 (default 50)
+#[tauri::command]
+fn start_location_crisis(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    location_id: String,
+    crisis_type: LocationCrisisType,
+    severity: Option<f64>,
+) -> AppResult<LocationCrisis> {
+    state.start_location_crisis(&db, &location_id, crisis_type, severity)
+}
+
+/// NPCs in a crisis (starving, in danger, ...), most urgent first, for
+/// quest and dialogue generation
+#[tauri::command]
+fn list_active_crises(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    world_id: Option<String>,
+) -> AppResult<Vec<Crisis>> {
+    state.list_active_crises(&db, world_id.as_deref())
+}
+
+/// The `k` (default 3) active threats closest to a map position
+#[tauri::command]
+fn nearest_threats(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    x: f64,
+    y: f64,
+    k: Option<usize>,
+    world_id: Option<String>,
+) -> AppResult<Vec<NearbyThreat>> {
+    check_position(x, y)?;
+    state.nearest_threats(&db, world_id.as_deref(), x, y, k.unwrap_or(3))
+}
+
+/// Threats, locations and NPCs (or only `kind`) within `radius` of a map
+/// position, closest first
+#[tauri::command]
+fn entities_in_radius(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    x: f64,
+    y: f64,
+    radius: f64,
+    kind: Option<EntityKind>,
+    world_id: Option<String>,
+) -> AppResult<Vec<SpatialHit>> {
+    check_position(x, y)?;
+    if radius.is_nan() || radius < 0.0 {
+        return Err(AppError::invalid_argument("radius", "must be 0 or more"));
+    }
+    state.entities_in_radius(&db, world_id.as_deref(), (x, y), radius, kind)
+}
+
+/// The `k` threats, locations or NPCs (or only `kind`) closest to a map position
+#[tauri::command]
+fn nearest_entities(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    x: f64,
+    y: f64,
+    k: usize,
+    kind: Option<EntityKind>,
+    world_id: Option<String>,
+) -> AppResult<Vec<SpatialHit>> {
+    check_position(x, y)?;
+    state.nearest_entities(&db, world_id.as_deref(), (x, y), k, kind)
+}
+
+/// Threats, locations and NPCs (or only `kind`) inside a box of the map
+#[tauri::command]
+fn entities_in_bbox(
+    state: State<'_, SimulationState>,
+    db: State<'_, Database>,
+    bbox: BBox,
+    kind: Option<EntityKind>,
+    world_id: Option<String>,
+) -> AppResult<Vec<SpatialHit>> {
+    check_position(bbox.min_x, bbox.min_y)?;
+    check_position(bbox.max_x, bbox.max_y)?;
+    if bbox.min_x > bbox.max_x || bbox.min_y > bbox.max_y {
+        return Err(AppError::invalid_argument(
+            "bbox",
+            "the box's min corner must not be past its max corner",
+        ));
+    }
+    state.entities_in_bbox(&db, world_id.as_deref(), bbox, kind)
+}
+
+#[tauri::command]
+fn get_player(db: State<'_, Database>) -> AppResult<Option<Player>> {
+    let conn = db.connect()?;
+    Ok(db::first_player(&conn)?)
 }
 
 fn main() {
+    let db = Database::locate();
+
     tauri::Builder::default()
-        .manage(SimulationState {
-            running: Mutex::new(false),
-        })
+        .manage(db)
+        .manage(SimulationState::new())
         .invoke_handler(tauri::generate_handler![
             start_simulation,
             stop_simulation,
-            get_world_state
+            pause_simulation,
+            resume_simulation,
+            step_simulation,
+            set_time_scale,
+            fast_forward_to,
+            cancel_fast_forward,
+            get_simulation_status,
+            get_world_state,
+            import_world,
+            get_npc_details,
+            decide_npc_activity,
+            list_storages,
+            get_storage_inventory,
+            transfer_resources,
+            trade_resources,
+            list_production_tasks,
+            get_market_prices,
+            query_safety,
+            nearest_threats,
+            move_threat,
+            clear_threat,
+            list_towns,
+            list_active_crises,
+            list_location_crises,
+            start_location_crisis,
+            entities_in_radius,
+            nearest_entities,
+            entities_in_bbox,
+            get_player
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
@@ -0,0 +1,769 @@
+// Domain types mirroring prisma/schema.prisma
+//
+// Field names serialize in camelCase so the frontend sees the same shape
+// Prisma returns. DateTime columns are stored by Prisma as epoch milliseconds.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// WORLD STATE
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct World {
+    pub id: String,
+    pub name: String,
+    pub current_day: i32,
+    pub current_hour: i32,
+    /// Created by import_world: it has exactly what its file lists and never
+    /// gets the default threats, towns, storages or recipes
+    pub imported: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// NPCs
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Npc {
+    pub id: String,
+    pub world_id: String,
+
+    // Identity
+    pub name: String,
+    pub age: i32,
+    pub occupation: String,
+    pub state: String, // alive, injured, kidnapped, dead
+
+    // Location
+    pub location_id: String,
+
+    // Personality Traits (0-100)
+    pub openness: i32,
+    pub conscientiousness: i32,
+    pub extraversion: i32,
+    pub agreeableness: i32,
+    pub neuroticism: i32,
+
+    // Values and Fears
+    pub values: Vec<String>,
+    pub fears: Vec<String>,
+
+    // Speech Pattern (0-100)
+    pub formality: i32,
+    pub verbosity: i32,
+    pub emotional_expression: i32,
+    pub dialect: String,
+    pub speech_quirks: Vec<String>,
+
+    // Needs (0-100)
+    pub need_food: i32,
+    pub need_safety: i32,
+    pub need_wealth: i32,
+    pub need_social: i32,
+    pub need_purpose: i32,
+    /// Energy; drains while awake, recovered by resting
+    pub need_rest: i32,
+
+    // Emotions (0-100)
+    pub emotion_happiness: i32,
+    pub emotion_anger: i32,
+    pub emotion_fear: i32,
+    pub emotion_sadness: i32,
+    pub emotion_trust: i32,
+    pub emotion_anticipation: i32,
+
+    // Derived Emotions
+    pub emotion_love: i32,
+    pub emotion_desperation: i32,
+    pub emotion_grief: i32,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// GOALS - What NPCs Want
+// ============================================================================
+
+/// One step of a goal's plan (`Action` in src/types.ts)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "ActionRepr")]
+pub struct Action {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<i32>, // hours
+    /// Free-text step ("Save money"), see `ActionRepr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Action kind used for plain-text plan steps
+pub const TASK_ACTION: &str = "task";
+
+/// The seed and test scripts store plans as arrays of plain strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ActionRepr {
+    Description(String),
+    #[serde(rename_all = "camelCase")]
+    Action {
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(default)]
+        target: Option<String>,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        duration: Option<i32>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+impl From<ActionRepr> for Action {
+    fn from(repr: ActionRepr) -> Self {
+        match repr {
+            ActionRepr::Description(description) => Action {
+                kind: TASK_ACTION.to_string(),
+                target: None,
+                location: None,
+                duration: None,
+                description: Some(description),
+            },
+            ActionRepr::Action {
+                kind,
+                target,
+                location,
+                duration,
+                description,
+            } => Action {
+                kind,
+                target,
+                location,
+                duration,
+                description,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub id: String,
+    pub npc_id: String,
+
+    #[serde(rename = "type")]
+    pub kind: String, // survival, rescue, revenge, romance, wealth, power, knowledge, escape
+    pub target: String,
+    pub priority: i32,
+    pub urgent: bool,
+    pub desperate: bool,
+    pub deadline: Option<i32>, // day number when goal expires
+    pub completed: bool,
+    pub failed: bool,
+
+    pub plan: Vec<Action>,
+    pub obstacles: Vec<String>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// RELATIONSHIPS - How NPCs Feel About Each Other
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub id: String,
+    pub from_npc_id: String,
+    pub to_npc_id: String,
+
+    // Relationship Values (-100 to 100)
+    pub value: i32,
+    pub trust: i32,
+    pub affection: i32,
+    pub respect: i32,
+    pub grudge: i32,
+    pub fear: i32,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// MEMORIES - NPCs Remember Events
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub id: String,
+    pub npc_id: String,
+
+    pub day: i32,
+    pub event: String,
+    pub emotion: String,
+    pub emotional_impact: i32, // 0-100
+    pub involved_npcs: Vec<String>,
+
+    pub created_at: i64,
+}
+
+// ============================================================================
+// EVENTS - Things That Happen in the World
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsequenceType {
+    Relationship,
+    Need,
+    Emotion,
+    Goal,
+    State,
+    Reputation,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConsequenceValue {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Consequence {
+    #[serde(rename = "type")]
+    pub kind: ConsequenceType,
+    pub npc_id: String,
+    pub field: String,
+    pub value: ConsequenceValue,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delayed: Option<i32>, // Apply after N days
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: String,
+    pub world_id: String,
+
+    pub day: i32,
+    pub hour: i32,
+
+    #[serde(rename = "type")]
+    pub kind: String, // kidnapping, murder, theft, discovery, conversation, etc.
+    pub description: String,
+    pub location_id: String,
+
+    /// From the implicit `_EventParticipants` join table
+    pub participant_ids: Vec<String>,
+    pub target_id: Option<String>,
+
+    pub resolved: bool,
+    pub consequences: Vec<Consequence>,
+    pub dramatic_value: i32, // 0-100
+
+    pub created_at: i64,
+}
+
+// ============================================================================
+// LOCATIONS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub id: String,
+    pub world_id: String,
+
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "type")]
+    pub kind: String, // building, outdoor, dungeon, etc.
+
+    pub has_food: bool,
+    pub has_shelter: bool,
+    pub is_public: bool,
+    pub is_dangerous: bool,
+    pub danger_level: i32,
+
+    /// Map position; None if the location is not on the map
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationCrisisType {
+    Fire,
+    Flood,
+    Famine,
+}
+
+impl LocationCrisisType {
+    pub const ALL: [LocationCrisisType; 3] = [
+        LocationCrisisType::Fire,
+        LocationCrisisType::Flood,
+        LocationCrisisType::Famine,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LocationCrisisType::Fire => "fire",
+            LocationCrisisType::Flood => "flood",
+            LocationCrisisType::Famine => "famine",
+        }
+    }
+}
+
+impl fmt::Display for LocationCrisisType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LocationCrisisType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fire" => Ok(LocationCrisisType::Fire),
+            "flood" => Ok(LocationCrisisType::Flood),
+            "famine" => Ok(LocationCrisisType::Famine),
+            other => Err(format!("unknown location crisis type '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationCrisis {
+    pub id: String,
+    pub world_id: String,
+    pub location_id: String,
+
+    #[serde(rename = "type")]
+    pub kind: LocationCrisisType,
+    pub severity: f64,             // 0-100, resolved at 0
+    pub source_id: Option<String>, // crisis it spread from
+
+    pub started_day: i32,
+    pub started_hour: i32,
+    pub resolved: bool,
+    pub resolved_day: Option<i32>,
+
+    // The location as it was before any crisis there
+    pub base_has_food: bool,
+    pub base_has_shelter: bool,
+    pub base_is_dangerous: bool,
+    pub base_danger_level: i32,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// SCHEDULE - NPC Daily Routines
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: String,
+    pub npc_id: String,
+
+    pub hour: i32,        // 0-23
+    pub activity: String, // work, sleep, eat, socialize, etc.
+    pub location_id: String,
+
+    pub created_at: i64,
+}
+
+// ============================================================================
+// FACTIONS - Groups with Goals
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Faction {
+    pub id: String,
+    pub world_id: String,
+
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String, // guild, government, criminal, religious
+    pub member_ids: Vec<String>,
+    pub leader_ids: Vec<String>,
+
+    // Faction State (0-100)
+    pub wealth: i32,
+    pub power: i32,
+    pub influence: i32,
+
+    /// factionId -> value
+    pub relationships: HashMap<String, i32>,
+    /// Free-form goal objects; the schema does not fix their shape
+    pub goals: Vec<serde_json::Value>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// RESOURCES - Storages, Inventories and Production
+// ============================================================================
+
+/// ResourceType in src/resource-system.ts, stored as its lowercase name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceType {
+    Wood,
+    Stone,
+    Iron,
+    Food,
+    Cloth,
+    Tools,
+    Weapons,
+    Medicine,
+}
+
+impl ResourceType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResourceType::Wood => "wood",
+            ResourceType::Stone => "stone",
+            ResourceType::Iron => "iron",
+            ResourceType::Food => "food",
+            ResourceType::Cloth => "cloth",
+            ResourceType::Tools => "tools",
+            ResourceType::Weapons => "weapons",
+            ResourceType::Medicine => "medicine",
+        }
+    }
+}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ResourceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wood" => Ok(ResourceType::Wood),
+            "stone" => Ok(ResourceType::Stone),
+            "iron" => Ok(ResourceType::Iron),
+            "food" => Ok(ResourceType::Food),
+            "cloth" => Ok(ResourceType::Cloth),
+            "tools" => Ok(ResourceType::Tools),
+            "weapons" => Ok(ResourceType::Weapons),
+            "medicine" => Ok(ResourceType::Medicine),
+            other => Err(format!("unknown resource type '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Storage {
+    pub id: String,
+    pub world_id: String,
+
+    pub key: String, // warehouse, market, armory
+    pub name: String,
+    pub emoji: String,
+    pub capacity: i32, // total units across all resources
+    pub x: f64,
+    pub y: f64,
+    pub coins: i32, // paid out when the storage buys, earned when it sells
+
+    /// From the `StorageItem` rows
+    pub inventory: BTreeMap<ResourceType, i32>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Hours are absolute (day * 24 + hour) so tasks can run past midnight
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductionTask {
+    pub id: String,
+    pub npc_id: String,
+    /// From the NPC row
+    pub npc_name: String,
+    pub storage_id: String, // Where the output is delivered
+
+    pub occupation: String,
+    pub resource: ResourceType,
+    pub amount: i32,
+    pub start_hour: i64,
+    pub end_hour: i64,
+    pub completed: bool,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Production recipe - what an occupation makes while working
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductionRecipe {
+    pub id: String,
+    pub world_id: String,
+
+    pub occupation: String, // lowercase
+    pub produces: ResourceType,
+    pub amount: i32,
+    pub time_hours: i32,
+    /// Inputs taken from the storage when a task starts
+    pub requires: BTreeMap<ResourceType, i32>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// THREATS - Danger on the Map
+// ============================================================================
+
+/// ThreatSource['type'] in src/threat-system.ts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatType {
+    WolfDen,
+    BanditCamp,
+    MonsterLair,
+    PlagueZone,
+    HauntedRuins,
+    Player,
+}
+
+impl ThreatType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThreatType::WolfDen => "wolf_den",
+            ThreatType::BanditCamp => "bandit_camp",
+            ThreatType::MonsterLair => "monster_lair",
+            ThreatType::PlagueZone => "plague_zone",
+            ThreatType::HauntedRuins => "haunted_ruins",
+            ThreatType::Player => "player",
+        }
+    }
+}
+
+impl fmt::Display for ThreatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ThreatType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wolf_den" => Ok(ThreatType::WolfDen),
+            "bandit_camp" => Ok(ThreatType::BanditCamp),
+            "monster_lair" => Ok(ThreatType::MonsterLair),
+            "plague_zone" => Ok(ThreatType::PlagueZone),
+            "haunted_ruins" => Ok(ThreatType::HauntedRuins),
+            "player" => Ok(ThreatType::Player),
+            other => Err(format!("unknown threat type '{}'", other)),
+        }
+    }
+}
+
+/// How a mobile threat moves on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatMovement {
+    /// Only moved from outside (like the player)
+    #[default]
+    Stationary,
+    /// Walks its route, then starts over
+    Patrol,
+    /// Drifts around its first waypoint
+    Wander,
+    /// Walks its route once and stays at the end (a raid)
+    Approach,
+}
+
+impl ThreatMovement {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThreatMovement::Stationary => "stationary",
+            ThreatMovement::Patrol => "patrol",
+            ThreatMovement::Wander => "wander",
+            ThreatMovement::Approach => "approach",
+        }
+    }
+}
+
+impl fmt::Display for ThreatMovement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ThreatMovement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stationary" => Ok(ThreatMovement::Stationary),
+            "patrol" => Ok(ThreatMovement::Patrol),
+            "wander" => Ok(ThreatMovement::Wander),
+            "approach" => Ok(ThreatMovement::Approach),
+            other => Err(format!("unknown threat movement '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreatSource {
+    pub id: String,
+    pub world_id: String,
+
+    pub key: String, // threat_1, player_threat
+    #[serde(rename = "type")]
+    pub kind: ThreatType,
+    pub name: String,
+    pub description: String,
+
+    // Map coordinates
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,   // influence radius
+    pub severity: i32, // 0-100
+    pub active: bool,  // false once cleared
+    pub mobile: bool,  // can move (like the player)
+
+    // Movement per in-game hour, for mobile threats
+    pub movement: ThreatMovement,
+    pub route: Vec<[f64; 2]>, // waypoints
+    pub route_index: i32,     // waypoint it is heading to
+    pub speed: f64,           // map units per hour
+    pub wander_radius: f64,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Town in src/town-safety-system.ts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Town {
+    pub id: String,
+    pub world_id: String,
+
+    pub key: String, // town_1
+    pub name: String,
+
+    // Town center and boundary
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+    pub population: i32,
+
+    pub base_safety: f64,    // 0-100, without external threats
+    pub current_safety: f64, // 0-100, from the active threats
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// PLAYER STATE - What the Player Has Done
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Player {
+    pub id: String,
+
+    pub name: String,
+    pub reputation: i32, // 0-100
+
+    /// npcId -> value
+    pub relationships: HashMap<String, i32>,
+
+    // Quest ids
+    pub active_quests: Vec<String>,
+    pub completed_quests: Vec<String>,
+    pub failed_quests: Vec<String>,
+
+    /// Free-form choice objects; the schema does not fix their shape
+    pub recent_choices: Vec<serde_json::Value>,
+
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// ============================================================================
+// AGGREGATES
+// ============================================================================
+
+/// An NPC with its open goals, highest priority first
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcState {
+    #[serde(flatten)]
+    pub npc: Npc,
+    pub goals: Vec<Goal>,
+}
+
+/// Payload of `get_world_state`: the world row plus what lives in it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldState {
+    #[serde(flatten)]
+    pub world: World,
+    pub npcs: Vec<NpcState>,
+    pub locations: Vec<Location>,
+    /// Fires, floods and famines still going on
+    pub location_crises: Vec<LocationCrisis>,
+    /// Most recent events first
+    pub events: Vec<Event>,
+    pub factions: Vec<Faction>,
+}
+
+/// Payload of `get_npc_details` (NPCAgent.getState in the TS simulation)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcDetails {
+    #[serde(flatten)]
+    pub npc: Npc,
+    pub location: Option<Location>,
+    pub goals: Vec<Goal>,
+    /// Most recent first
+    pub memories: Vec<Memory>,
+    pub relationships: Vec<Relationship>,
+    pub schedule: Vec<Schedule>,
+}
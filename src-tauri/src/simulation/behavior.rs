@@ -0,0 +1,556 @@
+// Need-based activity decisions - port of NeedBasedBehavior.decideAction in
+// src/need-based-behavior.ts
+//
+// Safety overrides everything: an NPC whose safety is critical flees home no
+// matter how hungry or broke it is. Every decision carries a trace of what was
+// considered, so the UI can answer "why is Sarah fleeing?".
+
+use serde::{Deserialize, Serialize};
+
+use super::buildings::BuildingEffects;
+use crate::models::{Location, Npc, Schedule};
+
+/// ActivityType in src/need-based-behavior.ts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activity {
+    Working,
+    Eating,
+    Socializing,
+    Resting,
+    Fleeing,
+    #[default]
+    Idle,
+}
+
+impl Activity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Activity::Working => "working",
+            Activity::Eating => "eating",
+            Activity::Socializing => "socializing",
+            Activity::Resting => "resting",
+            Activity::Fleeing => "fleeing",
+            Activity::Idle => "idle",
+        }
+    }
+
+    /// Schedule rows use short verbs (work, sleep, eat, socialize)
+    pub fn from_schedule(activity: &str) -> Self {
+        match activity {
+            "work" | "working" => Activity::Working,
+            "eat" | "eating" => Activity::Eating,
+            "socialize" | "socializing" => Activity::Socializing,
+            "sleep" | "rest" | "resting" => Activity::Resting,
+            _ => Activity::Idle,
+        }
+    }
+}
+
+/// LocationType in src/need-based-behavior.ts. No rule sends an NPC to a
+/// market, temple or town entrance; they only matter for building effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LocationType {
+    Home,
+    Workplace,
+    Tavern,
+    Market,
+    Temple,
+    TownEntrance,
+}
+
+impl LocationType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LocationType::Home => "home",
+            LocationType::Workplace => "workplace",
+            LocationType::Tavern => "tavern",
+            LocationType::Market => "market",
+            LocationType::Temple => "temple",
+            LocationType::TownEntrance => "town-entrance",
+        }
+    }
+}
+
+/// NPCDecision, plus the reasoning that led to it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcDecision {
+    /// Id of the location the NPC should go to
+    pub location: String,
+    pub location_type: LocationType,
+    pub activity: Activity,
+    pub reason: String,
+    pub priority: f64,
+    /// Needs read, options considered and the pick, in order
+    pub trace: Vec<String>,
+}
+
+// CRITICAL THRESHOLDS
+/// Below this = FLEE!
+pub const SECURITY_CRITICAL: i32 = 20;
+/// Below this = prefer safe locations
+const SECURITY_LOW: i32 = 40;
+/// Must eat NOW
+const HUNGER_CRITICAL: i32 = 25;
+/// Should eat soon
+const HUNGER_LOW: i32 = 50;
+/// Need to work
+const WEALTH_LOW: i32 = 40;
+/// Need social interaction
+const SOCIAL_LOW: i32 = 30;
+/// Need to sleep
+const REST_LOW: i32 = 25;
+
+/// The world's places as the decision sees them. The Prisma Location has no
+/// home/workplace type, so those come from the NPC's schedule and the rest
+/// from what a location offers.
+pub struct Places<'a> {
+    pub locations: &'a [Location],
+    pub schedule: &'a [Schedule],
+}
+
+impl Places<'_> {
+    fn scheduled(&self, activity: Activity) -> Option<&str> {
+        self.schedule
+            .iter()
+            .find(|entry| Activity::from_schedule(&entry.activity) == activity)
+            .map(|entry| entry.location_id.as_str())
+    }
+
+    /// Where the schedule has the NPC sleeping, else where it is now
+    fn home(&self, npc: &Npc) -> String {
+        self.scheduled(Activity::Resting)
+            .unwrap_or(&npc.location_id)
+            .to_string()
+    }
+
+    /// Where the schedule has the NPC working, else a place named after it
+    /// ("Marcus's Forge")
+    fn workplace(&self, npc: &Npc) -> Option<String> {
+        self.scheduled(Activity::Working)
+            .map(str::to_string)
+            .or_else(|| {
+                self.locations
+                    .iter()
+                    .find(|l| l.name.contains(&npc.name))
+                    .map(|l| l.id.clone())
+            })
+    }
+
+    fn name_of<'l>(&'l self, location_id: &'l str) -> &'l str {
+        self.locations
+            .iter()
+            .find(|l| l.id == location_id)
+            .map_or(location_id, |l| l.name.as_str())
+    }
+
+    /// What kind of place `location` is for this NPC: its home or workplace,
+    /// a place named like one in `locationNames`, or a tavern if it serves
+    /// food and shelter to the public
+    pub fn location_type(
+        &self,
+        npc: &Npc,
+        location: &Location,
+        effects: &BuildingEffects,
+    ) -> Option<LocationType> {
+        if self.home(npc) == location.id {
+            return Some(LocationType::Home);
+        }
+        if self.workplace(npc).as_deref() == Some(location.id.as_str()) {
+            return Some(LocationType::Workplace);
+        }
+        effects.named_type(&location.name).or_else(|| {
+            (location.is_public && location.has_food && location.has_shelter)
+                .then_some(LocationType::Tavern)
+        })
+    }
+
+    /// The safest public place serving food
+    fn tavern(&self) -> Option<String> {
+        self.locations
+            .iter()
+            .filter(|l| l.is_public && l.has_food && l.has_shelter)
+            .min_by_key(|l| l.danger_level)
+            .map(|l| l.id.clone())
+    }
+}
+
+fn option(
+    location: String,
+    location_type: LocationType,
+    activity: Activity,
+    reason: String,
+    priority: f64,
+) -> NpcDecision {
+    NpcDecision {
+        location,
+        location_type,
+        activity,
+        reason,
+        priority,
+        trace: Vec::new(),
+    }
+}
+
+/// Main decision function - determines what the NPC should do at `hour`
+pub fn decide_action(npc: &Npc, places: &Places, hour: i32) -> NpcDecision {
+    let mut trace = vec![format!(
+        "Needs: Food({}) Safety({}) Wealth({}) Social({}) Rest({})",
+        npc.need_food, npc.need_safety, npc.need_wealth, npc.need_social, npc.need_rest
+    )];
+    let home = places.home(npc);
+    let decided = |mut decision: NpcDecision, mut trace: Vec<String>| {
+        trace.push(format!(
+            "Decision: {} at {} ({})",
+            decision.activity.as_str(),
+            places.name_of(&decision.location),
+            decision.reason
+        ));
+        decision.trace = trace;
+        decision
+    };
+
+    // CRITICAL: Security override - flee home if in danger!
+    if npc.need_safety < SECURITY_CRITICAL {
+        trace.push(format!(
+            "Safety {} < {}: security overrides all other needs",
+            npc.need_safety, SECURITY_CRITICAL
+        ));
+        let decision = option(
+            home,
+            LocationType::Home,
+            Activity::Fleeing,
+            format!(
+                "Security critical ({}%) - fleeing home for safety",
+                npc.need_safety
+            ),
+            1000.0,
+        );
+        return decided(decision, trace);
+    }
+
+    // Night time (22-6) - prefer rest if tired
+    if !(6..22).contains(&hour) && npc.need_rest < REST_LOW {
+        trace.push(format!(
+            "Rest {} < {} at night: going home to sleep",
+            npc.need_rest, REST_LOW
+        ));
+        let decision = option(
+            home,
+            LocationType::Home,
+            Activity::Resting,
+            format!("Exhausted ({}%) and it's night", npc.need_rest),
+            80.0,
+        );
+        return decided(decision, trace);
+    }
+
+    let tavern = places.tavern();
+
+    // Critical hunger - must eat NOW
+    if npc.need_food < HUNGER_CRITICAL {
+        trace.push(format!(
+            "Food {} < {}: must eat immediately",
+            npc.need_food, HUNGER_CRITICAL
+        ));
+        let (location, location_type) = match &tavern {
+            Some(tavern) => (tavern.clone(), LocationType::Tavern),
+            None => (home.clone(), LocationType::Home),
+        };
+        let decision = option(
+            location,
+            location_type,
+            Activity::Eating,
+            format!("Critical hunger ({}%)", npc.need_food),
+            90.0,
+        );
+        return decided(decision, trace);
+    }
+
+    // Evaluate all possible actions and pick best
+    let mut options = Vec::new();
+
+    // Option: Work (if wealth low)
+    if npc.need_wealth < WEALTH_LOW {
+        match places.workplace(npc) {
+            Some(workplace) => options.push(option(
+                workplace,
+                LocationType::Workplace,
+                Activity::Working,
+                format!("Need money ({}%)", npc.need_wealth),
+                (100 - npc.need_wealth) as f64,
+            )),
+            None => trace.push("Wealth is low but there is no workplace".to_string()),
+        }
+    }
+
+    // Option: Eat (if hungry)
+    if npc.need_food < HUNGER_LOW {
+        let (location, location_type) = match &tavern {
+            Some(tavern) => (tavern.clone(), LocationType::Tavern),
+            None => (home.clone(), LocationType::Home),
+        };
+        options.push(option(
+            location,
+            location_type,
+            Activity::Eating,
+            format!("Hungry ({}%)", npc.need_food),
+            (100 - npc.need_food) as f64 * 0.8,
+        ));
+    }
+
+    // Option: Socialize (if lonely)
+    if npc.need_social < SOCIAL_LOW {
+        // Low security? Socialize at home instead of tavern
+        let (location, location_type) = match &tavern {
+            Some(tavern) if npc.need_safety >= SECURITY_LOW => {
+                (tavern.clone(), LocationType::Tavern)
+            }
+            _ => (home.clone(), LocationType::Home),
+        };
+        options.push(option(
+            location,
+            location_type,
+            Activity::Socializing,
+            format!("Lonely ({}%)", npc.need_social),
+            (100 - npc.need_social) as f64 * 0.7,
+        ));
+    }
+
+    // Option: Rest (if tired)
+    if npc.need_rest < REST_LOW {
+        options.push(option(
+            home.clone(),
+            LocationType::Home,
+            Activity::Resting,
+            format!("Tired ({}%)", npc.need_rest),
+            (100 - npc.need_rest) as f64 * 0.6,
+        ));
+    }
+
+    for option in &options {
+        trace.push(format!(
+            "Could go {} (priority {:.0}): {}",
+            option.activity.as_str(),
+            option.priority,
+            option.reason
+        ));
+    }
+
+    // Pick highest priority action (the first one on ties)
+    if let Some(chosen) = options
+        .into_iter()
+        .min_by(|a, b| b.priority.total_cmp(&a.priority))
+    {
+        return decided(chosen, trace);
+    }
+
+    // Default: idle at home
+    let decision = option(
+        home,
+        LocationType::Home,
+        Activity::Idle,
+        "All needs satisfied".to_string(),
+        0.0,
+    );
+    decided(decision, trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    /// Ann's cottage and forge, a tavern and a rougher inn
+    fn locations() -> Vec<Location> {
+        let mut cottage = fixtures::location("cottage", 0.0, 0.0);
+        cottage.name = "Ann's Cottage".to_string();
+        cottage.is_public = false;
+        let mut forge = fixtures::location("forge", 10.0, 0.0);
+        forge.is_public = false;
+        let mut inn = fixtures::location("inn", 20.0, 0.0);
+        inn.danger_level = 30;
+        vec![cottage, forge, inn, fixtures::location("tavern", 30.0, 0.0)]
+    }
+
+    fn schedule(hour: i32, activity: &str, location_id: &str) -> Schedule {
+        Schedule {
+            id: format!("{}-{}", activity, hour),
+            npc_id: "ann".to_string(),
+            hour,
+            activity: activity.to_string(),
+            location_id: location_id.to_string(),
+            created_at: 0,
+        }
+    }
+
+    fn decide(npc: &Npc, hour: i32) -> NpcDecision {
+        let locations = locations();
+        let schedule = [
+            schedule(22, "sleep", "cottage"),
+            schedule(8, "work", "forge"),
+        ];
+        let places = Places {
+            locations: &locations,
+            schedule: &schedule,
+        };
+        decide_action(npc, &places, hour)
+    }
+
+    fn choice(decision: &NpcDecision) -> (&str, LocationType, Activity) {
+        (
+            decision.location.as_str(),
+            decision.location_type,
+            decision.activity,
+        )
+    }
+
+    #[test]
+    fn content_npcs_idle_at_home() {
+        let decision = decide(&fixtures::npc("Ann"), 12);
+        assert_eq!(
+            choice(&decision),
+            ("cottage", LocationType::Home, Activity::Idle)
+        );
+        assert_eq!(
+            decision.trace,
+            [
+                "Needs: Food(80) Safety(80) Wealth(50) Social(60) Rest(80)",
+                "Decision: idle at Ann's Cottage (All needs satisfied)",
+            ]
+        );
+    }
+
+    #[test]
+    fn danger_sends_npcs_home_before_anything_else() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_safety = SECURITY_CRITICAL - 1;
+        npc.need_food = 0;
+        npc.need_rest = 0;
+
+        let decision = decide(&npc, 23);
+        assert_eq!(
+            choice(&decision),
+            ("cottage", LocationType::Home, Activity::Fleeing)
+        );
+        assert_eq!(decision.priority, 1000.0);
+        assert_eq!(
+            decision.trace[1..],
+            [
+                "Safety 19 < 20: security overrides all other needs",
+                "Decision: fleeing at Ann's Cottage (Security critical (19%) - fleeing home for safety)",
+            ]
+        );
+    }
+
+    #[test]
+    fn tired_npcs_sleep_from_22_until_6() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_rest = REST_LOW - 1;
+
+        for hour in [22, 23, 0, 5] {
+            let decision = decide(&npc, hour);
+            assert_eq!(decision.activity, Activity::Resting, "hour {}", hour);
+            assert_eq!(decision.reason, "Exhausted (24%) and it's night");
+        }
+        // By day resting is only one of the options
+        for hour in [6, 21] {
+            let decision = decide(&npc, hour);
+            assert_eq!(
+                choice(&decision),
+                ("cottage", LocationType::Home, Activity::Resting)
+            );
+            assert_eq!(decision.reason, "Tired (24%)");
+        }
+    }
+
+    #[test]
+    fn hungry_npcs_eat_at_the_safest_tavern() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_food = HUNGER_CRITICAL - 1;
+        npc.need_wealth = 0;
+
+        let decision = decide(&npc, 12);
+        assert_eq!(
+            choice(&decision),
+            ("tavern", LocationType::Tavern, Activity::Eating)
+        );
+        assert_eq!(decision.priority, 90.0);
+
+        // Without a tavern they eat at home
+        let schedule = [schedule(22, "sleep", "cottage")];
+        let places = Places {
+            locations: &[],
+            schedule: &schedule,
+        };
+        let decision = decide_action(&npc, &places, 12);
+        assert_eq!(
+            choice(&decision),
+            ("cottage", LocationType::Home, Activity::Eating)
+        );
+    }
+
+    #[test]
+    fn the_most_pressing_need_wins() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_wealth = 30;
+        npc.need_food = 40;
+
+        // Work 70 beats eating 48
+        let decision = decide(&npc, 12);
+        assert_eq!(
+            choice(&decision),
+            ("forge", LocationType::Workplace, Activity::Working)
+        );
+        assert_eq!(
+            decision.trace[1..],
+            [
+                "Could go working (priority 70): Need money (30%)",
+                "Could go eating (priority 48): Hungry (40%)",
+                "Decision: working at forge (Need money (30%))",
+            ]
+        );
+
+        npc.need_wealth = 50;
+        npc.need_social = 10;
+        let decision = decide(&npc, 12);
+        assert_eq!(
+            choice(&decision),
+            ("tavern", LocationType::Tavern, Activity::Socializing)
+        );
+        // Lonely but unsafe: company at home
+        npc.need_safety = SECURITY_LOW - 1;
+        let decision = decide(&npc, 12);
+        assert_eq!(
+            choice(&decision),
+            ("cottage", LocationType::Home, Activity::Socializing)
+        );
+    }
+
+    #[test]
+    fn workplaces_come_from_the_schedule_or_the_name() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_wealth = 10;
+        let mut locations = locations();
+        // The first place with her name in it
+        locations[0].name = "Cottage".to_string();
+        locations[1].name = "Ann's Forge".to_string();
+
+        let by_name = Places {
+            locations: &locations,
+            schedule: &[],
+        };
+        let decision = decide_action(&npc, &by_name, 12);
+        assert_eq!(decision.location, "forge");
+
+        let nowhere = Places {
+            locations: &[],
+            schedule: &[],
+        };
+        let decision = decide_action(&npc, &nowhere, 12);
+        assert_eq!(decision.activity, Activity::Idle);
+        assert_eq!(decision.trace[1], "Wealth is low but there is no workplace");
+    }
+}
@@ -0,0 +1,191 @@
+// Building and activity effects - port of src/building-effects.ts (and of
+// recoverNeeds in src/need-based-behavior.ts), read from data/building-effects.json
+//
+// Every hour an NPC gets the effect of what it is doing and of the kind of
+// place it is at, plus a bonus for company. Which kind of place a location is
+// comes from the NPC's schedule (home, workplace), the names listed in
+// `locationNames`, or what it offers (a tavern serves food and shelter).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::behavior::{Activity, LocationType};
+use crate::data;
+use crate::error::{AppError, AppResult};
+use crate::models::Npc;
+
+/// Set to use a building effects file other than data/building-effects.json
+const PATH_ENV: &str = "VIBEMASTER_BUILDING_EFFECTS";
+const RELATIVE_PATH: &str = "data/building-effects.json";
+const BUILT_IN: &str = include_str!("../../../data/building-effects.json");
+
+/// Change per hour; positive values are gains
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Effect {
+    pub need_food: i32,
+    pub need_safety: i32,
+    pub need_wealth: i32,
+    pub need_social: i32,
+    pub need_rest: i32,
+    pub emotion_happiness: i32,
+    pub emotion_fear: i32,
+}
+
+impl Effect {
+    fn values(&self) -> [(&'static str, i32); 7] {
+        [
+            ("needFood", self.need_food),
+            ("needSafety", self.need_safety),
+            ("needWealth", self.need_wealth),
+            ("needSocial", self.need_social),
+            ("needRest", self.need_rest),
+            ("emotionHappiness", self.emotion_happiness),
+            ("emotionFear", self.emotion_fear),
+        ]
+    }
+}
+
+/// Social bonus for other NPCs at the same place (not at work, where the
+/// workplace effect already counts coworkers)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Company {
+    pub social_per_npc: i32,
+    pub max_social: i32,
+    /// Extra happiness per NPC at home
+    pub home_happiness_per_npc: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BuildingEffects {
+    /// BUILDING_EFFECTS
+    pub locations: HashMap<LocationType, Effect>,
+    /// Locations whose name contains one of these are of that type
+    #[serde(default)]
+    pub location_names: BTreeMap<LocationType, Vec<String>>,
+    pub activities: HashMap<Activity, Effect>,
+    pub company: Company,
+}
+
+impl BuildingEffects {
+    /// The file from PATH_ENV or data/building-effects.json, else the copy
+    /// built into the binary
+    pub fn load() -> AppResult<Self> {
+        let path = data::locate(PATH_ENV, RELATIVE_PATH);
+        let effects: Self = data::load(path.as_deref(), BUILT_IN)?;
+        effects.validate(path.as_deref().unwrap_or(Path::new("(built in)")))?;
+        Ok(effects)
+    }
+
+    fn validate(&self, path: &Path) -> AppResult<()> {
+        let invalid = |reason: String| AppError::InvalidData {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let effects = self
+            .locations
+            .iter()
+            .map(|(kind, effect)| (kind.as_str(), effect))
+            .chain(
+                self.activities
+                    .iter()
+                    .map(|(activity, effect)| (activity.as_str(), effect)),
+            );
+        for (name, effect) in effects {
+            if let Some((stat, value)) = effect
+                .values()
+                .into_iter()
+                .find(|(_, value)| !(-100..=100).contains(value))
+            {
+                return Err(invalid(format!(
+                    "{}.{} is {}, must be between -100 and 100",
+                    name, stat, value
+                )));
+            }
+        }
+        if let Some(kind) = self
+            .location_names
+            .keys()
+            .find(|kind| matches!(kind, LocationType::Home | LocationType::Workplace))
+        {
+            return Err(invalid(format!(
+                "locationNames.{} is not allowed, {} comes from the schedule",
+                kind.as_str(),
+                kind.as_str()
+            )));
+        }
+        if self.company.social_per_npc < 0
+            || self.company.max_social < 0
+            || self.company.home_happiness_per_npc < 0
+        {
+            return Err(invalid("company bonuses must not be negative".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The type listed in `locationNames` whose names appear in `name`
+    pub fn named_type(&self, name: &str) -> Option<LocationType> {
+        self.location_names
+            .iter()
+            .find(|(_, names)| names.iter().any(|n| name.contains(n.as_str())))
+            .map(|(&kind, _)| kind)
+    }
+
+    /// recoverNeeds: the effect of an hour of `activity`
+    pub fn apply_activity(&self, npc: &mut Npc, activity: Activity) {
+        if let Some(effect) = self.activities.get(&activity) {
+            apply(npc, effect, 100);
+        }
+    }
+
+    /// applyBuildingEffects for an hour at a place of type `kind` with
+    /// `others` other NPCs there. Safety only recovers up to `safety_cap`,
+    /// what the threat map allows there.
+    pub fn apply_building(
+        &self,
+        npc: &mut Npc,
+        kind: LocationType,
+        others: usize,
+        safety_cap: i32,
+    ) {
+        if let Some(effect) = self.locations.get(&kind) {
+            apply(npc, effect, safety_cap);
+        }
+        if others == 0 {
+            return;
+        }
+
+        if kind != LocationType::Workplace {
+            let social = (others as i32 * self.company.social_per_npc).min(self.company.max_social);
+            npc.need_social = (npc.need_social + social).min(100);
+            npc.emotion_happiness = (npc.emotion_happiness + social / 2).min(100);
+        }
+        if kind == LocationType::Home {
+            let happiness = others as i32 * self.company.home_happiness_per_npc;
+            npc.emotion_happiness = (npc.emotion_happiness + happiness).min(100);
+        }
+    }
+}
+
+fn apply(npc: &mut Npc, effect: &Effect, safety_cap: i32) {
+    let add = |value: i32, change: i32| (value + change).clamp(0, 100);
+
+    npc.need_food = add(npc.need_food, effect.need_food);
+    npc.need_wealth = add(npc.need_wealth, effect.need_wealth);
+    npc.need_social = add(npc.need_social, effect.need_social);
+    npc.need_rest = add(npc.need_rest, effect.need_rest);
+    npc.emotion_happiness = add(npc.emotion_happiness, effect.emotion_happiness);
+    npc.emotion_fear = add(npc.emotion_fear, effect.emotion_fear);
+
+    // Recovery stops at what the threat map allows there
+    if effect.need_safety > 0 && npc.need_safety < safety_cap {
+        npc.need_safety = add(npc.need_safety, effect.need_safety).min(safety_cap);
+    } else if effect.need_safety < 0 {
+        npc.need_safety = add(npc.need_safety, effect.need_safety);
+    }
+}
@@ -0,0 +1,213 @@
+// Clearing threats - the player or a party of NPCs takes a threat off the map
+//
+// Brave NPCs in a safety crisis get a clear_threat goal (goals.rs). Every hour
+// the NPCs acting on such a goal rally against its threat; once the party
+// rallying at the same time is strong enough, the threat is cleared. The
+// player can clear a threat directly (the clear_threat command). Either way
+// the threat turns inactive, town safety is recalculated, and an Event plus
+// memories for the participants and the NPCs it threatened are recorded.
+
+use crate::db;
+use crate::models::{Event, Memory, Npc, ThreatSource};
+
+/// `by` of the clear_threat command when the player did it
+pub const PLAYER: &str = "player";
+
+/// What one NPC adds to a party; a party clears a threat whose severity is
+/// at most its strength
+const NPC_STRENGTH: i32 = 30;
+
+/// Memory strength of taking part / of being rid of the threat
+const CLEARED_IMPACT: i32 = 70;
+const RELIEF_IMPACT: i32 = 40;
+
+pub fn party_strength(party_size: usize) -> i32 {
+    party_size as i32 * NPC_STRENGTH
+}
+
+/// NPCs a party needs to clear the threat
+pub fn party_needed(threat: &ThreatSource) -> i32 {
+    (threat.severity + NPC_STRENGTH - 1) / NPC_STRENGTH
+}
+
+/// A threat that was just cleared and what to record about it
+#[derive(Debug, Clone)]
+pub struct Cleared {
+    /// Now inactive
+    pub threat: ThreatSource,
+    pub event: Event,
+    pub memories: Vec<Memory>,
+}
+
+/// Clear the threat. `party` is empty when the player cleared it; `relieved`
+/// are the other NPCs it threatened.
+pub fn clear(
+    threat: &ThreatSource,
+    party: &[&Npc],
+    relieved: &[&Npc],
+    (day, hour): (i32, i32),
+    location_id: &str,
+) -> Cleared {
+    let now = db::now_millis();
+    let mut threat = threat.clone();
+    threat.active = false;
+    threat.updated_at = now;
+
+    let by = if party.is_empty() {
+        "The player".to_string()
+    } else {
+        party
+            .iter()
+            .map(|npc| npc.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let participant_ids: Vec<String> = party.iter().map(|npc| npc.id.clone()).collect();
+
+    let event = Event {
+        id: db::new_id(),
+        world_id: threat.world_id.clone(),
+        day,
+        hour,
+        kind: "threat_cleared".to_string(),
+        description: format!("{} cleared {}", by, threat.name),
+        location_id: location_id.to_string(),
+        participant_ids: participant_ids.clone(),
+        target_id: None,
+        resolved: true,
+        consequences: Vec::new(),
+        dramatic_value: threat.severity.clamp(0, 100),
+        created_at: now,
+    };
+
+    let memory = |npc: &Npc, event: &str, emotion: &str, impact: i32| Memory {
+        id: db::new_id(),
+        npc_id: npc.id.clone(),
+        day,
+        event: event.to_string(),
+        emotion: emotion.to_string(),
+        emotional_impact: impact,
+        involved_npcs: participant_ids
+            .iter()
+            .filter(|id| **id != npc.id)
+            .cloned()
+            .collect(),
+        created_at: now,
+    };
+    let memories = party
+        .iter()
+        .map(|npc| memory(npc, "cleared_threat", "pride", CLEARED_IMPACT))
+        .chain(
+            relieved
+                .iter()
+                .map(|npc| memory(npc, "threat_cleared", "relief", RELIEF_IMPACT)),
+        )
+        .collect();
+
+    Cleared {
+        threat,
+        event,
+        memories,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ThreatMovement;
+    use crate::simulation::fixtures;
+
+    fn wolves(severity: i32) -> ThreatSource {
+        let mut threat = fixtures::threat(ThreatMovement::Stationary, &[[0.0, 0.0]], 0.0);
+        threat.severity = severity;
+        threat
+    }
+
+    #[test]
+    fn parties_grow_with_the_threats_severity() {
+        assert_eq!(party_strength(0), 0);
+        assert_eq!(party_strength(2), 2 * NPC_STRENGTH);
+
+        assert_eq!(party_needed(&wolves(30)), 1);
+        assert_eq!(party_needed(&wolves(50)), 2);
+        assert_eq!(party_needed(&wolves(60)), 2);
+        assert_eq!(party_needed(&wolves(61)), 3);
+        // A party of party_needed is always strong enough
+        for severity in 1..=100 {
+            let threat = wolves(severity);
+            assert!(party_strength(party_needed(&threat) as usize) >= threat.severity);
+            assert!(party_strength(party_needed(&threat) as usize - 1) < threat.severity);
+        }
+    }
+
+    #[test]
+    fn a_party_clears_the_threat_and_remembers_it() {
+        let threat = wolves(50);
+        let (ann, bob, cid) = (
+            fixtures::npc("Ann"),
+            fixtures::npc("Bob"),
+            fixtures::npc("Cid"),
+        );
+
+        let cleared = clear(&threat, &[&ann, &bob], &[&cid], (3, 14), "forest");
+        assert!(threat.active, "the original is left alone");
+        assert!(!cleared.threat.active);
+
+        let event = &cleared.event;
+        assert_eq!(event.kind, "threat_cleared");
+        assert_eq!(event.description, "Ann, Bob cleared Wolves");
+        assert_eq!((event.day, event.hour), (3, 14));
+        assert_eq!(event.location_id, "forest");
+        assert_eq!(event.participant_ids, ["ann", "bob"]);
+        assert_eq!(event.dramatic_value, 50);
+
+        let memories: Vec<(&str, &str, i32, Vec<String>)> = cleared
+            .memories
+            .iter()
+            .map(|m| {
+                (
+                    m.npc_id.as_str(),
+                    m.event.as_str(),
+                    m.emotional_impact,
+                    m.involved_npcs.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            memories,
+            [
+                (
+                    "ann",
+                    "cleared_threat",
+                    CLEARED_IMPACT,
+                    vec!["bob".to_string()]
+                ),
+                (
+                    "bob",
+                    "cleared_threat",
+                    CLEARED_IMPACT,
+                    vec!["ann".to_string()]
+                ),
+                (
+                    "cid",
+                    "threat_cleared",
+                    RELIEF_IMPACT,
+                    vec!["ann".to_string(), "bob".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_player_clears_alone() {
+        let cid = fixtures::npc("Cid");
+        let cleared = clear(&wolves(150), &[], &[&cid], (1, 8), "forest");
+
+        assert_eq!(cleared.event.description, "The player cleared Wolves");
+        assert!(cleared.event.participant_ids.is_empty());
+        assert_eq!(cleared.event.dramatic_value, 100);
+        assert_eq!(cleared.memories.len(), 1);
+        assert_eq!(cleared.memories[0].emotion, "relief");
+        assert!(cleared.memories[0].involved_npcs.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+// Crisis detection - port of detectCrisis in src/crisis-system.ts
+//
+// An NPC is in at most one crisis at a time, the first that applies in the
+// order below. The engine runs detection after every tick and records an
+// Event whenever an NPC enters or leaves a crisis (or swaps one for another).
+
+use serde::Serialize;
+
+use crate::db;
+use crate::models::{Event, Npc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrisisType {
+    Starvation,
+    Danger,
+    Poverty,
+    Illness,
+    EmotionalBreakdown,
+    Desperation,
+}
+
+impl CrisisType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CrisisType::Starvation => "starvation",
+            CrisisType::Danger => "danger",
+            CrisisType::Poverty => "poverty",
+            CrisisType::Illness => "illness",
+            CrisisType::EmotionalBreakdown => "emotional_breakdown",
+            CrisisType::Desperation => "desperation",
+        }
+    }
+
+    /// "Marcus is starving"
+    fn describe(self, name: &str) -> String {
+        match self {
+            CrisisType::Starvation => format!("{} is starving", name),
+            CrisisType::Danger => format!("{} is in danger", name),
+            CrisisType::Poverty => format!("{} has lost everything", name),
+            CrisisType::Illness => format!("{} is hurt and needs care", name),
+            CrisisType::EmotionalBreakdown => format!("{} is breaking down", name),
+            CrisisType::Desperation => format!("{} is desperate", name),
+        }
+    }
+}
+
+/// detectCrisis never reports "low"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrisisSeverity {
+    Medium,
+    High,
+    Extreme,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Crisis {
+    pub npc_id: String,
+    pub npc_name: String,
+    #[serde(rename = "type")]
+    pub kind: CrisisType,
+    pub severity: CrisisSeverity,
+    pub affects_dialogue: bool,
+    /// 0-100
+    pub urgency: i32,
+}
+
+/// Thresholds from detectCrisis
+const STARVING: i32 = 20;
+const IN_DANGER: i32 = 20;
+const EXTREME: i32 = 10;
+const BROKE: i32 = 10;
+const HEARTBROKEN: i32 = 15;
+const DESPERATE: f64 = 25.0;
+const EXTREMELY_DESPERATE: f64 = 15.0;
+/// No health need exists; an injured NPC counts as ill
+const ILLNESS_URGENCY: i32 = 80;
+
+/// detectCrisis: the NPC's crisis, if it is in one
+pub fn detect(npc: &Npc) -> Option<Crisis> {
+    use CrisisSeverity::*;
+    use CrisisType::*;
+
+    let severe = |need: i32| if need < EXTREME { Extreme } else { High };
+    let avg_need = (npc.need_food + npc.need_safety + npc.need_wealth) as f64 / 3.0;
+
+    let (kind, severity, urgency) = if npc.need_food < STARVING {
+        (Starvation, severe(npc.need_food), 100 - npc.need_food)
+    } else if npc.need_safety < IN_DANGER {
+        (Danger, severe(npc.need_safety), 100 - npc.need_safety)
+    } else if npc.need_wealth < BROKE {
+        (Poverty, Medium, 50)
+    } else if npc.emotion_happiness < HEARTBROKEN {
+        (EmotionalBreakdown, High, 85 - npc.emotion_happiness)
+    } else if avg_need < DESPERATE {
+        let severity = if avg_need < EXTREMELY_DESPERATE {
+            Extreme
+        } else {
+            High
+        };
+        (Desperation, severity, (100.0 - avg_need).round() as i32)
+    } else if npc.state == "injured" {
+        // Not in detectCrisis, so it comes after everything that is
+        (Illness, High, ILLNESS_URGENCY)
+    } else {
+        return None;
+    };
+
+    Some(Crisis {
+        npc_id: npc.id.clone(),
+        npc_name: npc.name.clone(),
+        kind,
+        severity,
+        affects_dialogue: true,
+        urgency: urgency.clamp(0, 100),
+    })
+}
+
+/// Event for an NPC entering (`active`) or leaving a crisis. Entering is as
+/// dramatic as the crisis is urgent; coming out of it half as much.
+pub fn crisis_event(npc: &Npc, crisis: &Crisis, active: bool, (day, hour): (i32, i32)) -> Event {
+    let (kind, description, dramatic_value) = if active {
+        ("crisis", crisis.kind.describe(&npc.name), crisis.urgency)
+    } else {
+        (
+            "crisis_over",
+            format!(
+                "{} is no longer in crisis ({})",
+                npc.name,
+                crisis.kind.as_str()
+            ),
+            crisis.urgency / 2,
+        )
+    };
+
+    Event {
+        id: db::new_id(),
+        world_id: npc.world_id.clone(),
+        day,
+        hour,
+        kind: kind.to_string(),
+        description,
+        location_id: npc.location_id.clone(),
+        participant_ids: vec![npc.id.clone()],
+        target_id: None,
+        resolved: !active,
+        consequences: Vec::new(),
+        dramatic_value,
+        created_at: db::now_millis(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    fn detected(npc: &Npc) -> Option<(CrisisType, CrisisSeverity, i32)> {
+        detect(npc).map(|crisis| (crisis.kind, crisis.severity, crisis.urgency))
+    }
+
+    #[test]
+    fn content_npcs_are_not_in_crisis() {
+        assert_eq!(detect(&fixtures::npc("Ann")), None);
+    }
+
+    #[test]
+    fn starvation_below_twenty_food() {
+        use CrisisSeverity::*;
+        let mut npc = fixtures::npc("Ann");
+
+        npc.need_food = 20;
+        assert_eq!(detected(&npc), None);
+        npc.need_food = 19;
+        assert_eq!(detected(&npc), Some((CrisisType::Starvation, High, 81)));
+        npc.need_food = 10;
+        assert_eq!(detected(&npc), Some((CrisisType::Starvation, High, 90)));
+        npc.need_food = 9;
+        assert_eq!(detected(&npc), Some((CrisisType::Starvation, Extreme, 91)));
+    }
+
+    #[test]
+    fn danger_below_twenty_safety() {
+        let mut npc = fixtures::npc("Ann");
+
+        npc.need_safety = 20;
+        assert_eq!(detected(&npc), None);
+        npc.need_safety = 5;
+        assert_eq!(
+            detected(&npc),
+            Some((CrisisType::Danger, CrisisSeverity::Extreme, 95))
+        );
+    }
+
+    #[test]
+    fn first_crisis_in_order_wins() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_wealth = 0;
+        npc.emotion_happiness = 0;
+        npc.state = "injured".to_string();
+        npc.need_safety = 15;
+        npc.need_food = 15;
+
+        let mut kinds = Vec::new();
+        while let Some(crisis) = detect(&npc) {
+            kinds.push(crisis.kind);
+            // Just past each threshold, so the needs stay desperate together
+            match crisis.kind {
+                CrisisType::Starvation => npc.need_food = STARVING,
+                CrisisType::Danger => npc.need_safety = IN_DANGER,
+                CrisisType::Poverty => npc.need_wealth = BROKE,
+                CrisisType::EmotionalBreakdown => npc.emotion_happiness = 60,
+                CrisisType::Desperation => {
+                    (npc.need_food, npc.need_safety, npc.need_wealth) = (80, 80, 50)
+                }
+                CrisisType::Illness => npc.state = "alive".to_string(),
+            }
+        }
+        assert_eq!(
+            kinds,
+            [
+                CrisisType::Starvation,
+                CrisisType::Danger,
+                CrisisType::Poverty,
+                CrisisType::EmotionalBreakdown,
+                CrisisType::Desperation,
+                CrisisType::Illness,
+            ]
+        );
+    }
+
+    #[test]
+    fn other_thresholds() {
+        use CrisisSeverity::*;
+        let mut npc = fixtures::npc("Ann");
+
+        npc.state = "injured".to_string();
+        assert_eq!(detected(&npc), Some((CrisisType::Illness, High, 80)));
+        npc.state = "alive".to_string();
+
+        npc.need_wealth = 9;
+        assert_eq!(detected(&npc), Some((CrisisType::Poverty, Medium, 50)));
+        npc.need_wealth = 10;
+        assert_eq!(detected(&npc), None);
+
+        npc.emotion_happiness = 14;
+        assert_eq!(
+            detected(&npc),
+            Some((CrisisType::EmotionalBreakdown, High, 71))
+        );
+        npc.emotion_happiness = 15;
+        assert_eq!(detected(&npc), None);
+    }
+
+    #[test]
+    fn desperation_when_needs_average_below_twenty_five() {
+        let mut npc = fixtures::npc("Ann");
+        (npc.need_food, npc.need_safety, npc.need_wealth) = (20, 20, 30);
+        assert_eq!(
+            detected(&npc),
+            Some((CrisisType::Desperation, CrisisSeverity::High, 77))
+        );
+
+        npc.need_wealth = 35;
+        assert_eq!(detected(&npc), None);
+    }
+}
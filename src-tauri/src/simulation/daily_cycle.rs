@@ -0,0 +1,228 @@
+// Four daily checkpoints - port of DailyCycleSystem in src/daily-cycle-system.ts
+//
+// NPCs re-decide where to be and what to do at Dawn, Midday, Evening and
+// Night (behavior.rs makes the decision). Between checkpoints only NPCs that
+// finished eating or are idle look for something else to do. Need decay stays
+// in the hourly tick (needs.rs), so a checkpoint does not decay needs again.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::behavior::{decide_action, Activity, NpcDecision, Places, SECURITY_CRITICAL};
+use super::events::{CheckpointReached, NpcMessage, NpcMoved};
+use crate::models::{Location, Npc, Schedule};
+
+/// (hour, name) of the 4 checkpoints per day
+pub const CHECKPOINTS: [(i32, &str); 4] =
+    [(6, "Dawn"), (12, "Midday"), (18, "Evening"), (22, "Night")];
+
+/// An eating NPC re-decides once food is above this
+const FED: i32 = 70;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextCheckpoint {
+    pub hour: i32,
+    pub name: &'static str,
+    pub hours_until: i32,
+}
+
+pub fn checkpoint_at(hour: i32) -> Option<&'static str> {
+    CHECKPOINTS
+        .iter()
+        .find(|(h, _)| *h == hour)
+        .map(|(_, name)| *name)
+}
+
+pub fn next_checkpoint(current_hour: i32) -> NextCheckpoint {
+    let (hour, name, hours_until) = CHECKPOINTS
+        .iter()
+        .find(|(hour, _)| *hour > current_hour)
+        .map(|&(hour, name)| (hour, name, hour - current_hour))
+        // Next checkpoint is tomorrow's first checkpoint
+        .unwrap_or((
+            CHECKPOINTS[0].0,
+            CHECKPOINTS[0].1,
+            24 - current_hour + CHECKPOINTS[0].0,
+        ));
+
+    NextCheckpoint {
+        hour,
+        name,
+        hours_until,
+    }
+}
+
+/// What one hour of the cycle produced, for the tick's diff
+#[derive(Debug, Default)]
+pub struct CycleOutput {
+    pub checkpoint: Option<CheckpointReached>,
+    pub messages: Vec<NpcMessage>,
+    pub movements: Vec<NpcMoved>,
+}
+
+/// Per-hour state shared by checkpoints and the hourly evaluation
+pub struct DailyCycle<'a> {
+    pub day: i32,
+    pub hour: i32,
+    /// What each NPC is currently doing, by id
+    pub activities: &'a mut HashMap<String, Activity>,
+    pub schedules: &'a HashMap<String, Vec<Schedule>>,
+    pub locations: &'a [Location],
+}
+
+impl DailyCycle<'_> {
+    /// onHourChange: a full checkpoint at 6, 12, 18 and 22, otherwise a
+    /// lighter check that only moves NPCs who are done eating or idle
+    pub fn on_hour_change(&mut self, npcs: &mut [Npc]) -> CycleOutput {
+        let mut output = CycleOutput::default();
+
+        if let Some(name) = checkpoint_at(self.hour) {
+            output.checkpoint = Some(self.run_checkpoint(name, npcs, &mut output));
+        } else {
+            self.run_hourly_evaluation(npcs, &mut output);
+        }
+        output
+    }
+
+    fn places<'p>(&'p self, npc: &Npc) -> Places<'p> {
+        Places {
+            locations: self.locations,
+            schedule: self
+                .schedules
+                .get(&npc.id)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+        }
+    }
+
+    fn location_name(&self, id: &str) -> String {
+        self.locations
+            .iter()
+            .find(|l| l.id == id)
+            .map(|l| l.name.clone())
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// runCheckpoint: security check, then decide and move every living NPC
+    fn run_checkpoint(
+        &mut self,
+        name: &'static str,
+        npcs: &mut [Npc],
+        output: &mut CycleOutput,
+    ) -> CheckpointReached {
+        let fleeing: Vec<String> = npcs
+            .iter()
+            .filter(|npc| npc.state != "dead" && npc.need_safety < SECURITY_CRITICAL)
+            .map(|npc| npc.id.clone())
+            .collect();
+
+        for npc in npcs.iter_mut().filter(|npc| npc.state != "dead") {
+            let decision = decide_action(npc, &self.places(npc), self.hour);
+            self.apply(npc, decision, output);
+        }
+
+        CheckpointReached {
+            day: self.day,
+            hour: self.hour,
+            name,
+            fleeing,
+        }
+    }
+
+    /// runHourlyEvaluation: eating only lasts until the NPC is fed, and idle
+    /// NPCs may find something to do between checkpoints
+    fn run_hourly_evaluation(&mut self, npcs: &mut [Npc], output: &mut CycleOutput) {
+        for npc in npcs.iter_mut().filter(|npc| npc.state != "dead") {
+            let activity = self.activities.get(&npc.id).copied().unwrap_or_default();
+            let done_eating = activity == Activity::Eating && npc.need_food > FED;
+            if !done_eating && activity != Activity::Idle {
+                continue;
+            }
+
+            let decision = decide_action(npc, &self.places(npc), self.hour);
+            if decision.activity != activity {
+                self.apply(npc, decision, output);
+            }
+        }
+    }
+
+    /// Move the NPC if needed and record its new activity
+    fn apply(&mut self, npc: &mut Npc, decision: NpcDecision, output: &mut CycleOutput) {
+        let old_activity = self.activities.get(&npc.id).copied().unwrap_or_default();
+        let mut texts = Vec::new();
+
+        if decision.location != npc.location_id {
+            let to_name = self.location_name(&decision.location);
+            texts.push(format!("Moved to {}", to_name));
+            output.movements.push(NpcMoved {
+                npc_id: npc.id.clone(),
+                npc_name: npc.name.clone(),
+                from_location_id: npc.location_id.clone(),
+                to_location_id: decision.location.clone(),
+                activity: decision.activity,
+            });
+        }
+        if old_activity != decision.activity {
+            texts.push(format!("Started {}", decision.activity.as_str()));
+        }
+
+        output
+            .messages
+            .extend(texts.into_iter().map(|message| NpcMessage {
+                npc_id: npc.id.clone(),
+                npc_name: npc.name.clone(),
+                day: self.day,
+                hour: self.hour,
+                message,
+            }));
+        npc.location_id = decision.location;
+        self.activities.insert(npc.id.clone(), decision.activity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_are_dawn_midday_evening_and_night() {
+        let found: Vec<(i32, &str)> = (0..24)
+            .filter_map(|hour| checkpoint_at(hour).map(|name| (hour, name)))
+            .collect();
+        assert_eq!(
+            found,
+            [(6, "Dawn"), (12, "Midday"), (18, "Evening"), (22, "Night")]
+        );
+    }
+
+    #[test]
+    fn next_checkpoint_is_later_the_same_day() {
+        let next = |hour| {
+            let next = next_checkpoint(hour);
+            (next.hour, next.name, next.hours_until)
+        };
+        assert_eq!(next(0), (6, "Dawn", 6));
+        assert_eq!(next(5), (6, "Dawn", 1));
+        // At a checkpoint the next one is already the following
+        assert_eq!(next(6), (12, "Midday", 6));
+        assert_eq!(next(12), (18, "Evening", 6));
+        assert_eq!(next(17), (18, "Evening", 1));
+        assert_eq!(next(18), (22, "Night", 4));
+    }
+
+    #[test]
+    fn next_checkpoint_after_night_is_tomorrows_dawn() {
+        assert_eq!(
+            next_checkpoint(22),
+            NextCheckpoint {
+                hour: 6,
+                name: "Dawn",
+                hours_until: 8,
+            }
+        );
+        assert_eq!(next_checkpoint(23).hours_until, 7);
+        assert_eq!(next_checkpoint(23).hour, 6);
+    }
+}
@@ -0,0 +1,187 @@
+// Tauri events pushed to the frontend after every tick
+//
+// Listen with `listen("world://tick", ...)` from @tauri-apps/api/event.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use super::behavior::Activity;
+use super::crisis::Crisis;
+use crate::models::{
+    Event, Goal, Location, LocationCrisis, Npc, ProductionTask, ThreatSource, Town,
+};
+
+/// Every tick, payload: `WorldDiff`
+pub const TICK: &str = "world://tick";
+/// NPCs that changed this tick, payload: `Vec<Npc>`
+pub const NPC_CHANGED: &str = "world://npc-changed";
+/// One per event created this tick, payload: `Event`
+pub const EVENT_CREATED: &str = "world://event-created";
+
+/// At each of the 4 daily checkpoints, payload: `CheckpointReached`
+pub const CHECKPOINT: &str = "world://checkpoint";
+/// Log line for an NPC's message panel, payload: `NpcMessage`
+pub const NPC_MESSAGE: &str = "world://npc-message";
+/// An NPC walked to another location, payload: `NpcMoved`
+pub const NPC_MOVED: &str = "world://npc-moved";
+/// A mobile threat moved on the map, payload: `ThreatMoved`
+pub const THREAT_MOVED: &str = "world://threat-moved";
+/// An NPC entered or left a crisis, payload: `CrisisChanged`
+pub const CRISIS: &str = "world://crisis";
+/// A fire, flood or famine started, changed or was resolved, payload: `LocationCrisis`
+pub const LOCATION_CRISIS: &str = "world://location-crisis";
+
+/// Sent once per in-game day during fast_forward_to, payload: `FastForwardProgress`
+pub const FAST_FORWARD_PROGRESS: &str = "world://fast-forward-progress";
+
+/// What changed in a single tick
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldDiff {
+    pub tick: u64,
+    pub day: i32,
+    pub hour: i32,
+    /// Full rows of the NPCs whose fields changed
+    pub npcs: Vec<Npc>,
+    /// Goals created, changed, completed or failed
+    pub goals: Vec<Goal>,
+    pub events: Vec<Event>,
+    pub checkpoints: Vec<CheckpointReached>,
+    pub messages: Vec<NpcMessage>,
+    pub movements: Vec<NpcMoved>,
+    /// Every hop of every mobile threat
+    pub threat_moves: Vec<ThreatMoved>,
+    /// Threats cleared by the player or a party of NPCs
+    pub cleared_threats: Vec<ThreatSource>,
+    /// Production tasks started or finished
+    pub production: Vec<ProductionTask>,
+    /// Towns whose safety was recalculated
+    pub towns: Vec<Town>,
+    /// NPCs that entered or left a crisis
+    pub crises: Vec<CrisisChanged>,
+    /// Fires, floods and famines that started, changed or were resolved
+    pub location_crises: Vec<LocationCrisis>,
+    /// Locations whose food, shelter or danger a crisis changed
+    pub locations: Vec<Location>,
+}
+
+impl WorldDiff {
+    /// Diff two NPC snapshots taken before and after a tick
+    pub fn changed_npcs(before: &[Npc], after: &[Npc]) -> Vec<Npc> {
+        let before: HashMap<&str, &Npc> = before.iter().map(|npc| (npc.id.as_str(), npc)).collect();
+        after
+            .iter()
+            .filter(|npc| before.get(npc.id.as_str()) != Some(npc))
+            .cloned()
+            .collect()
+    }
+
+    /// Keep only the latest version of each row, in first-seen order
+    pub fn latest_by_id<T>(changed: Vec<T>, id: impl Fn(&T) -> &str) -> Vec<T> {
+        let mut latest: Vec<T> = Vec::new();
+        for item in changed {
+            match latest.iter_mut().find(|seen| id(seen) == id(&item)) {
+                Some(existing) => *existing = item,
+                None => latest.push(item),
+            }
+        }
+        latest
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointReached {
+    pub day: i32,
+    pub hour: i32,
+    /// Dawn, Midday, Evening or Night
+    pub name: &'static str,
+    /// NPCs whose safety is critical
+    pub fleeing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcMessage {
+    pub npc_id: String,
+    pub npc_name: String,
+    pub day: i32,
+    pub hour: i32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcMoved {
+    pub npc_id: String,
+    pub npc_name: String,
+    pub from_location_id: String,
+    pub to_location_id: String,
+    /// What the NPC is going there to do
+    pub activity: Activity,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreatMoved {
+    /// As it is now
+    pub threat: ThreatSource,
+    pub from_x: f64,
+    pub from_y: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrisisChanged {
+    /// The crisis entered, or the one just left
+    #[serde(flatten)]
+    pub crisis: Crisis,
+    pub active: bool,
+    /// The Event recording the change
+    pub event_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FastForwardProgress {
+    pub day: i32,
+    pub hour: i32,
+    pub ticks_done: u64,
+    pub ticks_total: u64,
+}
+
+pub fn emit_diff<R: Runtime>(app: &AppHandle<R>, diff: &WorldDiff) {
+    if let Err(e) = app.emit(TICK, diff) {
+        eprintln!("Failed to emit {}: {}", TICK, e);
+    }
+
+    if !diff.npcs.is_empty() {
+        if let Err(e) = app.emit(NPC_CHANGED, &diff.npcs) {
+            eprintln!("Failed to emit {}: {}", NPC_CHANGED, e);
+        }
+    }
+
+    emit_each(app, EVENT_CREATED, &diff.events);
+    emit_each(app, CHECKPOINT, &diff.checkpoints);
+    emit_each(app, NPC_MESSAGE, &diff.messages);
+    emit_each(app, NPC_MOVED, &diff.movements);
+    emit_each(app, THREAT_MOVED, &diff.threat_moves);
+    emit_each(app, CRISIS, &diff.crises);
+    emit_each(app, LOCATION_CRISIS, &diff.location_crises);
+}
+
+fn emit_each<R: Runtime, T: Serialize>(app: &AppHandle<R>, event: &str, payloads: &[T]) {
+    for payload in payloads {
+        if let Err(e) = app.emit(event, payload) {
+            eprintln!("Failed to emit {}: {}", event, e);
+        }
+    }
+}
+
+pub fn emit_progress<R: Runtime>(app: &AppHandle<R>, progress: FastForwardProgress) {
+    if let Err(e) = app.emit(FAST_FORWARD_PROGRESS, progress) {
+        eprintln!("Failed to emit {}: {}", FAST_FORWARD_PROGRESS, e);
+    }
+}
@@ -0,0 +1,93 @@
+// Rows for unit tests, with everything not under test at a neutral value
+
+use crate::models::{Location, Npc, ThreatMovement, ThreatSource, ThreatType};
+
+pub const WORLD: &str = "world";
+
+/// A content adult with no crisis
+pub fn npc(name: &str) -> Npc {
+    Npc {
+        id: name.to_lowercase(),
+        world_id: WORLD.to_string(),
+        name: name.to_string(),
+        age: 30,
+        occupation: "farmer".to_string(),
+        state: "alive".to_string(),
+        location_id: "home".to_string(),
+        openness: 50,
+        conscientiousness: 50,
+        extraversion: 50,
+        agreeableness: 50,
+        neuroticism: 50,
+        values: Vec::new(),
+        fears: Vec::new(),
+        formality: 50,
+        verbosity: 50,
+        emotional_expression: 50,
+        dialect: String::new(),
+        speech_quirks: Vec::new(),
+        need_food: 80,
+        need_safety: 80,
+        need_wealth: 50,
+        need_social: 60,
+        need_purpose: 60,
+        need_rest: 80,
+        emotion_happiness: 60,
+        emotion_anger: 10,
+        emotion_fear: 10,
+        emotion_sadness: 10,
+        emotion_trust: 50,
+        emotion_anticipation: 50,
+        emotion_love: 0,
+        emotion_desperation: 0,
+        emotion_grief: 0,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+/// A safe public building at (x, y)
+pub fn location(id: &str, x: f64, y: f64) -> Location {
+    Location {
+        id: id.to_string(),
+        world_id: WORLD.to_string(),
+        name: id.to_string(),
+        description: String::new(),
+        kind: "building".to_string(),
+        has_food: true,
+        has_shelter: true,
+        is_public: true,
+        is_dangerous: false,
+        danger_level: 0,
+        x: Some(x),
+        y: Some(y),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+/// An active threat at the start of its route, moving `speed` units an hour
+pub fn threat(movement: ThreatMovement, route: &[[f64; 2]], speed: f64) -> ThreatSource {
+    let [x, y] = route.first().copied().unwrap_or_default();
+    ThreatSource {
+        id: "threat".to_string(),
+        world_id: WORLD.to_string(),
+        key: "threat".to_string(),
+        kind: ThreatType::WolfDen,
+        name: "Wolves".to_string(),
+        description: String::new(),
+        x,
+        y,
+        radius: 50.0,
+        severity: 50,
+        active: true,
+        mobile: true,
+        movement,
+        route: route.to_vec(),
+        route_index: 0,
+        speed,
+        wander_radius: 0.0,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
@@ -0,0 +1,493 @@
+// Goal evaluation and action planning - port of NPCAgent.evaluateGoals,
+// planAction and executeAction in src/simulation/NPC.ts
+//
+// Goals live in memory per NPC; the engine writes new and changed goals back
+// after each tick and drops finished ones.
+
+use std::cmp::Reverse;
+
+use super::rng::Rng;
+use crate::db;
+use crate::models::{Action, Goal, Location, Memory, Npc};
+
+// Goal types the planner knows how to act on
+pub const SURVIVAL: &str = "survival";
+pub const ESCAPE: &str = "escape";
+pub const RESCUE: &str = "rescue";
+/// Organise to clear the threat `target` (a ThreatSource id)
+pub const CLEAR_THREAT: &str = "clear_threat";
+
+// Action types
+const SEEK_FOOD: &str = "seek_food";
+const SEEK_SHELTER: &str = "seek_shelter";
+const SEARCH: &str = "search";
+const MOVE: &str = "move";
+/// Join the party against a threat; see clearing.rs
+pub const RALLY: &str = "rally";
+
+/// Needs at which crisis goals are created / become desperate
+const HUNGRY: i32 = 20;
+const STARVING: i32 = 10;
+const UNSAFE: i32 = 30;
+const TERRIFIED: i32 = 15;
+
+/// Needs at which crisis goals count as achieved
+const FED: i32 = 80;
+const SAFE: i32 = 70;
+
+/// NPCs less neurotic than this stand up to threats instead of only hiding
+const BRAVE: i32 = 50;
+/// Days a party has to gather before a clear_threat goal fails
+const CLEARING_DAYS: i32 = 3;
+
+/// Goals become urgent this many days before their deadline
+const DEADLINE_WARNING_DAYS: i32 = 2;
+
+/// Working on a plain-text plan step gives a sense of purpose
+const TASK_PURPOSE_GAIN: i32 = 5;
+
+pub fn is_open(goal: &Goal) -> bool {
+    !goal.completed && !goal.failed
+}
+
+fn has_open(goals: &[Goal], kind: &str) -> bool {
+    goals.iter().any(|goal| is_open(goal) && goal.kind == kind)
+}
+
+fn new_goal(npc_id: &str, kind: &str, priority: i32, desperate: bool) -> Goal {
+    let now = db::now_millis();
+    Goal {
+        id: db::new_id(),
+        npc_id: npc_id.to_string(),
+        kind: kind.to_string(),
+        target: String::new(),
+        priority,
+        urgent: true,
+        desperate,
+        deadline: None,
+        completed: false,
+        failed: false,
+        plan: Vec::new(),
+        obstacles: Vec::new(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn new_memory(npc: &Npc, day: i32, event: &str, emotion: &str, impact: i32) -> Memory {
+    Memory {
+        id: db::new_id(),
+        npc_id: npc.id.clone(),
+        day,
+        event: event.to_string(),
+        emotion: emotion.to_string(),
+        emotional_impact: impact,
+        involved_npcs: Vec::new(),
+        created_at: db::now_millis(),
+    }
+}
+
+/// Create crisis goals from needs, then update deadlines, completion and
+/// priorities of the NPC's open goals
+pub fn evaluate_goals(npc: &Npc, goals: &mut Vec<Goal>, day: i32) {
+    // FOOD CRISIS
+    if npc.need_food < HUNGRY && !has_open(goals, SURVIVAL) {
+        goals.push(new_goal(&npc.id, SURVIVAL, 100, npc.need_food < STARVING));
+    }
+
+    // SAFETY CRISIS
+    if npc.need_safety < UNSAFE && !has_open(goals, ESCAPE) {
+        goals.push(new_goal(&npc.id, ESCAPE, 90, npc.need_safety < TERRIFIED));
+    }
+
+    for goal in goals.iter_mut().filter(|goal| is_open(goal)) {
+        // Deadlines: urgent when close, desperate on the last day, failed after
+        if let Some(deadline) = goal.deadline {
+            let days_left = deadline - day;
+            if days_left < 0 {
+                goal.failed = true;
+                continue;
+            }
+            if days_left <= DEADLINE_WARNING_DAYS {
+                goal.urgent = true;
+            }
+            if days_left == 0 {
+                goal.desperate = true;
+            }
+        }
+
+        // Crisis goals follow the need that created them
+        match goal.kind.as_str() {
+            SURVIVAL => {
+                goal.desperate = npc.need_food < STARVING;
+                goal.completed = npc.need_food >= FED;
+            }
+            ESCAPE => {
+                goal.desperate = npc.need_safety < TERRIFIED;
+                goal.completed = npc.need_safety >= SAFE;
+            }
+            _ => {}
+        }
+
+        // Increase priority if urgent but not yet desperate
+        if goal.urgent && !goal.desperate {
+            goal.priority = (goal.priority + 10).min(100);
+        }
+
+        // Decrease priority if needs are met
+        if goal.kind == SURVIVAL && npc.need_food > 50 {
+            goal.priority = (goal.priority - 20).max(0);
+        }
+    }
+}
+
+/// A brave NPC in a safety crisis organises to clear the threat behind it.
+/// The goal goes first so that, on equal footing, it is acted on before the
+/// escape goal; a terrified NPC still runs.
+pub fn consider_clearing(npc: &Npc, goals: &mut Vec<Goal>, threat_id: &str, day: i32) {
+    if !has_open(goals, ESCAPE)
+        || has_open(goals, CLEAR_THREAT)
+        || npc.need_safety < TERRIFIED
+        || npc.neuroticism >= BRAVE
+    {
+        return;
+    }
+
+    let mut goal = new_goal(&npc.id, CLEAR_THREAT, 95, false);
+    goal.target = threat_id.to_string();
+    goal.deadline = Some(day + CLEARING_DAYS);
+    goals.insert(0, goal);
+}
+
+/// planAction for a single goal: the next plan step if there is one,
+/// otherwise a default action for the goal type
+fn plan_action(npc: &Npc, goal: &Goal) -> Option<Action> {
+    if let Some(step) = goal.plan.first() {
+        return Some(step.clone());
+    }
+
+    let action = |kind: &str, target: Option<String>, duration: i32| Action {
+        kind: kind.to_string(),
+        target,
+        location: None,
+        duration: Some(duration),
+        description: None,
+    };
+
+    match goal.kind.as_str() {
+        SURVIVAL if npc.need_food < 50 => Some(action(SEEK_FOOD, None, 1)),
+        ESCAPE => Some(action(SEEK_SHELTER, None, 1)),
+        RESCUE => Some(action(SEARCH, Some(goal.target.clone()), 2)),
+        CLEAR_THREAT => Some(action(RALLY, Some(goal.target.clone()), 1)),
+        _ => None,
+    }
+}
+
+/// Pick this tick's action: the most pressing open goal (desperate, then
+/// urgent, then priority) that yields one. Goals with obstacles are skipped
+/// unless the NPC is desperate about them.
+pub fn choose_action(npc: &Npc, goals: &[Goal]) -> Option<(usize, Action)> {
+    let mut order: Vec<usize> = (0..goals.len()).filter(|&i| is_open(&goals[i])).collect();
+    order.sort_by_key(|&i| {
+        let goal = &goals[i];
+        Reverse((goal.desperate, goal.urgent, goal.priority))
+    });
+
+    order.into_iter().find_map(|i| {
+        let goal = &goals[i];
+        if !goal.obstacles.is_empty() && !goal.desperate {
+            return None;
+        }
+        plan_action(npc, goal).map(|action| (i, action))
+    })
+}
+
+/// What carrying out an action left for the engine to record
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// A memory worth keeping
+    pub memory: Option<Memory>,
+    /// Where a move action is taking the NPC; the engine moves it on the map
+    pub move_to: Option<String>,
+}
+
+/// executeAction. Plan steps are consumed as they are carried out and the
+/// goal completes with its last step.
+pub fn execute_action(
+    npc: &mut Npc,
+    goal: &mut Goal,
+    action: &Action,
+    day: i32,
+    locations: &[Location],
+    rng: &mut Rng,
+) -> Outcome {
+    let from_plan = goal.plan.first() == Some(action);
+    let mut memory = None;
+    let mut move_to = None;
+
+    match action.kind.as_str() {
+        SEEK_FOOD => {
+            let gain = 30.0 + rng.next_f64() * 20.0;
+            npc.need_food = (npc.need_food + gain as i32).min(100);
+            memory = Some(new_memory(npc, day, "found_food", "relief", 40));
+        }
+        SEEK_SHELTER => {
+            npc.need_safety = (npc.need_safety + 50).min(100);
+        }
+        // Searching has no effect of its own yet; the engine gathers everyone
+        // rallying this hour into parties
+        SEARCH | RALLY => {}
+        MOVE => {
+            let wanted = action.location.as_deref().or(action.target.as_deref());
+            if let Some(location) = locations
+                .iter()
+                .find(|l| Some(l.id.as_str()) == wanted || Some(l.name.as_str()) == wanted)
+            {
+                move_to = Some(location.id.clone());
+            }
+        }
+        _ => {
+            // Free-text plan steps ("Save money") and unknown action types
+            npc.need_purpose = (npc.need_purpose + TASK_PURPOSE_GAIN).min(100);
+        }
+    }
+
+    if from_plan {
+        goal.plan.remove(0);
+        if goal.plan.is_empty() {
+            goal.completed = true;
+            memory = Some(new_memory(
+                npc,
+                day,
+                &format!("completed_{}_goal", goal.kind),
+                "pride",
+                60,
+            ));
+        }
+    }
+
+    Outcome { memory, move_to }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    fn step(kind: &str) -> Action {
+        Action {
+            kind: kind.to_string(),
+            target: None,
+            location: None,
+            duration: None,
+            description: None,
+        }
+    }
+
+    /// A goal nobody is worried about yet
+    fn goal(kind: &str, priority: i32) -> Goal {
+        let mut goal = new_goal("ann", kind, priority, false);
+        goal.urgent = false;
+        goal
+    }
+
+    #[test]
+    fn crisis_goals_start_below_the_need_thresholds() {
+        let mut npc = fixtures::npc("Ann");
+        let mut goals = Vec::new();
+        evaluate_goals(&npc, &mut goals, 1);
+        assert!(goals.is_empty());
+
+        npc.need_food = HUNGRY - 1;
+        npc.need_safety = UNSAFE - 1;
+        evaluate_goals(&npc, &mut goals, 1);
+        let kinds: Vec<(&str, bool)> = goals
+            .iter()
+            .map(|goal| (goal.kind.as_str(), goal.desperate))
+            .collect();
+        assert_eq!(kinds, [(SURVIVAL, false), (ESCAPE, false)]);
+        // Urgent, not desperate: escape goes up from 90, survival stays capped
+        assert_eq!(goals[0].priority, 100);
+        assert_eq!(goals[1].priority, 100);
+
+        // No second goal of a kind while one is open; desperate below the
+        // lower thresholds
+        npc.need_food = STARVING - 1;
+        npc.need_safety = TERRIFIED - 1;
+        evaluate_goals(&npc, &mut goals, 1);
+        assert_eq!(goals.len(), 2);
+        assert!(goals.iter().all(|goal| goal.desperate));
+    }
+
+    #[test]
+    fn crisis_goals_complete_when_the_need_recovers() {
+        let mut npc = fixtures::npc("Ann");
+        let mut goals = vec![goal(SURVIVAL, 100), goal(ESCAPE, 90)];
+
+        npc.need_food = FED - 1;
+        npc.need_safety = SAFE - 1;
+        evaluate_goals(&npc, &mut goals, 1);
+        assert!(goals.iter().all(is_open));
+        // Not hungry any more: survival drops back
+        assert_eq!(goals[0].priority, 80);
+        assert_eq!(goals[1].priority, 90);
+
+        npc.need_food = FED;
+        npc.need_safety = SAFE;
+        evaluate_goals(&npc, &mut goals, 1);
+        assert!(goals.iter().all(|goal| goal.completed && !goal.failed));
+    }
+
+    #[test]
+    fn deadlines_make_goals_urgent_then_desperate_then_failed() {
+        let npc = fixtures::npc("Ann");
+        let mut goals = vec![goal("save_money", 50)];
+        goals[0].deadline = Some(10);
+
+        evaluate_goals(&npc, &mut goals, 10 - DEADLINE_WARNING_DAYS - 1);
+        assert!(!goals[0].urgent);
+        assert_eq!(goals[0].priority, 50);
+
+        evaluate_goals(&npc, &mut goals, 10 - DEADLINE_WARNING_DAYS);
+        assert!(goals[0].urgent && !goals[0].desperate);
+        assert_eq!(goals[0].priority, 60);
+
+        evaluate_goals(&npc, &mut goals, 10);
+        assert!(goals[0].desperate);
+        assert_eq!(goals[0].priority, 60);
+
+        evaluate_goals(&npc, &mut goals, 11);
+        assert!(goals[0].failed && !is_open(&goals[0]));
+    }
+
+    #[test]
+    fn each_goal_type_has_its_own_action() {
+        let mut npc = fixtures::npc("Ann");
+        let mut rescue = goal(RESCUE, 50);
+        rescue.target = "bob".to_string();
+        let mut clear = goal(CLEAR_THREAT, 50);
+        clear.target = "wolves".to_string();
+
+        let chosen = |npc: &Npc, goal: &Goal| {
+            choose_action(npc, std::slice::from_ref(goal))
+                .map(|(_, action)| (action.kind, action.target, action.duration))
+        };
+        // Survival only acts once the NPC is actually hungry
+        assert_eq!(chosen(&npc, &goal(SURVIVAL, 100)), None);
+        npc.need_food = 49;
+        assert_eq!(
+            chosen(&npc, &goal(SURVIVAL, 100)),
+            Some((SEEK_FOOD.to_string(), None, Some(1)))
+        );
+        assert_eq!(
+            chosen(&npc, &goal(ESCAPE, 90)),
+            Some((SEEK_SHELTER.to_string(), None, Some(1)))
+        );
+        assert_eq!(
+            chosen(&npc, &rescue),
+            Some((SEARCH.to_string(), Some("bob".to_string()), Some(2)))
+        );
+        assert_eq!(
+            chosen(&npc, &clear),
+            Some((RALLY.to_string(), Some("wolves".to_string()), Some(1)))
+        );
+        assert_eq!(chosen(&npc, &goal("save_money", 50)), None);
+
+        // A plan step wins over the default action
+        let mut planned = goal(ESCAPE, 90);
+        planned.plan = vec![step(MOVE)];
+        assert_eq!(chosen(&npc, &planned), Some((MOVE.to_string(), None, None)));
+    }
+
+    #[test]
+    fn desperate_goals_go_first_and_obstacles_block_the_rest() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_food = 30;
+        let mut goals = vec![goal(ESCAPE, 90), goal(SURVIVAL, 10), goal(RESCUE, 100)];
+        goals[0].urgent = true;
+        goals[1].desperate = true;
+
+        assert_eq!(choose_action(&npc, &goals).unwrap().0, 1);
+        goals[1].completed = true;
+        // Urgent beats a higher priority
+        assert_eq!(choose_action(&npc, &goals).unwrap().0, 0);
+        goals[0].obstacles = vec!["locked in".to_string()];
+        assert_eq!(choose_action(&npc, &goals).unwrap().0, 2);
+        goals[0].desperate = true;
+        assert_eq!(choose_action(&npc, &goals).unwrap().0, 0);
+    }
+
+    #[test]
+    fn only_brave_npcs_that_can_still_act_rally() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_safety = UNSAFE - 1;
+        npc.neuroticism = BRAVE - 1;
+
+        let mut goals = Vec::new();
+        consider_clearing(&npc, &mut goals, "wolves", 4);
+        assert!(goals.is_empty(), "no escape goal, nothing to clear");
+
+        goals.push(goal(ESCAPE, 90));
+        consider_clearing(&npc, &mut goals, "wolves", 4);
+        assert_eq!(goals[0].kind, CLEAR_THREAT);
+        assert_eq!(goals[0].target, "wolves");
+        assert_eq!(goals[0].deadline, Some(4 + CLEARING_DAYS));
+        // Rallies rather than hides
+        assert_eq!(choose_action(&npc, &goals).unwrap().1.kind, RALLY);
+
+        consider_clearing(&npc, &mut goals, "wolves", 4);
+        assert_eq!(goals.len(), 2);
+
+        let mut timid = npc.clone();
+        timid.neuroticism = BRAVE;
+        let mut terrified = npc.clone();
+        terrified.need_safety = TERRIFIED - 1;
+        for npc in [timid, terrified] {
+            let mut goals = vec![goal(ESCAPE, 90)];
+            consider_clearing(&npc, &mut goals, "wolves", 4);
+            assert_eq!(goals.len(), 1);
+        }
+    }
+
+    #[test]
+    fn actions_change_needs_and_plans_complete_goals() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_food = 10;
+        npc.need_safety = 70;
+        let mut rng = Rng::new(1);
+        let mut survival = goal(SURVIVAL, 100);
+
+        let outcome = execute_action(&mut npc, &mut survival, &step(SEEK_FOOD), 1, &[], &mut rng);
+        assert!((40..=60).contains(&npc.need_food));
+        assert_eq!(outcome.memory.unwrap().event, "found_food");
+
+        execute_action(
+            &mut npc,
+            &mut survival,
+            &step(SEEK_SHELTER),
+            1,
+            &[],
+            &mut rng,
+        );
+        assert_eq!(npc.need_safety, 100);
+        assert!(is_open(&survival), "default actions don't finish the goal");
+
+        let mut plan = goal("save_money", 50);
+        plan.plan = vec![step("Save money"), step(MOVE)];
+        plan.plan[1].location = Some("Market".to_string());
+        let mut market = fixtures::location("market", 0.0, 0.0);
+        market.name = "Market".to_string();
+        let locations = [market];
+
+        let action = plan.plan[0].clone();
+        let outcome = execute_action(&mut npc, &mut plan, &action, 1, &locations, &mut rng);
+        assert_eq!(npc.need_purpose, 60 + TASK_PURPOSE_GAIN);
+        assert!(outcome.memory.is_none() && is_open(&plan));
+
+        let action = plan.plan[0].clone();
+        let outcome = execute_action(&mut npc, &mut plan, &action, 2, &locations, &mut rng);
+        assert_eq!(outcome.move_to.as_deref(), Some("market"));
+        assert_eq!(outcome.memory.unwrap().event, "completed_save_money_goal");
+        assert!(plan.completed && plan.plan.is_empty());
+    }
+}
@@ -0,0 +1,445 @@
+// Location crises - fires, floods and famines
+//
+// The TS prototype only has fixed crisis zones that trigger when the player
+// walks in (src/location-crisis-system.ts). Here a crisis breaks out at a
+// location, spreads to the locations around it, dies down over time (faster
+// with NPCs there to fight it) and is resolved once its severity reaches 0.
+// While it lasts it takes away the location's food and/or shelter, raises its
+// danger level (which caps the safety of everyone there, see spatial.rs) and
+// wears on the needs of the NPCs present.
+
+use super::threats;
+use crate::db;
+use crate::models::{Event, Location, LocationCrisis, LocationCrisisType, Npc};
+
+/// How a kind of crisis behaves, per in-game hour
+struct Profile {
+    /// Chance of breaking out at a location where it can
+    outbreak: f64,
+    /// Chance at full severity of spreading to each location within reach
+    spread: f64,
+    reach: f64,
+    /// Severity lost on its own, and for each NPC present
+    decay: f64,
+    relief: f64,
+    takes_food: bool,
+    takes_shelter: bool,
+    /// Danger level added at full severity
+    danger: f64,
+    /// Food and rest NPCs present lose at full severity
+    hunger: f64,
+    fatigue: f64,
+}
+
+fn profile(kind: LocationCrisisType) -> Profile {
+    match kind {
+        LocationCrisisType::Fire => Profile {
+            outbreak: 0.002,
+            spread: 0.15,
+            reach: 150.0,
+            decay: 2.0,
+            relief: 4.0,
+            takes_food: false,
+            takes_shelter: true,
+            danger: 60.0,
+            hunger: 0.0,
+            fatigue: 10.0,
+        },
+        LocationCrisisType::Flood => Profile {
+            outbreak: 0.001,
+            spread: 0.1,
+            reach: 200.0,
+            decay: 1.5,
+            relief: 2.0,
+            takes_food: true,
+            takes_shelter: true,
+            danger: 40.0,
+            hunger: 5.0,
+            fatigue: 5.0,
+        },
+        LocationCrisisType::Famine => Profile {
+            outbreak: 0.0005,
+            spread: 0.05,
+            reach: 250.0,
+            decay: 0.5,
+            relief: 0.5,
+            takes_food: true,
+            takes_shelter: false,
+            danger: 20.0,
+            hunger: 10.0,
+            fatigue: 0.0,
+        },
+    }
+}
+
+/// Severity of a crisis breaking out is OUTBREAK_SEVERITY plus up to
+/// OUTBREAK_SPREAD; a crisis that spreads starts at SPREAD_SEVERITY of its source
+const OUTBREAK_SEVERITY: f64 = 30.0;
+const OUTBREAK_SPREAD: f64 = 40.0;
+const SPREAD_SEVERITY: f64 = 0.6;
+/// A location this dangerous counts as dangerous
+const DANGEROUS: i32 = 50;
+/// Dramatic value of a crisis being resolved
+const RESOLVED_DRAMA: i32 = 25;
+
+impl LocationCrisisType {
+    pub fn emoji(self) -> &'static str {
+        match self {
+            LocationCrisisType::Fire => "🔥",
+            LocationCrisisType::Flood => "🌊",
+            LocationCrisisType::Famine => "🌾",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            LocationCrisisType::Fire => "Fire",
+            LocationCrisisType::Flood => "Flood",
+            LocationCrisisType::Famine => "Famine",
+        }
+    }
+}
+
+/// Fires break out in buildings, floods outdoors, famines where food is kept
+pub fn can_break_out(kind: LocationCrisisType, location: &Location) -> bool {
+    match kind {
+        LocationCrisisType::Fire => location.kind == "building",
+        LocationCrisisType::Flood => location.kind != "building",
+        LocationCrisisType::Famine => location.has_food,
+    }
+}
+
+/// Chance of breaking out this hour
+pub fn outbreak_chance(kind: LocationCrisisType) -> f64 {
+    profile(kind).outbreak
+}
+
+/// Chance of the crisis spreading from `from` to `to` this hour; 0 if either
+/// is off the map or out of reach
+pub fn spread_chance(crisis: &LocationCrisis, from: &Location, to: &Location) -> f64 {
+    let (Some(fx), Some(fy), Some(tx), Some(ty)) = (from.x, from.y, to.x, to.y) else {
+        return 0.0;
+    };
+    let profile = profile(crisis.kind);
+    if from.id == to.id || threats::distance(fx, fy, tx, ty) > profile.reach {
+        return 0.0;
+    }
+    profile.spread * crisis.severity / 100.0
+}
+
+/// Severity of a crisis breaking out, `roll` in 0..1
+pub fn outbreak_severity(roll: f64) -> f64 {
+    OUTBREAK_SEVERITY + OUTBREAK_SPREAD * roll
+}
+
+pub fn spread_severity(crisis: &LocationCrisis) -> f64 {
+    crisis.severity * SPREAD_SEVERITY
+}
+
+/// A new crisis at `location`. The location as it was before any crisis is
+/// taken from the crises already there, if any.
+pub fn start(
+    kind: LocationCrisisType,
+    location: &Location,
+    severity: f64,
+    source: Option<&LocationCrisis>,
+    here: &[&LocationCrisis],
+    (day, hour): (i32, i32),
+) -> LocationCrisis {
+    let now = db::now_millis();
+    let (base_has_food, base_has_shelter, base_is_dangerous, base_danger_level) = match here.first()
+    {
+        Some(other) => (
+            other.base_has_food,
+            other.base_has_shelter,
+            other.base_is_dangerous,
+            other.base_danger_level,
+        ),
+        None => (
+            location.has_food,
+            location.has_shelter,
+            location.is_dangerous,
+            location.danger_level,
+        ),
+    };
+
+    LocationCrisis {
+        id: db::new_id(),
+        world_id: location.world_id.clone(),
+        location_id: location.id.clone(),
+        kind,
+        severity: severity.clamp(0.0, 100.0),
+        source_id: source.map(|source| source.id.clone()),
+        started_day: day,
+        started_hour: hour,
+        resolved: false,
+        resolved_day: None,
+        base_has_food,
+        base_has_shelter,
+        base_is_dangerous,
+        base_danger_level,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// One hour of the crisis dying down with `helpers` NPCs fighting it.
+/// Returns true if it was resolved.
+pub fn die_down(crisis: &mut LocationCrisis, helpers: usize, day: i32) -> bool {
+    let profile = profile(crisis.kind);
+    crisis.severity = (crisis.severity - profile.decay - profile.relief * helpers as f64).max(0.0);
+    crisis.updated_at = db::now_millis();
+    if crisis.severity > 0.0 {
+        return false;
+    }
+
+    crisis.resolved = true;
+    crisis.resolved_day = Some(day);
+    true
+}
+
+/// Set the location's food, shelter and danger from its crises (resolved ones
+/// only count for the snapshot of how it was before). Returns true if
+/// anything changed.
+pub fn affect_location(location: &mut Location, crises: &[&LocationCrisis]) -> bool {
+    let Some(base) = crises.first() else {
+        return false;
+    };
+    let active: Vec<(&LocationCrisis, Profile)> = crises
+        .iter()
+        .filter(|crisis| !crisis.resolved)
+        .map(|crisis| (*crisis, profile(crisis.kind)))
+        .collect();
+
+    let added_danger: f64 = active
+        .iter()
+        .map(|(crisis, profile)| profile.danger * crisis.severity / 100.0)
+        .sum();
+    let danger_level = (base.base_danger_level + added_danger.round() as i32).clamp(0, 100);
+
+    let before = (
+        location.has_food,
+        location.has_shelter,
+        location.is_dangerous,
+        location.danger_level,
+    );
+    location.has_food = base.base_has_food && !active.iter().any(|(_, p)| p.takes_food);
+    location.has_shelter = base.base_has_shelter && !active.iter().any(|(_, p)| p.takes_shelter);
+    location.is_dangerous = base.base_is_dangerous || danger_level >= DANGEROUS;
+    location.danger_level = danger_level;
+
+    let changed = before
+        != (
+            location.has_food,
+            location.has_shelter,
+            location.is_dangerous,
+            location.danger_level,
+        );
+    if changed {
+        location.updated_at = db::now_millis();
+    }
+    changed
+}
+
+/// Hunger and fatigue for an NPC at a location in crisis
+pub fn affect_npc<'a>(npc: &mut Npc, crises: impl IntoIterator<Item = &'a LocationCrisis>) {
+    let (hunger, fatigue) = crises.into_iter().filter(|crisis| !crisis.resolved).fold(
+        (0.0, 0.0),
+        |(hunger, fatigue), crisis| {
+            let profile = profile(crisis.kind);
+            let share = crisis.severity / 100.0;
+            (
+                hunger + profile.hunger * share,
+                fatigue + profile.fatigue * share,
+            )
+        },
+    );
+
+    npc.need_food = (npc.need_food - hunger.round() as i32).clamp(0, 100);
+    npc.need_rest = (npc.need_rest - fatigue.round() as i32).clamp(0, 100);
+}
+
+/// "🔥 Fire at Marcus's Forge!", or "... spread to ..." when it came from
+/// another location
+pub fn started_message(crisis: &LocationCrisis, location: &Location) -> String {
+    let verb = if crisis.source_id.is_some() {
+        "spread to"
+    } else {
+        "at"
+    };
+    format!(
+        "{} {} {} {}!",
+        crisis.kind.emoji(),
+        crisis.kind.title(),
+        verb,
+        location.name
+    )
+}
+
+/// Event for a crisis that broke out or spread (as dramatic as it is
+/// severe), or that was resolved. `present` are the NPCs at the location.
+pub fn crisis_event(
+    crisis: &LocationCrisis,
+    location: &Location,
+    present: &[&Npc],
+    (day, hour): (i32, i32),
+) -> Event {
+    let (kind, description, dramatic_value) = if crisis.resolved {
+        let over = match crisis.kind {
+            LocationCrisisType::Fire => "is out",
+            LocationCrisisType::Flood => "has receded",
+            LocationCrisisType::Famine => "is over",
+        };
+        (
+            "location_crisis_resolved",
+            format!("The {} at {} {}", crisis.kind, location.name, over),
+            RESOLVED_DRAMA,
+        )
+    } else {
+        (
+            "location_crisis",
+            started_message(crisis, location),
+            crisis.severity.round() as i32,
+        )
+    };
+
+    Event {
+        id: db::new_id(),
+        world_id: crisis.world_id.clone(),
+        day,
+        hour,
+        kind: kind.to_string(),
+        description,
+        location_id: location.id.clone(),
+        participant_ids: present.iter().map(|npc| npc.id.clone()).collect(),
+        target_id: None,
+        resolved: crisis.resolved,
+        consequences: Vec::new(),
+        dramatic_value,
+        created_at: db::now_millis(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    const CLOCK: (i32, i32) = (3, 12);
+
+    fn crisis(kind: LocationCrisisType, location: &Location, severity: f64) -> LocationCrisis {
+        start(kind, location, severity, None, &[], CLOCK)
+    }
+
+    /// Hours until the crisis is resolved with `helpers` NPCs fighting it
+    fn hours_to_resolve(mut crisis: LocationCrisis, helpers: usize) -> usize {
+        (1..1000)
+            .find(|_| die_down(&mut crisis, helpers, CLOCK.0))
+            .unwrap()
+    }
+
+    #[test]
+    fn crises_die_down_faster_with_helpers() {
+        let forge = fixtures::location("forge", 0.0, 0.0);
+        let fire = crisis(LocationCrisisType::Fire, &forge, 20.0);
+
+        assert_eq!(hours_to_resolve(fire.clone(), 0), 10);
+        assert_eq!(hours_to_resolve(fire, 2), 2);
+    }
+
+    #[test]
+    fn die_down_resolves_at_zero() {
+        let farm = fixtures::location("farm", 0.0, 0.0);
+        let mut famine = crisis(LocationCrisisType::Famine, &farm, 1.0);
+
+        assert!(!die_down(&mut famine, 0, 4));
+        assert_eq!(famine.severity, 0.5);
+        assert!(!famine.resolved);
+
+        assert!(die_down(&mut famine, 0, 5));
+        assert_eq!(famine.severity, 0.0);
+        assert!(famine.resolved);
+        assert_eq!(famine.resolved_day, Some(5));
+    }
+
+    #[test]
+    fn crises_spread_only_within_reach() {
+        let forge = fixtures::location("forge", 0.0, 0.0);
+        let next_door = fixtures::location("next_door", 100.0, 0.0);
+        let far_away = fixtures::location("far_away", 200.0, 0.0);
+        let mut off_map = fixtures::location("off_map", 0.0, 0.0);
+        (off_map.x, off_map.y) = (None, None);
+        let fire = crisis(LocationCrisisType::Fire, &forge, 60.0);
+
+        assert!((spread_chance(&fire, &forge, &next_door) - 0.09).abs() < 1e-9);
+        assert_eq!(spread_chance(&fire, &forge, &far_away), 0.0);
+        assert_eq!(spread_chance(&fire, &forge, &forge), 0.0);
+        assert_eq!(spread_chance(&fire, &forge, &off_map), 0.0);
+    }
+
+    #[test]
+    fn spread_crises_start_weaker_and_remember_their_source() {
+        let forge = fixtures::location("forge", 0.0, 0.0);
+        let next_door = fixtures::location("next_door", 100.0, 0.0);
+        let fire = crisis(LocationCrisisType::Fire, &forge, 60.0);
+
+        let spread = start(
+            fire.kind,
+            &next_door,
+            spread_severity(&fire),
+            Some(&fire),
+            &[],
+            CLOCK,
+        );
+        assert!((spread.severity - 36.0).abs() < 1e-9);
+        assert_eq!(spread.source_id.as_deref(), Some(fire.id.as_str()));
+        assert_eq!(
+            started_message(&spread, &next_door),
+            "🔥 Fire spread to next_door!"
+        );
+    }
+
+    #[test]
+    fn locations_recover_once_their_crises_are_resolved() {
+        let mut forge = fixtures::location("forge", 0.0, 0.0);
+        let mut fire = crisis(LocationCrisisType::Fire, &forge, 100.0);
+
+        assert!(affect_location(&mut forge, &[&fire]));
+        assert!(!forge.has_shelter);
+        assert!(forge.has_food);
+        assert_eq!(forge.danger_level, 60);
+        assert!(forge.is_dangerous);
+
+        // A second crisis keeps the location as it was before the first
+        let mut famine = start(
+            LocationCrisisType::Famine,
+            &forge,
+            50.0,
+            None,
+            &[&fire],
+            CLOCK,
+        );
+        assert!(famine.base_has_shelter);
+        assert_eq!(famine.base_danger_level, 0);
+
+        fire.resolved = true;
+        famine.resolved = true;
+        assert!(affect_location(&mut forge, &[&fire, &famine]));
+        assert!(forge.has_shelter);
+        assert_eq!(forge.danger_level, 0);
+        assert!(!forge.is_dangerous);
+        assert!(!affect_location(&mut forge, &[&fire, &famine]));
+    }
+
+    #[test]
+    fn npcs_present_go_hungry_and_tired() {
+        let farm = fixtures::location("farm", 0.0, 0.0);
+        let famine = crisis(LocationCrisisType::Famine, &farm, 50.0);
+        let fire = crisis(LocationCrisisType::Fire, &farm, 50.0);
+        let mut npc = fixtures::npc("Ann");
+
+        affect_npc(&mut npc, [&famine, &fire]);
+        assert_eq!(npc.need_food, 75);
+        assert_eq!(npc.need_rest, 75);
+    }
+}
@@ -0,0 +1,341 @@
+// Market prices - supply and demand over the world's storages
+//
+// There is no market in the TS prototype; RESOURCE_INFO has no value and
+// needWealth only ever drifts. Here every resource has a base price that is
+// scaled by how much the world needs (recipe inputs, meals) against how much
+// is in stock. NPCs sell what they produce and buy their meals at these
+// prices, so needWealth follows what they earn and spend. Storages trade
+// with each other at the same prices, paying out of their coins.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::resources::{self, recipe_for, Economy};
+use crate::models::{Npc, ResourceType, Storage};
+
+/// Price when supply exactly meets demand
+const BASE_PRICES: [(ResourceType, i32); 8] = {
+    use ResourceType::*;
+    [
+        (Wood, 2),
+        (Stone, 3),
+        (Iron, 5),
+        (Food, 2),
+        (Cloth, 6),
+        (Tools, 12),
+        (Weapons, 20),
+        (Medicine, 10),
+    ]
+};
+
+/// Key of the storage NPCs shop at first
+pub const MARKET_STORAGE: &str = "market";
+/// Food units bought per hour of eating
+pub const MEAL: i32 = 1;
+
+/// Stock the world wants of every resource, even if nobody uses it yet
+const BASE_DEMAND: i32 = 10;
+/// Batches of inputs a producer wants on hand
+const INPUT_BATCHES: i32 = 2;
+/// Food units an NPC eats per day
+const FOOD_PER_NPC: i32 = 3;
+/// Prices stay within base / 4 ..= base * 4
+const PRICE_SPREAD: f64 = 4.0;
+/// Coins per point of needWealth, so one sale can't max it out
+const COINS_PER_WEALTH: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketPrice {
+    pub resource: ResourceType,
+    pub base_price: i32,
+    /// Coins per unit right now
+    pub price: i32,
+    /// Units in all storages
+    pub supply: i32,
+    /// Units the world wants in stock
+    pub demand: i32,
+}
+
+/// Goods that changed hands between two storages
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    pub resource: ResourceType,
+    /// Units moved, 0 if nothing could be traded
+    pub amount: i32,
+    /// Coins per unit
+    pub price: i32,
+    /// Coins the buyer paid the seller
+    pub coins: i32,
+}
+
+/// Prices of one moment; rebuilt whenever they are needed
+pub struct Market {
+    prices: BTreeMap<ResourceType, MarketPrice>,
+}
+
+impl Market {
+    pub fn new(economy: &Economy, npcs: &[Npc]) -> Self {
+        let living: Vec<&Npc> = npcs.iter().filter(|npc| npc.state != "dead").collect();
+
+        let prices = BASE_PRICES
+            .iter()
+            .map(|&(resource, base_price)| {
+                let supply: i32 = economy
+                    .storages
+                    .iter()
+                    .map(|storage| storage.get(resource))
+                    .sum();
+
+                let inputs: i32 = living
+                    .iter()
+                    .filter_map(|npc| recipe_for(&economy.recipes, &npc.occupation))
+                    .flat_map(|recipe| recipe.requires.iter())
+                    .filter(|(required, _)| **required == resource)
+                    .map(|(_, amount)| amount * INPUT_BATCHES)
+                    .sum();
+                let meals = if resource == ResourceType::Food {
+                    living.len() as i32 * FOOD_PER_NPC
+                } else {
+                    0
+                };
+                let demand = BASE_DEMAND + inputs + meals;
+
+                let ratio =
+                    (demand as f64 / supply.max(1) as f64).clamp(1.0 / PRICE_SPREAD, PRICE_SPREAD);
+                let price = ((base_price as f64 * ratio).round() as i32).max(1);
+
+                let price = MarketPrice {
+                    resource,
+                    base_price,
+                    price,
+                    supply,
+                    demand,
+                };
+                (resource, price)
+            })
+            .collect();
+
+        Self { prices }
+    }
+
+    pub fn price(&self, resource: ResourceType) -> i32 {
+        self.prices.get(&resource).map_or(1, |price| price.price)
+    }
+
+    pub fn prices(&self) -> Vec<MarketPrice> {
+        self.prices.values().copied().collect()
+    }
+
+    /// Pay the NPC for `amount` units. Returns the coins earned.
+    pub fn sell(&self, npc: &mut Npc, resource: ResourceType, amount: i32) -> i32 {
+        let coins = self.price(resource) * amount.max(0);
+        let earned = if coins > 0 {
+            (coins / COINS_PER_WEALTH).max(1)
+        } else {
+            0
+        };
+        npc.need_wealth = (npc.need_wealth + earned).min(100);
+        coins
+    }
+
+    /// Take `amount` units out of the storage and charge the NPC for them;
+    /// the storage keeps the coins. Returns the coins paid, or None if the NPC can't afford it or the
+    /// storage is short.
+    pub fn buy(
+        &self,
+        npc: &mut Npc,
+        storage: &mut Storage,
+        resource: ResourceType,
+        amount: i32,
+    ) -> Option<i32> {
+        let coins = self.price(resource) * amount;
+        let cost = (coins + COINS_PER_WEALTH - 1) / COINS_PER_WEALTH;
+        if npc.need_wealth < cost || !storage.has(resource, amount) {
+            return None;
+        }
+
+        storage.remove(resource, amount);
+        storage.coins += coins;
+        npc.need_wealth -= cost;
+        Some(coins)
+    }
+
+    /// Sell up to `amount` units from one storage to another. Only what the
+    /// seller has, the buyer has room for and the buyer can pay for changes
+    /// hands; the buyer's coins go to the seller.
+    pub fn trade(
+        &self,
+        seller: &mut Storage,
+        buyer: &mut Storage,
+        resource: ResourceType,
+        amount: i32,
+    ) -> Trade {
+        let price = self.price(resource);
+        let amount = amount
+            .min(seller.get(resource))
+            .min(buyer.capacity - buyer.total_stored())
+            .min(buyer.coins / price)
+            .max(0);
+
+        let amount = resources::transfer(seller, buyer, resource, amount);
+        let coins = price * amount;
+        buyer.coins -= coins;
+        seller.coins += coins;
+        Trade {
+            resource,
+            amount,
+            price,
+            coins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+    use crate::simulation::resources;
+
+    /// An economy with one storage holding `stock`
+    fn economy(stock: &[(ResourceType, i32)]) -> Economy {
+        let mut storage = Storage::new(
+            fixtures::WORLD,
+            resources::DEFAULT_STORAGE,
+            "Warehouse",
+            "🏚️",
+            100_000,
+            (0.0, 0.0),
+        );
+        for &(resource, amount) in stock {
+            storage.add(resource, amount);
+        }
+        Economy {
+            storages: vec![storage],
+            recipes: resources::default_recipes(fixtures::WORLD),
+            tasks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn prices_follow_supply_and_demand() {
+        // Nobody lives here, so the world only wants BASE_DEMAND of each
+        let market = Market::new(
+            &economy(&[(ResourceType::Stone, 10), (ResourceType::Iron, 20)]),
+            &[],
+        );
+
+        assert_eq!(market.price(ResourceType::Stone), 3);
+        let iron = market
+            .prices()
+            .into_iter()
+            .find(|price| price.resource == ResourceType::Iron)
+            .unwrap();
+        assert_eq!((iron.supply, iron.demand), (20, 10));
+        // Twice the stock the world needs: half of 5, rounded
+        assert_eq!(iron.price, 3);
+    }
+
+    #[test]
+    fn prices_are_clamped_to_a_quarter_and_four_times_base() {
+        let empty = Market::new(&economy(&[]), &[]);
+        let flooded = Market::new(
+            &economy(&BASE_PRICES.map(|(resource, _)| (resource, 10_000))),
+            &[],
+        );
+
+        for (resource, base) in BASE_PRICES {
+            assert_eq!(empty.price(resource), base * 4);
+            let lowest = ((base as f64 / 4.0).round() as i32).max(1);
+            assert_eq!(flooded.price(resource), lowest);
+        }
+    }
+
+    #[test]
+    fn producers_and_mouths_add_demand() {
+        let mut carpenter = fixtures::npc("Marcus");
+        carpenter.occupation = "Carpenter".to_string();
+        let mut dead = fixtures::npc("Bob");
+        dead.state = "dead".to_string();
+
+        let market = Market::new(&economy(&[]), &[carpenter, fixtures::npc("Ann"), dead]);
+        let demand = |resource| {
+            market
+                .prices()
+                .into_iter()
+                .find(|price| price.resource == resource)
+                .unwrap()
+                .demand
+        };
+        // Two living NPCs eat; the dead one doesn't
+        assert_eq!(demand(ResourceType::Food), BASE_DEMAND + 2 * FOOD_PER_NPC);
+        // Two batches of the carpenter's wood and iron
+        assert_eq!(demand(ResourceType::Wood), BASE_DEMAND + 4);
+        assert_eq!(demand(ResourceType::Iron), BASE_DEMAND + 2);
+        assert_eq!(demand(ResourceType::Medicine), BASE_DEMAND);
+    }
+
+    #[test]
+    fn buying_needs_the_money_and_the_stock() {
+        let market = Market::new(&economy(&[]), &[]);
+        let mut economy = economy(&[(ResourceType::Food, 5)]);
+        let storage = &mut economy.storages[0];
+        let mut npc = fixtures::npc("Ann");
+
+        // Food costs 8 coins while the market has none: 2 points of wealth
+        assert_eq!(
+            market.buy(&mut npc, storage, ResourceType::Food, 1),
+            Some(8)
+        );
+        assert_eq!(npc.need_wealth, 48);
+        assert_eq!(storage.get(ResourceType::Food), 4);
+        assert_eq!(storage.coins, 8);
+
+        assert_eq!(market.buy(&mut npc, storage, ResourceType::Food, 10), None);
+        npc.need_wealth = 1;
+        assert_eq!(market.buy(&mut npc, storage, ResourceType::Food, 1), None);
+        assert_eq!(storage.get(ResourceType::Food), 4);
+    }
+
+    #[test]
+    fn trading_moves_goods_and_settles_the_price() {
+        let market = Market::new(&economy(&[]), &[]);
+        let mut economy = economy(&[(ResourceType::Wood, 10)]);
+        let mut seller = economy.storages.remove(0);
+        let mut buyer = Storage::new(fixtures::WORLD, "market", "Market", "🏪", 6, (0.0, 0.0));
+        buyer.coins = 40;
+
+        // Prices come from an empty world: wood costs 8 coins
+        let trade = market.trade(&mut seller, &mut buyer, ResourceType::Wood, 3);
+        assert_eq!(
+            trade,
+            Trade {
+                resource: ResourceType::Wood,
+                amount: 3,
+                price: 8,
+                coins: 24,
+            }
+        );
+        assert_eq!((seller.get(ResourceType::Wood), seller.coins), (7, 24));
+        assert_eq!((buyer.get(ResourceType::Wood), buyer.coins), (3, 16));
+
+        // 16 coins pay for 2 of the 5 asked
+        let trade = market.trade(&mut seller, &mut buyer, ResourceType::Wood, 5);
+        assert_eq!((trade.amount, trade.coins), (2, 16));
+        assert_eq!(buyer.coins, 0);
+
+        // Room for one more, but no coins left
+        let trade = market.trade(&mut seller, &mut buyer, ResourceType::Wood, 1);
+        assert_eq!(trade.amount, 0);
+
+        // Rich now, but there is room for one unit only
+        buyer.coins = 1_000;
+        let trade = market.trade(&mut seller, &mut buyer, ResourceType::Wood, 10);
+        assert_eq!(trade.amount, 1);
+        assert_eq!(buyer.total_stored(), buyer.capacity);
+        assert_eq!(seller.get(ResourceType::Wood), 4);
+        assert_eq!(seller.coins + buyer.coins, 1_000 + 40);
+    }
+}
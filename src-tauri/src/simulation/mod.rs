@@ -0,0 +1,1989 @@
+// Native world simulation - Rust counterpart of src/simulation/WorldSimulation.ts
+//
+// The tick loop runs on its own thread, owned by `SimulationState`.
+// 1 tick = 1 in-game hour, one tick every `tick_speed_ms / time_scale` of real time.
+// After each tick the changes are pushed to the frontend (see events.rs).
+// This clock is the only one: the frontend follows world://tick rather than
+// running its own hourly timer.
+
+mod behavior;
+mod buildings;
+mod clearing;
+mod crisis;
+mod daily_cycle;
+pub mod events;
+#[cfg(test)]
+mod fixtures;
+mod goals;
+mod location_crises;
+mod market;
+mod needs;
+mod patrols;
+mod resources;
+mod rng;
+mod spatial;
+mod threats;
+mod towns;
+mod worlds;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Event, Goal, Location, LocationCrisis, LocationCrisisType, Npc, ProductionTask, ResourceType,
+    Schedule, Storage, ThreatSource, ThreatType, Town, World,
+};
+pub use behavior::NpcDecision;
+use behavior::{Activity, LocationType, Places};
+use buildings::BuildingEffects;
+pub use crisis::Crisis;
+pub use daily_cycle::next_checkpoint;
+use daily_cycle::{DailyCycle, NextCheckpoint};
+use events::{
+    CheckpointReached, CrisisChanged, FastForwardProgress, NpcMessage, NpcMoved, ThreatMoved,
+    WorldDiff,
+};
+use market::Market;
+pub use market::{MarketPrice, Trade};
+use resources::{Economy, ProductionBlocked};
+use rng::Rng;
+use spatial::WorldMap;
+pub use spatial::{BBox, EntityKind, SpatialHit};
+pub use threats::{NearbyThreat, SafetyReport};
+pub use worlds::import_world;
+
+/// Real-time milliseconds per tick (same default as WorldSimulation.ts)
+pub const DEFAULT_TICK_SPEED_MS: u64 = 1000;
+/// Furthest a single fast-forward may go, in in-game days
+pub const MAX_FAST_FORWARD_DAYS: i32 = 30;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulationConfig {
+    pub tick_speed_ms: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            tick_speed_ms: DEFAULT_TICK_SPEED_MS,
+        }
+    }
+}
+
+/// In-game time, loaded from and saved back to the `World` row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldClock {
+    pub day: i32,
+    pub hour: i32,
+}
+
+impl WorldClock {
+    /// Hours from this time until `other` (negative if `other` is earlier)
+    pub fn hours_until(&self, other: WorldClock) -> i64 {
+        (other.day as i64 * 24 + other.hour as i64) - (self.day as i64 * 24 + self.hour as i64)
+    }
+
+    /// Advance one hour, into the next day after 23:00
+    pub fn advance(&mut self) {
+        self.hour += 1;
+        if self.hour >= 24 {
+            self.hour = 0;
+            self.day += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Running,
+    Paused,
+    Stopped,
+}
+
+/// Payload of `get_simulation_status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationStatus {
+    pub state: RunState,
+    pub world_id: Option<String>,
+    pub day: Option<i32>,
+    pub hour: Option<i32>,
+    /// Ticks since the last start_simulation
+    pub tick_count: u64,
+    /// Measured from the last two ticks; 0 unless running
+    pub ticks_per_second: f64,
+    pub tick_speed_ms: u64,
+    pub time_scale: f64,
+    pub next_checkpoint: Option<NextCheckpoint>,
+}
+
+/// Everything a tick mutates. Shared between the loop thread and commands.
+struct Engine {
+    conn: Connection,
+    world_id: String,
+    config: SimulationConfig,
+    time_scale: f64,
+    clock: WorldClock,
+    npcs: Vec<Npc>,
+    /// Open goals by NPC id; finished goals are dropped once saved
+    goals: HashMap<String, Vec<Goal>>,
+    locations: Vec<Location>,
+    schedules: HashMap<String, Vec<Schedule>>,
+    /// What each NPC is doing since the last checkpoint (idle until the first)
+    activities: HashMap<String, Activity>,
+    economy: Economy,
+    /// What places and activities do to NPCs (data/building-effects.json)
+    effects: BuildingEffects,
+    /// Threats and towns, plus the spatial index over threats, locations and NPCs
+    map: WorldMap,
+    rng: Rng,
+    /// Events created since the last diff, drained into it
+    new_events: Vec<Event>,
+    /// Goals created or changed since the last diff
+    changed_goals: Vec<Goal>,
+    /// Checkpoint output since the last diff
+    checkpoints: Vec<CheckpointReached>,
+    messages: Vec<NpcMessage>,
+    movements: Vec<NpcMoved>,
+    threat_moves: Vec<ThreatMoved>,
+    cleared_threats: Vec<ThreatSource>,
+    production: Vec<ProductionTask>,
+    /// Towns whose safety was recalculated since the last diff
+    changed_towns: Vec<Town>,
+    /// The crisis each NPC is in right now, by NPC id
+    crises: HashMap<String, Crisis>,
+    crisis_changes: Vec<CrisisChanged>,
+    /// Fires, floods and famines going on
+    location_crises: Vec<LocationCrisis>,
+    /// Location crises and locations changed since the last diff
+    changed_location_crises: Vec<LocationCrisis>,
+    changed_locations: Vec<Location>,
+    tick_count: u64,
+    paused: bool,
+    last_tick_at: Option<Instant>,
+    ticks_per_second: f64,
+}
+
+impl Engine {
+    /// Load the world (or the first world) from the database
+    fn load(
+        db: &Database,
+        world_id: Option<&str>,
+        config: SimulationConfig,
+        time_scale: f64,
+    ) -> AppResult<Self> {
+        let mut conn = db.connect()?;
+        let world = match world_id {
+            Some(id) => db::get_world(&conn, id)?,
+            None => db::first_world(&conn)?,
+        }
+        .ok_or_else(|| AppError::not_found("World", world_id.unwrap_or("(first)")))?;
+        let npcs = db::list_npcs(&conn, &world.id)?;
+        let mut goals: HashMap<String, Vec<Goal>> = HashMap::new();
+        for goal in db::list_goals(&conn, &world.id)? {
+            if goals::is_open(&goal) {
+                goals.entry(goal.npc_id.clone()).or_default().push(goal);
+            }
+        }
+        let locations = db::list_locations(&conn, &world.id)?;
+        let mut schedules = HashMap::new();
+        for npc in &npcs {
+            schedules.insert(npc.id.clone(), db::list_schedule(&conn, &npc.id)?);
+        }
+        let economy = load_economy(&mut conn, &world)?;
+        let effects = BuildingEffects::load()?;
+        let map = load_world_map(&mut conn, &world)?;
+        let location_crises = db::list_location_crises(&conn, &world.id, false)?;
+        // Crises the world was saved in are not news
+        let crises = active_crises(&npcs)
+            .map(|crisis| (crisis.npc_id.clone(), crisis))
+            .collect();
+
+        Ok(Self {
+            conn,
+            world_id: world.id,
+            config,
+            time_scale,
+            clock: WorldClock {
+                day: world.current_day,
+                hour: world.current_hour,
+            },
+            npcs,
+            goals,
+            locations,
+            schedules,
+            activities: HashMap::new(),
+            economy,
+            effects,
+            map,
+            rng: Rng::from_time(),
+            new_events: Vec::new(),
+            changed_goals: Vec::new(),
+            checkpoints: Vec::new(),
+            messages: Vec::new(),
+            movements: Vec::new(),
+            threat_moves: Vec::new(),
+            cleared_threats: Vec::new(),
+            production: Vec::new(),
+            changed_towns: Vec::new(),
+            crises,
+            crisis_changes: Vec::new(),
+            location_crises,
+            changed_location_crises: Vec::new(),
+            changed_locations: Vec::new(),
+            tick_count: 0,
+            paused: false,
+            last_tick_at: None,
+            ticks_per_second: 0.0,
+        })
+    }
+
+    /// Real time between two ticks of the background loop
+    fn tick_interval(&self) -> Duration {
+        let millis = self.config.tick_speed_ms as f64 / self.time_scale;
+        Duration::from_secs_f64(millis.max(1.0) / 1000.0)
+    }
+
+    /// One real-time tick of the background loop
+    fn tick(&mut self) -> WorldDiff {
+        let now = Instant::now();
+        if let Some(last) = self.last_tick_at {
+            let elapsed = now.duration_since(last).as_secs_f64();
+            if elapsed > 0.0 {
+                self.ticks_per_second = 1.0 / elapsed;
+            }
+        }
+        self.last_tick_at = Some(now);
+
+        self.run_ticks(1)
+    }
+
+    /// Advance `n` hours and return everything that changed across them
+    fn run_ticks(&mut self, n: u32) -> WorldDiff {
+        let npcs_before = self.npcs.clone();
+
+        for _ in 0..n {
+            self.advance_hour();
+        }
+
+        self.take_diff(&npcs_before)
+    }
+
+    /// Everything that changed since `npcs_before` was taken
+    fn take_diff(&mut self, npcs_before: &[Npc]) -> WorldDiff {
+        WorldDiff {
+            tick: self.tick_count,
+            day: self.clock.day,
+            hour: self.clock.hour,
+            npcs: WorldDiff::changed_npcs(npcs_before, &self.npcs),
+            goals: WorldDiff::latest_by_id(std::mem::take(&mut self.changed_goals), |goal| {
+                &goal.id
+            }),
+            events: std::mem::take(&mut self.new_events),
+            checkpoints: std::mem::take(&mut self.checkpoints),
+            messages: std::mem::take(&mut self.messages),
+            movements: std::mem::take(&mut self.movements),
+            threat_moves: std::mem::take(&mut self.threat_moves),
+            cleared_threats: std::mem::take(&mut self.cleared_threats),
+            production: WorldDiff::latest_by_id(std::mem::take(&mut self.production), |task| {
+                &task.id
+            }),
+            towns: WorldDiff::latest_by_id(std::mem::take(&mut self.changed_towns), |town| {
+                &town.id
+            }),
+            crises: std::mem::take(&mut self.crisis_changes),
+            location_crises: WorldDiff::latest_by_id(
+                std::mem::take(&mut self.changed_location_crises),
+                |crisis| &crisis.id,
+            ),
+            locations: WorldDiff::latest_by_id(
+                std::mem::take(&mut self.changed_locations),
+                |location| &location.id,
+            ),
+        }
+    }
+
+    /// Run headless for up to `max_ticks` hours, stopping early at midnight,
+    /// all in one transaction instead of one commit per hour. Returns the
+    /// hours run.
+    fn fast_forward_day(&mut self, max_ticks: u64) -> AppResult<u64> {
+        let mut ticks = 0;
+        self.conn.execute_batch("BEGIN")?;
+        while ticks < max_ticks {
+            self.advance_hour();
+            ticks += 1;
+            if self.clock.hour == 0 {
+                break;
+            }
+        }
+        self.conn.execute_batch("COMMIT")?;
+        Ok(ticks)
+    }
+
+    /// NPCAgent.tick for every living NPC (needs, safety from the threat
+    /// map, activity and building effects, emotions, goals, action), then one
+    /// batched write of everything that changed. Returns who rallied against
+    /// which threat.
+    fn update_npcs(&mut self) -> BTreeMap<String, Vec<String>> {
+        let day = self.clock.day;
+        let mut changed_npcs = Vec::new();
+        let mut changed_goals = Vec::new();
+        let mut memories = Vec::new();
+        let mut messages = Vec::new();
+        let mut rallies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        // Goal actions that took NPCs somewhere else
+        let mut movements = Vec::new();
+        let mut occupancy: HashMap<String, usize> = HashMap::new();
+        for npc in self.npcs.iter().filter(|npc| npc.state != "dead") {
+            *occupancy.entry(npc.location_id.clone()).or_default() += 1;
+        }
+
+        for npc in self.npcs.iter_mut().filter(|npc| npc.state != "dead") {
+            let goals = self.goals.entry(npc.id.clone()).or_default();
+            let npc_before = npc.clone();
+            let goals_before = goals.clone();
+
+            needs::update_needs(npc, goals.iter().any(goals::is_open));
+            // The threat this NPC could stand up to (the player can't be cleared)
+            let mut clearable = None;
+            // What kind of place the NPC is at, and the safety it allows
+            let mut place: Option<(LocationType, i32)> = None;
+            if let Some(location) = self.locations.iter().find(|l| l.id == npc.location_id) {
+                let local = self.map.location_safety(location);
+                let change = needs::update_safety(npc, local.safety);
+                let places = Places {
+                    locations: &self.locations,
+                    schedule: self
+                        .schedules
+                        .get(&npc.id)
+                        .map(Vec::as_slice)
+                        .unwrap_or(&[]),
+                };
+                place = places
+                    .location_type(npc, location, &self.effects)
+                    .map(|kind| (kind, local.safety));
+                clearable = local
+                    .threats
+                    .iter()
+                    .find(|impact| impact.threat.kind != ThreatType::Player)
+                    .map(|impact| impact.threat.id.clone());
+                let message = match (local.threats.first(), local.town) {
+                    _ if change >= 0 => None,
+                    (Some(worst), _) => Some(format!(
+                        "{} {} nearby! 🛡️({})",
+                        worst.threat.kind.emoji(),
+                        worst.threat.kind.as_str().replace('_', " "),
+                        change
+                    )),
+                    (None, Some(_)) => {
+                        Some(format!("Town danger affecting safety! 🛡️({})", change))
+                    }
+                    (None, None) => None,
+                };
+                if let Some(message) = message {
+                    messages.push((npc.id.clone(), npc.name.clone(), message));
+                }
+            }
+            let activity = self.activities.get(&npc.id).copied().unwrap_or_default();
+            self.effects.apply_activity(npc, activity);
+            let crises_here = active_at(&self.location_crises, &npc.location_id);
+            location_crises::affect_npc(npc, crises_here);
+            needs::update_emotions(npc);
+            if let Some((kind, safety_cap)) = place {
+                let others = occupancy.get(&npc.location_id).map_or(0, |n| n - 1);
+                self.effects.apply_building(npc, kind, others, safety_cap);
+            }
+            goals::evaluate_goals(npc, goals, day);
+            if let Some(threat_id) = &clearable {
+                goals::consider_clearing(npc, goals, threat_id, day);
+            }
+            if let Some((index, action)) = goals::choose_action(npc, goals) {
+                if let Some(threat_id) = action
+                    .target
+                    .as_ref()
+                    .filter(|_| action.kind == goals::RALLY)
+                {
+                    rallies
+                        .entry(threat_id.clone())
+                        .or_default()
+                        .push(npc.id.clone());
+                }
+                let outcome = goals::execute_action(
+                    npc,
+                    &mut goals[index],
+                    &action,
+                    day,
+                    &self.locations,
+                    &mut self.rng,
+                );
+                memories.extend(outcome.memory);
+                if let Some(to) = outcome.move_to.filter(|to| *to != npc.location_id) {
+                    let to_name = self
+                        .locations
+                        .iter()
+                        .find(|l| l.id == to)
+                        .map_or("?", |l| l.name.as_str());
+                    messages.push((
+                        npc.id.clone(),
+                        npc.name.clone(),
+                        format!("Moved to {}", to_name),
+                    ));
+                    movements.push(NpcMoved {
+                        npc_id: npc.id.clone(),
+                        npc_name: npc.name.clone(),
+                        from_location_id: std::mem::replace(&mut npc.location_id, to.clone()),
+                        to_location_id: to,
+                        activity,
+                    });
+                }
+            }
+
+            if *npc != npc_before {
+                changed_npcs.push(&*npc);
+            }
+            changed_goals.extend(
+                goals
+                    .iter()
+                    .filter(|goal| !goals_before.contains(goal))
+                    .cloned(),
+            );
+            goals.retain(goals::is_open);
+        }
+
+        if let Err(e) = db::save_npcs(&mut self.conn, &changed_npcs) {
+            eprintln!("Failed to save NPCs: {}", e);
+        }
+        if let Err(e) = db::save_goals(&mut self.conn, &changed_goals) {
+            eprintln!("Failed to save goals: {}", e);
+        }
+        if let Err(e) = db::insert_memories(&mut self.conn, &memories) {
+            eprintln!("Failed to save memories: {}", e);
+        }
+        for moved in &movements {
+            if let Some(npc) = self.npcs.iter().find(|npc| npc.id == moved.npc_id) {
+                self.map.place_npc(npc);
+            }
+        }
+        self.movements.extend(movements);
+        self.changed_goals.extend(changed_goals);
+        self.push_messages(messages);
+        rallies
+    }
+
+    /// Parties rallying against a threat clear it once they are strong
+    /// enough; smaller ones keep gathering
+    fn rally_against_threats(&mut self, rallies: BTreeMap<String, Vec<String>>) {
+        let mut messages = Vec::new();
+
+        for (threat_id, npc_ids) in rallies {
+            let Some(threat) = self.map.threat(&threat_id).filter(|t| t.active).cloned() else {
+                continue;
+            };
+            let party: Vec<&Npc> = self
+                .npcs
+                .iter()
+                .filter(|npc| npc_ids.contains(&npc.id))
+                .collect();
+
+            if clearing::party_strength(party.len()) < threat.severity {
+                for npc in &party {
+                    messages.push((
+                        npc.id.clone(),
+                        npc.name.clone(),
+                        format!(
+                            "⚔️ Rallying against {} ({}/{})",
+                            threat.name,
+                            party.len(),
+                            clearing::party_needed(&threat)
+                        ),
+                    ));
+                }
+                continue;
+            }
+
+            let cleared = match self.clear_threat(&threat, &party) {
+                Ok(cleared) => cleared,
+                Err(e) => {
+                    eprintln!("Failed to clear {}: {}", threat.name, e);
+                    continue;
+                }
+            };
+            for npc in &party {
+                messages.push((
+                    npc.id.clone(),
+                    npc.name.clone(),
+                    format!("⚔️ Cleared {}!", threat.name),
+                ));
+            }
+            if let Err(e) = self.apply_cleared(cleared) {
+                eprintln!("Failed to save cleared threat: {}", e);
+            }
+        }
+
+        self.push_messages(messages);
+    }
+
+    /// clearing::clear with the NPCs the threat reached and the location
+    /// closest to it
+    fn clear_threat(&self, threat: &ThreatSource, party: &[&Npc]) -> AppResult<clearing::Cleared> {
+        let location_id = clearing_location(&self.map, threat, party)?;
+        let threatened = self.map.npcs_threatened_by(threat);
+        let relieved: Vec<&Npc> = self
+            .npcs
+            .iter()
+            .filter(|npc| threatened.contains(&npc.id) && !party.iter().any(|p| p.id == npc.id))
+            .collect();
+
+        Ok(clearing::clear(
+            threat,
+            party,
+            &relieved,
+            (self.clock.day, self.clock.hour),
+            &location_id,
+        ))
+    }
+
+    /// Take a cleared threat off the map and record it
+    fn apply_cleared(&mut self, cleared: clearing::Cleared) -> rusqlite::Result<()> {
+        let (towns, goals) = save_cleared(
+            &mut self.conn,
+            &mut self.map,
+            &cleared,
+            self.goals.values_mut().flatten(),
+        )?;
+        for goals in self.goals.values_mut() {
+            goals.retain(goals::is_open);
+        }
+
+        self.changed_towns.extend(towns);
+        self.changed_goals.extend(goals);
+        self.new_events.push(cleared.event);
+        self.cleared_threats.push(cleared.threat);
+        Ok(())
+    }
+
+    /// detectCrisis for every living NPC. Entering or leaving a crisis (a
+    /// different crisis counts as both) is recorded as an Event.
+    fn update_crises(&mut self) {
+        let clock = (self.clock.day, self.clock.hour);
+        let mut events = Vec::new();
+        let mut messages = Vec::new();
+
+        for npc in &self.npcs {
+            let current = if npc.state == "dead" {
+                None
+            } else {
+                crisis::detect(npc)
+            };
+            let previous = self.crises.get(&npc.id);
+            if previous.map(|crisis| crisis.kind) == current.as_ref().map(|crisis| crisis.kind) {
+                continue;
+            }
+
+            if let Some(previous) = self.crises.remove(&npc.id) {
+                let event = crisis::crisis_event(npc, &previous, false, clock);
+                self.crisis_changes.push(CrisisChanged {
+                    crisis: previous,
+                    active: false,
+                    event_id: event.id.clone(),
+                });
+                events.push(event);
+            }
+            if let Some(current) = current {
+                let event = crisis::crisis_event(npc, &current, true, clock);
+                messages.push((
+                    npc.id.clone(),
+                    npc.name.clone(),
+                    format!("🚨 {}", event.description),
+                ));
+                self.crisis_changes.push(CrisisChanged {
+                    crisis: current.clone(),
+                    active: true,
+                    event_id: event.id.clone(),
+                });
+                self.crises.insert(npc.id.clone(), current);
+                events.push(event);
+            }
+        }
+
+        if let Err(e) = db::insert_events(&mut self.conn, &events) {
+            eprintln!("Failed to save crisis events: {}", e);
+        }
+        self.new_events.extend(events);
+        self.push_messages(messages);
+    }
+
+    /// Location crises for an hour: each one dies down (faster for every NPC
+    /// there fighting it) and may spread to the locations around it, and new
+    /// ones may break out. The locations they are at get their food, shelter
+    /// and danger updated.
+    fn update_location_crises(&mut self) {
+        let clock = (self.clock.day, self.clock.hour);
+        let mut events = Vec::new();
+        let mut messages = Vec::new();
+        // To tell which crises changed this hour; new ones are appended
+        let severities: Vec<f64> = self.location_crises.iter().map(|c| c.severity).collect();
+
+        for crisis in &mut self.location_crises {
+            let present = present_at(&self.npcs, &crisis.location_id);
+            if !location_crises::die_down(crisis, present.len(), clock.0) {
+                continue;
+            }
+            if let Some(location) = self.locations.iter().find(|l| l.id == crisis.location_id) {
+                events.push(location_crises::crisis_event(
+                    crisis, location, &present, clock,
+                ));
+            }
+        }
+
+        let mut started: Vec<LocationCrisis> = Vec::new();
+        for source in self
+            .location_crises
+            .iter()
+            .filter(|crisis| !crisis.resolved)
+        {
+            let Some(from) = self.locations.iter().find(|l| l.id == source.location_id) else {
+                continue;
+            };
+            for to in &self.locations {
+                let chance = location_crises::spread_chance(source, from, to);
+                if chance <= 0.0 || self.rng.next_f64() >= chance {
+                    continue;
+                }
+                // Crises resolved this hour still hold how the location was before
+                let here: Vec<&LocationCrisis> = self
+                    .location_crises
+                    .iter()
+                    .chain(&started)
+                    .filter(|crisis| crisis.location_id == to.id)
+                    .collect();
+                if here
+                    .iter()
+                    .any(|crisis| !crisis.resolved && crisis.kind == source.kind)
+                {
+                    continue;
+                }
+                let severity = location_crises::spread_severity(source);
+                let crisis =
+                    location_crises::start(source.kind, to, severity, Some(source), &here, clock);
+                started.push(crisis);
+            }
+        }
+        for location in &self.locations {
+            let here: Vec<&LocationCrisis> = self
+                .location_crises
+                .iter()
+                .chain(&started)
+                .filter(|crisis| crisis.location_id == location.id)
+                .collect();
+            if here.iter().any(|crisis| !crisis.resolved) {
+                continue;
+            }
+            let kind = LocationCrisisType::ALL.into_iter().find(|&kind| {
+                location_crises::can_break_out(kind, location)
+                    && self.rng.next_f64() < location_crises::outbreak_chance(kind)
+            });
+            if let Some(kind) = kind {
+                let severity = location_crises::outbreak_severity(self.rng.next_f64());
+                let crisis = location_crises::start(kind, location, severity, None, &here, clock);
+                started.push(crisis);
+            }
+        }
+
+        for crisis in &started {
+            let Some(location) = self.locations.iter().find(|l| l.id == crisis.location_id) else {
+                continue;
+            };
+            let present = present_at(&self.npcs, &location.id);
+            let message = location_crises::started_message(crisis, location);
+            for npc in &present {
+                messages.push((npc.id.clone(), npc.name.clone(), message.clone()));
+            }
+            events.push(location_crises::crisis_event(
+                crisis, location, &present, clock,
+            ));
+        }
+        self.location_crises.extend(started);
+
+        let mut changed_locations = Vec::new();
+        for location in &mut self.locations {
+            let here: Vec<&LocationCrisis> = self
+                .location_crises
+                .iter()
+                .filter(|crisis| crisis.location_id == location.id)
+                .collect();
+            if location_crises::affect_location(location, &here) {
+                changed_locations.push(location.clone());
+            }
+        }
+
+        let changed_crises: Vec<&LocationCrisis> = self
+            .location_crises
+            .iter()
+            .enumerate()
+            .filter(|(i, crisis)| {
+                severities
+                    .get(*i)
+                    .is_none_or(|&severity| crisis.resolved || crisis.severity != severity)
+            })
+            .map(|(_, crisis)| crisis)
+            .collect();
+        if let Err(e) = db::save_location_crises(&mut self.conn, &changed_crises) {
+            eprintln!("Failed to save location crises: {}", e);
+        }
+        if let Err(e) = db::save_location_effects(
+            &mut self.conn,
+            &changed_locations.iter().collect::<Vec<_>>(),
+        ) {
+            eprintln!("Failed to save locations: {}", e);
+        }
+        if let Err(e) = db::insert_events(&mut self.conn, &events) {
+            eprintln!("Failed to save location crisis events: {}", e);
+        }
+
+        self.changed_location_crises
+            .extend(changed_crises.into_iter().cloned());
+        self.location_crises.retain(|crisis| !crisis.resolved);
+        self.changed_locations.extend(changed_locations);
+        self.new_events.extend(events);
+        self.push_messages(messages);
+    }
+
+    /// Start a crisis at one of the world's locations (start_location_crisis)
+    fn start_location_crisis(
+        &mut self,
+        location_id: &str,
+        kind: LocationCrisisType,
+        severity: f64,
+    ) -> AppResult<LocationCrisis> {
+        let clock = (self.clock.day, self.clock.hour);
+        let location = self
+            .locations
+            .iter_mut()
+            .find(|l| l.id == location_id)
+            .ok_or_else(|| AppError::not_found("Location", location_id))?;
+        let here = active_at(&self.location_crises, location_id);
+        let present = present_at(&self.npcs, location_id);
+        let (crisis, event) = begin_location_crisis(
+            &mut self.conn,
+            location,
+            &here,
+            (kind, severity),
+            &present,
+            clock,
+        )?;
+
+        let message = location_crises::started_message(&crisis, location);
+        let messages = present
+            .iter()
+            .map(|npc| (npc.id.clone(), npc.name.clone(), message.clone()))
+            .collect();
+        self.changed_locations.push(location.clone());
+        self.changed_location_crises.push(crisis.clone());
+        self.location_crises.push(crisis.clone());
+        self.new_events.push(event);
+        self.push_messages(messages);
+        Ok(crisis)
+    }
+
+    /// Move every mobile threat along its route or wander for an hour, then
+    /// put the moved ones on the map (recalculating town safety) and save them
+    fn move_threats(&mut self) {
+        let mut moved = Vec::new();
+        for threat in self.map.threats() {
+            let mut threat = threat.clone();
+            let from = (threat.x, threat.y);
+            if patrols::advance(&mut threat, &mut self.rng) {
+                threat.updated_at = db::now_millis();
+                moved.push((threat, from));
+            }
+        }
+        if moved.is_empty() {
+            return;
+        }
+
+        if let Err(e) = db::save_threats(
+            &mut self.conn,
+            &moved.iter().map(|(threat, _)| threat).collect::<Vec<_>>(),
+        ) {
+            eprintln!("Failed to save threats: {}", e);
+        }
+
+        let mut towns = Vec::new();
+        for (threat, (from_x, from_y)) in moved {
+            self.threat_moves.push(ThreatMoved {
+                threat: threat.clone(),
+                from_x,
+                from_y,
+            });
+            towns.extend(self.map.update_threat(threat));
+        }
+
+        let towns = WorldDiff::latest_by_id(towns, |town| &town.id);
+        if let Err(e) = db::save_towns(&mut self.conn, &towns.iter().collect::<Vec<_>>()) {
+            eprintln!("Failed to save towns: {}", e);
+        }
+        self.changed_towns.extend(towns);
+    }
+
+    /// Apply a threat that was added, moved or cleared and save it, along
+    /// with the towns whose safety it changed
+    fn update_threat(&mut self, threat: ThreatSource) -> rusqlite::Result<()> {
+        db::save_threats(&mut self.conn, &[&threat])?;
+        let towns = self.map.update_threat(threat);
+        db::save_towns(&mut self.conn, &towns.iter().collect::<Vec<_>>())?;
+        self.changed_towns.extend(towns);
+        Ok(())
+    }
+
+    /// Daily cycle for the hour just started: checkpoint decisions at 6, 12,
+    /// 18 and 22, activity changes in between
+    fn run_daily_cycle(&mut self) {
+        let output = DailyCycle {
+            day: self.clock.day,
+            hour: self.clock.hour,
+            activities: &mut self.activities,
+            schedules: &self.schedules,
+            locations: &self.locations,
+        }
+        .on_hour_change(&mut self.npcs);
+
+        let moved: Vec<&Npc> = self
+            .npcs
+            .iter()
+            .filter(|npc| output.movements.iter().any(|m| m.npc_id == npc.id))
+            .collect();
+        for npc in &moved {
+            self.map.place_npc(npc);
+        }
+        if let Err(e) = db::save_npcs(&mut self.conn, &moved) {
+            eprintln!("Failed to save NPC locations: {}", e);
+        }
+
+        self.checkpoints.extend(output.checkpoint);
+        self.messages.extend(output.messages);
+        self.movements.extend(output.movements);
+    }
+
+    /// Deliver finished production, then start a task for every working NPC
+    /// whose occupation has a recipe. Returns what was delivered, by NPC id,
+    /// for the market to pay for.
+    fn update_production(&mut self) -> Vec<(String, ResourceType, i32)> {
+        let now = self.clock.day as i64 * 24 + self.clock.hour as i64;
+        let mut messages = Vec::new();
+        // Tasks started or finished this hour are pushed past here
+        let produced = self.production.len();
+        let mut delivered = Vec::new();
+
+        for (task, stored) in self.economy.update_production(now) {
+            let info = task.resource.info();
+            let mut text = format!("Finished {}x {} {}", task.amount, info.name, info.emoji);
+            if stored < task.amount {
+                text.push_str(&format!(" (storage full, only {} stored)", stored));
+            }
+            messages.push((task.npc_id.clone(), task.npc_name.clone(), text));
+            delivered.push((task.npc_id.clone(), task.resource, stored));
+            self.production.push(task);
+        }
+
+        for npc in self.npcs.iter().filter(|npc| npc.state != "dead") {
+            let working = self.activities.get(&npc.id) == Some(&Activity::Working);
+            let Some(recipe) = resources::recipe_for(&self.economy.recipes, &npc.occupation) else {
+                continue;
+            };
+            let recipe = recipe.clone();
+            if !working || self.economy.active_task(&npc.id).is_some() {
+                continue;
+            }
+
+            let text = match self.economy.start_production(
+                db::new_id(),
+                npc,
+                &recipe,
+                now,
+                resources::DEFAULT_STORAGE,
+            ) {
+                Ok(task) => {
+                    self.production.push(task.clone());
+                    let info = recipe.produces.info();
+                    format!(
+                        "Started making {}x {} {}",
+                        recipe.amount, info.name, info.emoji
+                    )
+                }
+                Err(ProductionBlocked::NoStorage) => {
+                    "Cannot work! Nowhere to store goods".to_string()
+                }
+                Err(ProductionBlocked::MissingMaterials(missing)) => {
+                    let missing: Vec<String> = missing
+                        .iter()
+                        .map(|(resource, amount)| format!("{} {}", amount, resource.info().name))
+                        .collect();
+                    format!("Cannot start: Missing materials! ({})", missing.join(", "))
+                }
+            };
+            messages.push((npc.id.clone(), npc.name.clone(), text));
+        }
+
+        // Starting a task takes its materials, finishing one stores the goods
+        if self.production.len() > produced {
+            let storages: Vec<&Storage> = self.economy.storages.iter().collect();
+            if let Err(e) = db::save_storages(&mut self.conn, &storages) {
+                eprintln!("Failed to save storages: {}", e);
+            }
+        }
+        if let Err(e) = db::save_production_tasks(&mut self.conn, &self.production[produced..]) {
+            eprintln!("Failed to save production tasks: {}", e);
+        }
+
+        self.push_messages(messages);
+        delivered
+    }
+
+    /// Pay NPCs for what they delivered, then let eating NPCs buy their meal
+    /// (from the market if it has food, else from the warehouse)
+    fn update_market(&mut self, delivered: Vec<(String, ResourceType, i32)>) {
+        let market = Market::new(&self.economy, &self.npcs);
+        let mut messages = Vec::new();
+        let mut traded: Vec<String> = Vec::new();
+        let mut touched_storages: Vec<String> = Vec::new();
+
+        for (npc_id, resource, amount) in delivered.into_iter().filter(|(_, _, n)| *n > 0) {
+            let Some(npc) = self.npcs.iter_mut().find(|npc| npc.id == npc_id) else {
+                continue;
+            };
+            let coins = market.sell(npc, resource, amount);
+            let info = resource.info();
+            messages.push((
+                npc.id.clone(),
+                npc.name.clone(),
+                format!(
+                    "Sold {}x {} {} for {} coins",
+                    amount, info.name, info.emoji, coins
+                ),
+            ));
+            traded.push(npc_id);
+        }
+
+        for npc in self.npcs.iter_mut().filter(|npc| npc.state != "dead") {
+            if self.activities.get(&npc.id) != Some(&Activity::Eating) {
+                continue;
+            }
+            let Some(storage) = [market::MARKET_STORAGE, resources::DEFAULT_STORAGE]
+                .iter()
+                .filter_map(|key| self.economy.storages.iter().position(|s| s.key == *key))
+                .find(|&index| self.economy.storages[index].has(ResourceType::Food, market::MEAL))
+                .map(|index| &mut self.economy.storages[index])
+            else {
+                continue;
+            };
+
+            if let Some(coins) = market.buy(npc, storage, ResourceType::Food, market::MEAL) {
+                messages.push((
+                    npc.id.clone(),
+                    npc.name.clone(),
+                    format!("Bought a meal for {} coins", coins),
+                ));
+                traded.push(npc.id.clone());
+                touched_storages.push(storage.id.clone());
+            }
+        }
+
+        let npcs: Vec<&Npc> = self
+            .npcs
+            .iter()
+            .filter(|npc| traded.contains(&npc.id))
+            .collect();
+        if let Err(e) = db::save_npcs(&mut self.conn, &npcs) {
+            eprintln!("Failed to save NPC wealth: {}", e);
+        }
+        let storages: Vec<&Storage> = self
+            .economy
+            .storages
+            .iter()
+            .filter(|storage| touched_storages.contains(&storage.id))
+            .collect();
+        if let Err(e) = db::save_storages(&mut self.conn, &storages) {
+            eprintln!("Failed to save storages: {}", e);
+        }
+
+        self.push_messages(messages);
+    }
+
+    /// Queue (npc id, npc name, text) messages for the hour just started
+    fn push_messages(&mut self, messages: Vec<(String, String, String)>) {
+        let (day, hour) = (self.clock.day, self.clock.hour);
+        self.messages.extend(
+            messages
+                .into_iter()
+                .map(|(npc_id, npc_name, message)| NpcMessage {
+                    npc_id,
+                    npc_name,
+                    day,
+                    hour,
+                    message,
+                }),
+        );
+    }
+
+    /// Simulate a single in-game hour
+    fn advance_hour(&mut self) {
+        self.tick_count += 1;
+
+        self.move_threats();
+        self.update_location_crises();
+        let rallies = self.update_npcs();
+        self.rally_against_threats(rallies);
+        self.update_crises();
+
+        self.clock.advance();
+        self.run_daily_cycle();
+        let delivered = self.update_production();
+        self.update_market(delivered);
+
+        if let Err(e) =
+            db::save_world_clock(&self.conn, &self.world_id, self.clock.day, self.clock.hour)
+        {
+            eprintln!("Failed to save world time: {}", e);
+        }
+    }
+}
+
+enum Control {
+    Stop,
+}
+
+/// Storages, recipes and open production tasks of the world. A world without
+/// any storages or recipes gets the default ones, saved right away so they
+/// have stable ids; imported worlds keep exactly what their file listed.
+fn load_economy(conn: &mut Connection, world: &World) -> rusqlite::Result<Economy> {
+    let mut storages = db::list_storages(conn, &world.id)?;
+    if storages.is_empty() && !world.imported {
+        storages = resources::default_storages(&world.id);
+        db::save_storages(conn, &storages.iter().collect::<Vec<_>>())?;
+    }
+    let mut recipes = db::list_production_recipes(conn, &world.id)?;
+    if recipes.is_empty() && !world.imported {
+        recipes = resources::default_recipes(&world.id);
+        db::save_production_recipes(conn, &recipes.iter().collect::<Vec<_>>())?;
+    }
+    let tasks = db::list_production_tasks(conn, &world.id, false)?;
+
+    Ok(Economy {
+        storages,
+        recipes,
+        tasks,
+    })
+}
+
+/// Prices of a world that isn't loaded, from what is saved
+fn load_market(conn: &Connection, world: &World) -> rusqlite::Result<Market> {
+    let mut recipes = db::list_production_recipes(conn, &world.id)?;
+    if recipes.is_empty() && !world.imported {
+        // Saved the first time the world runs
+        recipes = resources::default_recipes(&world.id);
+    }
+    let economy = Economy {
+        storages: db::list_storages(conn, &world.id)?,
+        recipes,
+        tasks: Vec::new(),
+    };
+    let npcs = db::list_npcs(conn, &world.id)?;
+    Ok(Market::new(&economy, &npcs))
+}
+
+/// Living NPCs at the location
+fn present_at<'a>(npcs: &'a [Npc], location_id: &str) -> Vec<&'a Npc> {
+    npcs.iter()
+        .filter(|npc| npc.state != "dead" && npc.location_id == location_id)
+        .collect()
+}
+
+/// Unresolved crises at the location
+fn active_at<'a>(
+    crises: impl IntoIterator<Item = &'a LocationCrisis>,
+    location_id: &str,
+) -> Vec<&'a LocationCrisis> {
+    crises
+        .into_iter()
+        .filter(|crisis| !crisis.resolved && crisis.location_id == location_id)
+        .collect()
+}
+
+/// Start a crisis at `location` and save it, the location and its Event.
+/// `here` are the crises already going on there.
+fn begin_location_crisis(
+    conn: &mut Connection,
+    location: &mut Location,
+    here: &[&LocationCrisis],
+    (kind, severity): (LocationCrisisType, f64),
+    present: &[&Npc],
+    clock: (i32, i32),
+) -> AppResult<(LocationCrisis, Event)> {
+    if !(severity > 0.0 && severity <= 100.0) {
+        return Err(AppError::invalid_argument(
+            "severity",
+            "must be above 0 and at most 100",
+        ));
+    }
+    if here.iter().any(|crisis| crisis.kind == kind) {
+        return Err(AppError::invalid_argument(
+            "type",
+            format!("there already is a {} at {}", kind, location.name),
+        ));
+    }
+
+    let crisis = location_crises::start(kind, location, severity, None, here, clock);
+    let mut all = here.to_vec();
+    all.push(&crisis);
+    location_crises::affect_location(location, &all);
+    let event = location_crises::crisis_event(&crisis, location, present, clock);
+
+    db::save_location_crises(conn, &[&crisis])?;
+    db::save_location_effects(conn, &[location])?;
+    db::insert_events(conn, std::slice::from_ref(&event))?;
+    Ok((crisis, event))
+}
+
+/// Crises of the living NPCs
+fn active_crises(npcs: &[Npc]) -> impl Iterator<Item = Crisis> + '_ {
+    npcs.iter()
+        .filter(|npc| npc.state != "dead")
+        .filter_map(crisis::detect)
+}
+
+/// Threats of the world; a world without any gets THREAT_SOURCES unless it
+/// was imported
+fn load_threats(conn: &mut Connection, world: &World) -> rusqlite::Result<Vec<ThreatSource>> {
+    let mut threats = db::list_threats(conn, &world.id)?;
+    if threats.is_empty() && !world.imported {
+        threats = threats::default_threats(&world.id);
+        db::save_threats(conn, &threats.iter().collect::<Vec<_>>())?;
+    }
+    Ok(threats)
+}
+
+/// Towns of the world; a world without any gets TOWNS unless it was imported
+fn load_towns(conn: &mut Connection, world: &World) -> rusqlite::Result<Vec<Town>> {
+    let mut towns = db::list_towns(conn, &world.id)?;
+    if towns.is_empty() && !world.imported {
+        towns = towns::default_towns(&world.id);
+        db::save_towns(conn, &towns.iter().collect::<Vec<_>>())?;
+    }
+    Ok(towns)
+}
+
+/// Threats, towns, locations and NPCs of the world on the map. Town safety
+/// is recalculated once here so freshly seeded threats and towns agree.
+fn load_world_map(conn: &mut Connection, world: &World) -> rusqlite::Result<WorldMap> {
+    let threats = load_threats(conn, world)?;
+    let towns = load_towns(conn, world)?;
+    let locations = db::list_locations(conn, &world.id)?;
+    let npcs = db::list_npcs(conn, &world.id)?;
+
+    let mut map = WorldMap::new(threats, towns, &locations, &npcs);
+    let changed = map.recalculate_towns();
+    db::save_towns(conn, &changed.iter().collect::<Vec<_>>())?;
+    Ok(map)
+}
+
+/// Save a cleared threat with its event and memories, the towns it made
+/// safer and the clear_threat goals it completed. Returns those towns and goals.
+fn save_cleared<'a>(
+    conn: &mut Connection,
+    map: &mut WorldMap,
+    cleared: &clearing::Cleared,
+    goals: impl IntoIterator<Item = &'a mut Goal>,
+) -> rusqlite::Result<(Vec<Town>, Vec<Goal>)> {
+    db::save_threats(conn, &[&cleared.threat])?;
+    let towns = map.update_threat(cleared.threat.clone());
+    db::save_towns(conn, &towns.iter().collect::<Vec<_>>())?;
+    db::insert_events(conn, std::slice::from_ref(&cleared.event))?;
+    db::insert_memories(conn, &cleared.memories)?;
+
+    let mut completed = Vec::new();
+    for goal in goals {
+        if goals::is_open(goal)
+            && goal.kind == goals::CLEAR_THREAT
+            && goal.target == cleared.threat.id
+        {
+            goal.completed = true;
+            completed.push(goal.clone());
+        }
+    }
+    db::save_goals(conn, &completed)?;
+    Ok((towns, completed))
+}
+
+fn check_clearable(threat: &ThreatSource) -> AppResult<()> {
+    if !threat.active {
+        return Err(AppError::invalid_argument(
+            "threatId",
+            "threat is already cleared",
+        ));
+    }
+    if threat.kind == ThreatType::Player {
+        return Err(AppError::invalid_argument(
+            "threatId",
+            "the player can't be cleared",
+        ));
+    }
+    Ok(())
+}
+
+/// Where a clearing is recorded: the location closest to the threat, else
+/// where the party stands. Its Event can't be saved without one.
+fn clearing_location(map: &WorldMap, threat: &ThreatSource, party: &[&Npc]) -> AppResult<String> {
+    map.nearest_location(threat.x, threat.y)
+        .or_else(|| party.first().map(|npc| npc.location_id.clone()))
+        .filter(|location_id| !location_id.is_empty())
+        .ok_or_else(|| {
+            AppError::invalid_argument("threatId", "there is no location to record the clearing at")
+        })
+}
+
+/// A mobile threat moved to (x, y)
+fn moved_threat(mut threat: ThreatSource, x: f64, y: f64) -> AppResult<ThreatSource> {
+    if !threat.mobile {
+        return Err(AppError::invalid_argument(
+            "threatId",
+            "threat is not mobile",
+        ));
+    }
+    if !x.is_finite() || !y.is_finite() {
+        return Err(AppError::invalid_argument("x", "position must be finite"));
+    }
+
+    threat.x = x;
+    threat.y = y;
+    threat.updated_at = db::now_millis();
+    Ok(threat)
+}
+
+/// Handle to the running loop thread
+struct Worker {
+    control: Sender<Control>,
+    thread: JoinHandle<()>,
+}
+
+/// Clears the fast-forward flag when the run ends, however it ends
+struct FastForwarding<'a>(&'a AtomicBool);
+
+impl Drop for FastForwarding<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+pub struct SimulationState {
+    engine: Arc<Mutex<Option<Engine>>>,
+    worker: Mutex<Option<Worker>>,
+    /// Kept here so it survives stop/start
+    time_scale: Mutex<f64>,
+    /// A fast-forward is running; nothing else may tick the engine
+    fast_forwarding: AtomicBool,
+    cancel_fast_forward: AtomicBool,
+}
+
+impl SimulationState {
+    pub fn new() -> Self {
+        Self {
+            engine: Arc::new(Mutex::new(None)),
+            worker: Mutex::new(None),
+            time_scale: Mutex::new(1.0),
+            fast_forwarding: AtomicBool::new(false),
+            cancel_fast_forward: AtomicBool::new(false),
+        }
+    }
+
+    /// Load the world from the database and spawn the tick thread.
+    /// Each tick's diff is emitted through `app`.
+    pub fn start<R: Runtime>(
+        &self,
+        app: AppHandle<R>,
+        db: &Database,
+        world_id: Option<&str>,
+        config: SimulationConfig,
+    ) -> AppResult<()> {
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_some() || self.fast_forwarding.load(Ordering::SeqCst) {
+            return Err(AppError::SimulationAlreadyRunning);
+        }
+
+        let time_scale = *self.time_scale.lock().unwrap();
+        *self.engine.lock().unwrap() = Some(Engine::load(db, world_id, config, time_scale)?);
+
+        let (control, rx) = mpsc::channel();
+        let engine = Arc::clone(&self.engine);
+
+        let thread = thread::spawn(move || loop {
+            // Re-read every tick so set_time_scale applies immediately
+            let interval = engine
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(Engine::tick_interval)
+                .unwrap_or(Duration::from_millis(config.tick_speed_ms));
+
+            // Sleeping on the channel lets stop() interrupt a long tick interval
+            match rx.recv_timeout(interval) {
+                Ok(Control::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    // Release the engine before emitting so commands are not blocked
+                    let diff = engine
+                        .lock()
+                        .unwrap()
+                        .as_mut()
+                        .filter(|engine| !engine.paused)
+                        .map(Engine::tick);
+                    if let Some(diff) = diff {
+                        events::emit_diff(&app, &diff);
+                    }
+                }
+            }
+        });
+
+        *worker = Some(Worker { control, thread });
+        Ok(())
+    }
+
+    /// Stop the tick thread and wait for it to exit. Returns false if it was not running.
+    pub fn stop(&self) -> bool {
+        let Some(worker) = self.worker.lock().unwrap().take() else {
+            return false;
+        };
+
+        let _ = worker.control.send(Control::Stop);
+        let _ = worker.thread.join();
+        true
+    }
+
+    /// Hold the tick loop without stopping its thread. Returns false if not running.
+    pub fn pause(&self) -> bool {
+        self.set_paused(true)
+    }
+
+    /// Continue a paused tick loop. Returns false if not paused.
+    pub fn resume(&self) -> bool {
+        self.set_paused(false)
+    }
+
+    fn set_paused(&self, paused: bool) -> bool {
+        let worker = self.worker.lock().unwrap();
+        let mut engine = self.engine.lock().unwrap();
+        match engine.as_mut() {
+            // Resuming would tick in between the fast-forward's days
+            Some(_) if !paused && self.fast_forwarding.load(Ordering::SeqCst) => false,
+            Some(engine) if worker.is_some() && engine.paused != paused => {
+                engine.paused = paused;
+                // Don't count the pause as one long tick
+                engine.last_tick_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Run exactly `n_ticks` hours on the calling thread and return the
+    /// combined diff. Only allowed while paused or stopped; when nothing is
+    /// loaded yet the first world is loaded from the database.
+    pub fn step(&self, db: &Database, n_ticks: u32) -> AppResult<WorldDiff> {
+        let worker = self.worker.lock().unwrap();
+        let mut engine = self.engine.lock().unwrap();
+        let engine = self.idle_engine(&worker, &mut engine, db)?;
+        Ok(engine.run_ticks(n_ticks))
+    }
+
+    /// Multiply the tick rate, e.g. 2.0 runs twice as many ticks per second
+    pub fn set_time_scale(&self, multiplier: f64) {
+        *self.time_scale.lock().unwrap() = multiplier;
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            engine.time_scale = multiplier;
+        }
+    }
+
+    /// Run headless until `target` (while paused or stopped), at most
+    /// MAX_FAST_FORWARD_DAYS ahead. Nothing is emitted per tick; `on_progress`
+    /// is called once per in-game day. The engine is only locked for one day
+    /// at a time so commands keep answering, and `cancel_fast_forward` stops
+    /// the run at the next midnight. Returns the combined diff of what ran.
+    pub fn fast_forward(
+        &self,
+        db: &Database,
+        target: WorldClock,
+        mut on_progress: impl FnMut(FastForwardProgress),
+    ) -> AppResult<WorldDiff> {
+        let (mut progress, npcs_before) = {
+            let worker = self.worker.lock().unwrap();
+            let mut engine = self.engine.lock().unwrap();
+            let engine = self.idle_engine(&worker, &mut engine, db)?;
+
+            let hours = engine.clock.hours_until(target);
+            if hours <= 0 {
+                return Err(AppError::invalid_argument(
+                    "day",
+                    format!(
+                        "Day {}, {:02}:00 is not after the current time (Day {}, {:02}:00)",
+                        target.day, target.hour, engine.clock.day, engine.clock.hour
+                    ),
+                ));
+            }
+            if hours > i64::from(MAX_FAST_FORWARD_DAYS) * 24 {
+                return Err(AppError::invalid_argument(
+                    "day",
+                    format!(
+                        "must be at most {} days after the current day ({})",
+                        MAX_FAST_FORWARD_DAYS, engine.clock.day
+                    ),
+                ));
+            }
+
+            // Checked under the worker lock, like every other run
+            self.fast_forwarding.store(true, Ordering::SeqCst);
+            self.cancel_fast_forward.store(false, Ordering::SeqCst);
+            let progress = FastForwardProgress {
+                day: engine.clock.day,
+                hour: engine.clock.hour,
+                ticks_done: 0,
+                ticks_total: hours as u64,
+            };
+            (progress, engine.npcs.clone())
+        };
+        let _running = FastForwarding(&self.fast_forwarding);
+
+        while progress.ticks_done < progress.ticks_total
+            && !self.cancel_fast_forward.load(Ordering::SeqCst)
+        {
+            {
+                let mut engine = self.engine.lock().unwrap();
+                let engine = engine
+                    .as_mut()
+                    .ok_or_else(|| AppError::not_found("World", "(fast-forward)"))?;
+                progress.ticks_done +=
+                    engine.fast_forward_day(progress.ticks_total - progress.ticks_done)?;
+                progress.day = engine.clock.day;
+                progress.hour = engine.clock.hour;
+            }
+            on_progress(progress);
+        }
+
+        let mut engine = self.engine.lock().unwrap();
+        let engine = engine
+            .as_mut()
+            .ok_or_else(|| AppError::not_found("World", "(fast-forward)"))?;
+        Ok(engine.take_diff(&npcs_before))
+    }
+
+    /// Stop a fast-forward at the next midnight. Returns false if none is running.
+    pub fn cancel_fast_forward(&self) -> bool {
+        if !self.fast_forwarding.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.cancel_fast_forward.store(true, Ordering::SeqCst);
+        true
+    }
+
+    /// The engine for synchronous runs: rejected while the loop is ticking,
+    /// loaded from the first world if nothing has been loaded yet
+    fn idle_engine<'a>(
+        &self,
+        worker: &Option<Worker>,
+        engine: &'a mut Option<Engine>,
+        db: &Database,
+    ) -> AppResult<&'a mut Engine> {
+        if self.fast_forwarding.load(Ordering::SeqCst) {
+            return Err(AppError::SimulationAlreadyRunning);
+        }
+        if let Some(engine) = engine.as_ref() {
+            if worker.is_some() && !engine.paused {
+                return Err(AppError::SimulationAlreadyRunning);
+            }
+        }
+
+        if engine.is_none() {
+            let time_scale = *self.time_scale.lock().unwrap();
+            *engine = Some(Engine::load(
+                db,
+                None,
+                SimulationConfig::default(),
+                time_scale,
+            )?);
+        }
+        Ok(engine.as_mut().unwrap())
+    }
+
+    /// What the NPC would do right now and why. Uses the running world when
+    /// it holds the NPC, the database otherwise; nothing is changed.
+    pub fn decide_npc_activity(&self, db: &Database, npc_id: &str) -> AppResult<NpcDecision> {
+        if let Some(engine) = self.engine.lock().unwrap().as_ref() {
+            if let Some(npc) = engine.npcs.iter().find(|npc| npc.id == npc_id) {
+                let places = Places {
+                    locations: &engine.locations,
+                    schedule: engine
+                        .schedules
+                        .get(npc_id)
+                        .map(Vec::as_slice)
+                        .unwrap_or(&[]),
+                };
+                return Ok(behavior::decide_action(npc, &places, engine.clock.hour));
+            }
+        }
+
+        let conn = db.connect()?;
+        let npc = db::get_npc(&conn, npc_id)?.ok_or_else(|| AppError::not_found("NPC", npc_id))?;
+        let hour = db::get_world(&conn, &npc.world_id)?.map_or(0, |world| world.current_hour);
+        let locations = db::list_locations(&conn, &npc.world_id)?;
+        let schedule = db::list_schedule(&conn, npc_id)?;
+        let places = Places {
+            locations: &locations,
+            schedule: &schedule,
+        };
+        Ok(behavior::decide_action(&npc, &places, hour))
+    }
+
+    /// Move up to `amount` of `resource` between two storages of the same
+    /// world. Goes through the running world when it holds the storages, so
+    /// the next tick sees the change. Returns the amount moved (0 if the
+    /// source had none or the destination is full).
+    pub fn transfer_resources(
+        &self,
+        db: &Database,
+        from_id: &str,
+        to_id: &str,
+        resource: ResourceType,
+        amount: i32,
+    ) -> AppResult<i32> {
+        if amount <= 0 {
+            return Err(AppError::invalid_argument(
+                "amount",
+                "must be greater than 0",
+            ));
+        }
+        if from_id == to_id {
+            return Err(AppError::invalid_argument(
+                "toStorageId",
+                "must differ from fromStorageId",
+            ));
+        }
+
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            if let Some((from, to)) = engine.economy.storage_pair_mut(from_id, to_id) {
+                let moved = resources::transfer(from, to, resource, amount);
+                let (from, to) = (&*from, &*to);
+                db::save_storages(&mut engine.conn, &[from, to])?;
+                return Ok(moved);
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let mut from = db::get_storage(&conn, from_id)?
+            .ok_or_else(|| AppError::not_found("Storage", from_id))?;
+        let mut to =
+            db::get_storage(&conn, to_id)?.ok_or_else(|| AppError::not_found("Storage", to_id))?;
+        if from.world_id != to.world_id {
+            return Err(AppError::invalid_argument(
+                "toStorageId",
+                "must be in the same world as fromStorageId",
+            ));
+        }
+        let moved = resources::transfer(&mut from, &mut to, resource, amount);
+        db::save_storages(&mut conn, &[&from, &to])?;
+        Ok(moved)
+    }
+
+    /// Current prices of every resource in the world (the running one, or
+    /// the first one when `world_id` is None)
+    pub fn market_prices(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+    ) -> AppResult<Vec<MarketPrice>> {
+        if let Some(engine) = self.engine.lock().unwrap().as_ref() {
+            if world_id.is_none_or(|id| id == engine.world_id) {
+                return Ok(Market::new(&engine.economy, &engine.npcs).prices());
+            }
+        }
+
+        let conn = db.connect()?;
+        let world = match world_id {
+            Some(id) => db::get_world(&conn, id)?,
+            None => db::first_world(&conn)?,
+        }
+        .ok_or_else(|| AppError::not_found("World", world_id.unwrap_or("(first)")))?;
+        Ok(load_market(&conn, &world)?.prices())
+    }
+
+    /// Sell up to `amount` units from one storage to another at market
+    /// prices, limited by the seller's stock and the buyer's room and coins
+    pub fn trade_resources(
+        &self,
+        db: &Database,
+        seller_id: &str,
+        buyer_id: &str,
+        resource: ResourceType,
+        amount: i32,
+    ) -> AppResult<Trade> {
+        if amount <= 0 {
+            return Err(AppError::invalid_argument(
+                "amount",
+                "must be greater than 0",
+            ));
+        }
+        if seller_id == buyer_id {
+            return Err(AppError::invalid_argument(
+                "buyerStorageId",
+                "must differ from sellerStorageId",
+            ));
+        }
+
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            let market = Market::new(&engine.economy, &engine.npcs);
+            if let Some((seller, buyer)) = engine.economy.storage_pair_mut(seller_id, buyer_id) {
+                let trade = market.trade(seller, buyer, resource, amount);
+                let (seller, buyer) = (&*seller, &*buyer);
+                db::save_storages(&mut engine.conn, &[seller, buyer])?;
+                return Ok(trade);
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let mut seller = db::get_storage(&conn, seller_id)?
+            .ok_or_else(|| AppError::not_found("Storage", seller_id))?;
+        let mut buyer = db::get_storage(&conn, buyer_id)?
+            .ok_or_else(|| AppError::not_found("Storage", buyer_id))?;
+        if seller.world_id != buyer.world_id {
+            return Err(AppError::invalid_argument(
+                "buyerStorageId",
+                "must be in the same world as sellerStorageId",
+            ));
+        }
+        let world = db::get_world(&conn, &seller.world_id)?
+            .ok_or_else(|| AppError::not_found("World", &seller.world_id))?;
+        let trade = load_market(&conn, &world)?.trade(&mut seller, &mut buyer, resource, amount);
+        db::save_storages(&mut conn, &[&seller, &buyer])?;
+        Ok(trade)
+    }
+
+    /// Move a mobile threat (like the player). The towns around it get their
+    /// safety recalculated and saved right away; the running world sees the
+    /// move on its next tick.
+    pub fn move_threat(
+        &self,
+        db: &Database,
+        threat_id: &str,
+        x: f64,
+        y: f64,
+    ) -> AppResult<ThreatSource> {
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            if let Some(threat) = engine.map.threat(threat_id).cloned() {
+                let threat = moved_threat(threat, x, y)?;
+                engine.update_threat(threat.clone())?;
+                return Ok(threat);
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let threat = db::get_threat(&conn, threat_id)?
+            .ok_or_else(|| AppError::not_found("ThreatSource", threat_id))?;
+        let threat = moved_threat(threat, x, y)?;
+        let world = db::get_world(&conn, &threat.world_id)?
+            .ok_or_else(|| AppError::not_found("World", &threat.world_id))?;
+        let mut map = load_world_map(&mut conn, &world)?;
+        db::save_threats(&mut conn, &[&threat])?;
+        let towns = map.update_threat(threat.clone());
+        db::save_towns(&mut conn, &towns.iter().collect::<Vec<_>>())?;
+        Ok(threat)
+    }
+
+    /// Clear a threat: `by` is "player" or the id of the NPC who did it.
+    /// Recorded as an Event with memories for the NPC and the NPCs the
+    /// threat reached; returns the event. Fails if there is no location to
+    /// record it at.
+    pub fn clear_threat(&self, db: &Database, threat_id: &str, by: &str) -> AppResult<Event> {
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            if let Some(threat) = engine.map.threat(threat_id).cloned() {
+                check_clearable(&threat)?;
+                let party: Vec<&Npc> = if by == clearing::PLAYER {
+                    Vec::new()
+                } else {
+                    let npc = engine
+                        .npcs
+                        .iter()
+                        .find(|npc| npc.id == by && npc.state != "dead")
+                        .ok_or_else(|| AppError::not_found("NPC", by))?;
+                    vec![npc]
+                };
+                let cleared = engine.clear_threat(&threat, &party)?;
+                let event = cleared.event.clone();
+                engine.apply_cleared(cleared)?;
+                return Ok(event);
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let threat = db::get_threat(&conn, threat_id)?
+            .ok_or_else(|| AppError::not_found("ThreatSource", threat_id))?;
+        check_clearable(&threat)?;
+        let world = db::get_world(&conn, &threat.world_id)?
+            .ok_or_else(|| AppError::not_found("World", &threat.world_id))?;
+        let npcs = db::list_npcs(&conn, &world.id)?;
+        let party: Vec<&Npc> = if by == clearing::PLAYER {
+            Vec::new()
+        } else {
+            let npc = npcs
+                .iter()
+                .find(|npc| npc.id == by && npc.state != "dead")
+                .ok_or_else(|| AppError::not_found("NPC", by))?;
+            vec![npc]
+        };
+
+        let mut map = load_world_map(&mut conn, &world)?;
+        let location_id = clearing_location(&map, &threat, &party)?;
+        let threatened = map.npcs_threatened_by(&threat);
+        let relieved: Vec<&Npc> = npcs
+            .iter()
+            .filter(|npc| threatened.contains(&npc.id) && npc.id != by)
+            .collect();
+        let cleared = clearing::clear(
+            &threat,
+            &party,
+            &relieved,
+            (world.current_day, world.current_hour),
+            &location_id,
+        );
+
+        let mut goals = db::list_goals(&conn, &world.id)?;
+        save_cleared(&mut conn, &mut map, &cleared, &mut goals)?;
+        Ok(cleared.event)
+    }
+
+    /// NPCs of the world currently in a crisis, most urgent first
+    pub fn list_active_crises(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+    ) -> AppResult<Vec<Crisis>> {
+        let mut crises: Vec<Crisis> = match self.engine.lock().unwrap().as_ref() {
+            Some(engine) if world_id.is_none_or(|id| id == engine.world_id) => {
+                engine.crises.values().cloned().collect()
+            }
+            _ => {
+                let conn = db.connect()?;
+                let world = match world_id {
+                    Some(id) => db::get_world(&conn, id)?,
+                    None => db::first_world(&conn)?,
+                }
+                .ok_or_else(|| AppError::not_found("World", world_id.unwrap_or("(first)")))?;
+                active_crises(&db::list_npcs(&conn, &world.id)?).collect()
+            }
+        };
+        crises.sort_by(|a, b| {
+            b.urgency
+                .cmp(&a.urgency)
+                .then_with(|| a.npc_name.cmp(&b.npc_name))
+        });
+        Ok(crises)
+    }
+
+    /// Fires, floods and famines of the world, oldest first; resolved ones
+    /// only with `include_resolved`
+    pub fn list_location_crises(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        include_resolved: bool,
+    ) -> AppResult<Vec<LocationCrisis>> {
+        if let Some(engine) = self.engine.lock().unwrap().as_ref() {
+            if !include_resolved && world_id.is_none_or(|id| id == engine.world_id) {
+                return Ok(engine.location_crises.clone());
+            }
+        }
+
+        let conn = db.connect()?;
+        let world = match world_id {
+            Some(id) => db::get_world(&conn, id)?,
+            None => db::first_world(&conn)?,
+        }
+        .ok_or_else(|| AppError::not_found("World", world_id.unwrap_or("(first)")))?;
+        Ok(db::list_location_crises(
+            &conn,
+            &world.id,
+            include_resolved,
+        )?)
+    }
+
+    /// Start a fire, flood or famine at a location. It takes effect on the
+    /// location right away and then runs its course with the simulation.
+    pub fn start_location_crisis(
+        &self,
+        db: &Database,
+        location_id: &str,
+        kind: LocationCrisisType,
+        severity: Option<f64>,
+    ) -> AppResult<LocationCrisis> {
+        let severity = severity.unwrap_or_else(|| location_crises::outbreak_severity(0.5));
+        if let Some(engine) = self.engine.lock().unwrap().as_mut() {
+            if engine.locations.iter().any(|l| l.id == location_id) {
+                return engine.start_location_crisis(location_id, kind, severity);
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let mut location = db::get_location(&conn, location_id)?
+            .ok_or_else(|| AppError::not_found("Location", location_id))?;
+        let world = db::get_world(&conn, &location.world_id)?
+            .ok_or_else(|| AppError::not_found("World", &location.world_id))?;
+        let crises = db::list_location_crises(&conn, &world.id, false)?;
+        let npcs = db::list_npcs(&conn, &world.id)?;
+        let (crisis, _) = begin_location_crisis(
+            &mut conn,
+            &mut location,
+            &active_at(&crises, location_id),
+            (kind, severity),
+            &present_at(&npcs, location_id),
+            (world.current_day, world.current_hour),
+        )?;
+        Ok(crisis)
+    }
+
+    /// Towns of the world with their current safety
+    pub fn list_towns(&self, db: &Database, world_id: Option<&str>) -> AppResult<Vec<Town>> {
+        self.with_world_map(db, world_id, |map| map.towns().to_vec())
+    }
+
+    /// Safety at a point of the map, with the threats that lower it
+    pub fn query_safety(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        x: f64,
+        y: f64,
+    ) -> AppResult<SafetyReport> {
+        self.with_world_map(db, world_id, |map| map.position_safety(x, y, 100.0))
+    }
+
+    /// The `k` active threats closest to a point of the map
+    pub fn nearest_threats(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        x: f64,
+        y: f64,
+        k: usize,
+    ) -> AppResult<Vec<NearbyThreat>> {
+        self.with_world_map(db, world_id, |map| map.nearest_threats(x, y, k))
+    }
+
+    /// Threats, locations and NPCs within `radius` of a point, closest first
+    pub fn entities_in_radius(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        (x, y): (f64, f64),
+        radius: f64,
+        kind: Option<EntityKind>,
+    ) -> AppResult<Vec<SpatialHit>> {
+        self.with_world_map(db, world_id, |map| {
+            map.index().within_radius(x, y, radius, kind)
+        })
+    }
+
+    /// The `k` threats, locations or NPCs closest to a point
+    pub fn nearest_entities(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        (x, y): (f64, f64),
+        k: usize,
+        kind: Option<EntityKind>,
+    ) -> AppResult<Vec<SpatialHit>> {
+        self.with_world_map(db, world_id, |map| map.index().nearest(x, y, k, kind))
+    }
+
+    /// Threats, locations and NPCs inside a box
+    pub fn entities_in_bbox(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        bbox: BBox,
+        kind: Option<EntityKind>,
+    ) -> AppResult<Vec<SpatialHit>> {
+        let (min, max) = ((bbox.min_x, bbox.min_y), (bbox.max_x, bbox.max_y));
+        self.with_world_map(db, world_id, |map| map.index().in_bbox(min, max, kind))
+    }
+
+    /// Run `query` on the map of the running world, or of the given (or
+    /// first) world loaded from the database
+    fn with_world_map<T>(
+        &self,
+        db: &Database,
+        world_id: Option<&str>,
+        query: impl FnOnce(&WorldMap) -> T,
+    ) -> AppResult<T> {
+        if let Some(engine) = self.engine.lock().unwrap().as_ref() {
+            if world_id.is_none_or(|id| id == engine.world_id) {
+                return Ok(query(&engine.map));
+            }
+        }
+
+        let mut conn = db.connect()?;
+        let world = match world_id {
+            Some(id) => db::get_world(&conn, id)?,
+            None => db::first_world(&conn)?,
+        }
+        .ok_or_else(|| AppError::not_found("World", world_id.unwrap_or("(first)")))?;
+        Ok(query(&load_world_map(&mut conn, &world)?))
+    }
+
+    pub fn status(&self) -> SimulationStatus {
+        let running = self.worker.lock().unwrap().is_some();
+        let engine = self.engine.lock().unwrap();
+
+        let Some(engine) = engine.as_ref() else {
+            return SimulationStatus {
+                state: RunState::Stopped,
+                world_id: None,
+                day: None,
+                hour: None,
+                tick_count: 0,
+                ticks_per_second: 0.0,
+                tick_speed_ms: SimulationConfig::default().tick_speed_ms,
+                time_scale: *self.time_scale.lock().unwrap(),
+                next_checkpoint: None,
+            };
+        };
+
+        let state = match (running, engine.paused) {
+            (false, _) => RunState::Stopped,
+            (true, true) => RunState::Paused,
+            (true, false) => RunState::Running,
+        };
+
+        SimulationStatus {
+            state,
+            world_id: Some(engine.world_id.clone()),
+            day: Some(engine.clock.day),
+            hour: Some(engine.clock.hour),
+            tick_count: engine.tick_count,
+            ticks_per_second: if state == RunState::Running {
+                engine.ticks_per_second
+            } else {
+                0.0
+            },
+            tick_speed_ms: engine.config.tick_speed_ms,
+            time_scale: engine.time_scale,
+            next_checkpoint: Some(daily_cycle::next_checkpoint(engine.clock.hour)),
+        }
+    }
+}
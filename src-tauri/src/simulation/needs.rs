@@ -0,0 +1,204 @@
+// Need decay and emotion derivation - port of NPCAgent.updateNeeds and
+// NPCAgent.updateEmotions in src/simulation/NPC.ts
+//
+// Works on the in-memory NPC; the engine writes changed rows back in one batch.
+
+use crate::models::Npc;
+
+/// Hourly decay rates
+const FOOD_DECAY: i32 = 2;
+const SOCIAL_DECAY: i32 = 1;
+/// About the 10 per checkpoint of src/need-based-behavior.ts
+const REST_DECAY: i32 = 2;
+/// Only applies while the NPC has no open goals
+const PURPOSE_DECAY: i32 = 1;
+/// Hourly safety regained once the danger is gone
+const SAFETY_RECOVERY: i32 = 5;
+
+/// Below these thresholds fear / sadness build up
+const LOW_SAFETY: i32 = 30;
+const LOW_SOCIAL: i32 = 30;
+
+/// Needs decay over time (called once per tick)
+pub fn update_needs(npc: &mut Npc, has_open_goals: bool) {
+    // Food decreases every hour
+    npc.need_food = (npc.need_food - FOOD_DECAY).max(0);
+
+    // Safety follows the threat map (update_safety)
+
+    // Social needs decrease if alone
+    npc.need_social = (npc.need_social - SOCIAL_DECAY).max(0);
+
+    // Wealth doesn't decay naturally
+
+    // Energy drains while awake; resting makes up for it (buildings.rs)
+    npc.need_rest = (npc.need_rest - REST_DECAY).max(0);
+
+    // Purpose decreases if no active goals
+    if !has_open_goals {
+        npc.need_purpose = (npc.need_purpose - PURPOSE_DECAY).max(0);
+    }
+}
+
+/// Safety drops at once to what the NPC's position allows (see
+/// threats::location_safety) and climbs back slowly once the danger is gone.
+/// Returns the change.
+pub fn update_safety(npc: &mut Npc, map_safety: i32) -> i32 {
+    let old_safety = npc.need_safety;
+    npc.need_safety = if map_safety < old_safety {
+        map_safety
+    } else {
+        (old_safety + SAFETY_RECOVERY).min(map_safety)
+    };
+    npc.need_safety - old_safety
+}
+
+fn average_needs(npc: &Npc) -> f64 {
+    let total =
+        npc.need_food + npc.need_safety + npc.need_wealth + npc.need_social + npc.need_purpose;
+    total as f64 / 5.0
+}
+
+/// Emotions follow needs. Like the TS version, derived emotions are computed
+/// from the emotion values as they were at the start of the update.
+pub fn update_emotions(npc: &mut Npc) {
+    let avg_needs = average_needs(npc);
+    let old_happiness = npc.emotion_happiness;
+    let old_fear = npc.emotion_fear;
+    let old_sadness = npc.emotion_sadness;
+
+    // Happiness based on overall needs satisfaction
+    npc.emotion_happiness = avg_needs.floor() as i32;
+
+    // Fear increases when safety is low
+    npc.emotion_fear = if npc.need_safety < LOW_SAFETY {
+        (old_fear + 10).min(100)
+    } else {
+        (old_fear - 5).max(0)
+    };
+
+    // Sadness increases when social needs low
+    npc.emotion_sadness = if npc.need_social < LOW_SOCIAL {
+        (old_sadness + 5).min(100)
+    } else {
+        (old_sadness - 3).max(0)
+    };
+
+    // Derived emotions
+    npc.emotion_love = (old_happiness + npc.emotion_trust) / 2;
+    npc.emotion_desperation =
+        (((old_fear + old_sadness) as f64 + (100.0 - avg_needs)) / 3.0).floor() as i32;
+    npc.emotion_grief = (old_sadness * 2 + (100 - npc.need_social)) / 3;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    #[test]
+    fn needs_decay_every_hour() {
+        let mut npc = fixtures::npc("Ann");
+        update_needs(&mut npc, false);
+        assert_eq!(
+            (
+                npc.need_food,
+                npc.need_social,
+                npc.need_rest,
+                npc.need_purpose
+            ),
+            (78, 59, 78, 59)
+        );
+        // Neither wealth nor safety decays on its own
+        assert_eq!((npc.need_wealth, npc.need_safety), (50, 80));
+
+        // Working towards a goal keeps the sense of purpose
+        update_needs(&mut npc, true);
+        assert_eq!((npc.need_food, npc.need_purpose), (76, 59));
+    }
+
+    #[test]
+    fn needs_stop_at_zero() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_food = 1;
+        npc.need_social = 0;
+        npc.need_rest = 1;
+        npc.need_purpose = 0;
+        update_needs(&mut npc, false);
+        assert_eq!(
+            (
+                npc.need_food,
+                npc.need_social,
+                npc.need_rest,
+                npc.need_purpose
+            ),
+            (0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn safety_drops_at_once_and_recovers_slowly() {
+        let mut npc = fixtures::npc("Ann");
+        assert_eq!(update_safety(&mut npc, 30), -50);
+        assert_eq!(npc.need_safety, 30);
+
+        assert_eq!(update_safety(&mut npc, 100), SAFETY_RECOVERY);
+        assert_eq!(npc.need_safety, 35);
+        // Never above what the position allows
+        assert_eq!(update_safety(&mut npc, 38), 3);
+        assert_eq!(update_safety(&mut npc, 38), 0);
+
+        npc.need_safety = 98;
+        update_safety(&mut npc, 100);
+        assert_eq!(npc.need_safety, 100);
+        update_safety(&mut npc, 100);
+        assert_eq!(npc.need_safety, 100);
+    }
+
+    #[test]
+    fn emotions_ease_while_needs_are_met() {
+        let mut npc = fixtures::npc("Ann");
+        // Average need (81 + 80 + 50 + 60 + 60) / 5 = 66.2
+        npc.need_food = 81;
+        update_emotions(&mut npc);
+
+        assert_eq!(npc.emotion_happiness, 66);
+        assert_eq!((npc.emotion_fear, npc.emotion_sadness), (5, 7));
+        // Derived from the old happiness, fear and sadness
+        assert_eq!(npc.emotion_love, (60 + 50) / 2);
+        // (10 + 10 + 33.8) / 3, floored
+        assert_eq!(npc.emotion_desperation, 17);
+        assert_eq!(npc.emotion_grief, (10 * 2 + 40) / 3);
+    }
+
+    #[test]
+    fn fear_and_sadness_build_up_when_unsafe_and_alone() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_safety = LOW_SAFETY - 10;
+        npc.need_social = LOW_SOCIAL - 10;
+        update_emotions(&mut npc);
+
+        // Average need (80 + 20 + 50 + 20 + 60) / 5 = 46
+        assert_eq!(npc.emotion_happiness, 46);
+        assert_eq!((npc.emotion_fear, npc.emotion_sadness), (20, 15));
+        assert_eq!(npc.emotion_desperation, (10 + 10 + 54) / 3);
+        assert_eq!(npc.emotion_grief, (10 * 2 + 80) / 3);
+    }
+
+    #[test]
+    fn emotions_stay_within_0_and_100() {
+        let mut npc = fixtures::npc("Ann");
+        npc.need_safety = 0;
+        npc.need_social = 0;
+        npc.emotion_fear = 95;
+        npc.emotion_sadness = 98;
+        update_emotions(&mut npc);
+        assert_eq!((npc.emotion_fear, npc.emotion_sadness), (100, 100));
+
+        let mut npc = fixtures::npc("Bob");
+        npc.emotion_fear = 3;
+        npc.emotion_sadness = 2;
+        update_emotions(&mut npc);
+        assert_eq!((npc.emotion_fear, npc.emotion_sadness), (0, 0));
+    }
+}
@@ -0,0 +1,164 @@
+// Mobile threats - patrols, wandering and raids
+//
+// In the TS prototype only the player threat ever moves. Here every active,
+// mobile threat moves up to `speed` map units per in-game hour according to
+// its `movement`. Towns are not recalculated here; the engine hands each
+// moved threat to WorldMap::update_threat.
+
+use std::f64::consts::TAU;
+
+use super::rng::Rng;
+use super::threats;
+use crate::models::{ThreatMovement, ThreatSource};
+
+/// Move the threat for one hour. Returns false if it stayed where it was.
+pub fn advance(threat: &mut ThreatSource, rng: &mut Rng) -> bool {
+    if !threat.active || !threat.mobile || threat.speed <= 0.0 {
+        return false;
+    }
+
+    let from = (threat.x, threat.y);
+    match threat.movement {
+        ThreatMovement::Stationary => {}
+        ThreatMovement::Patrol => follow_route(threat, true),
+        ThreatMovement::Approach => follow_route(threat, false),
+        ThreatMovement::Wander => wander(threat, rng),
+    }
+    (threat.x, threat.y) != from
+}
+
+/// Walk towards the current waypoint, on to the next ones while there is
+/// distance left. At the end of the route a patrol starts over; an approach
+/// stays at the last waypoint.
+fn follow_route(threat: &mut ThreatSource, looping: bool) {
+    let len = threat.route.len();
+    let mut remaining = threat.speed;
+
+    // A full lap at most, so a route of one point can't spin forever
+    for _ in 0..len {
+        let index = (threat.route_index.max(0) as usize).min(len - 1);
+        let [tx, ty] = threat.route[index];
+        let distance = threats::distance(threat.x, threat.y, tx, ty);
+
+        if distance > remaining {
+            step_towards(threat, (tx, ty), remaining);
+            return;
+        }
+
+        (threat.x, threat.y) = (tx, ty);
+        remaining -= distance;
+        if index + 1 < len {
+            threat.route_index = index as i32 + 1;
+        } else if looping {
+            threat.route_index = 0;
+        } else {
+            // Arrived
+            return;
+        }
+    }
+}
+
+/// One step in a random direction, or back towards home (the first
+/// waypoint) if that step would leave the wander radius
+fn wander(threat: &mut ThreatSource, rng: &mut Rng) {
+    let (home_x, home_y) = threat
+        .route
+        .first()
+        .map_or((threat.x, threat.y), |&[x, y]| (x, y));
+
+    let angle = rng.next_f64() * TAU;
+    let x = threat.x + threat.speed * angle.cos();
+    let y = threat.y + threat.speed * angle.sin();
+
+    if threats::distance(x, y, home_x, home_y) <= threat.wander_radius {
+        (threat.x, threat.y) = (x, y);
+    } else {
+        let speed = threat.speed;
+        step_towards(threat, (home_x, home_y), speed);
+    }
+}
+
+fn step_towards(threat: &mut ThreatSource, (tx, ty): (f64, f64), amount: f64) {
+    let distance = threats::distance(threat.x, threat.y, tx, ty);
+    if distance <= amount {
+        (threat.x, threat.y) = (tx, ty);
+        return;
+    }
+
+    threat.x += (tx - threat.x) / distance * amount;
+    threat.y += (ty - threat.y) / distance * amount;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    fn walk(threat: &mut ThreatSource, hours: usize) -> Vec<(f64, f64)> {
+        let mut rng = Rng::new(1);
+        (0..hours)
+            .map(|_| {
+                advance(threat, &mut rng);
+                (threat.x, threat.y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn patrol_walks_its_route_and_starts_over() {
+        let mut threat = fixtures::threat(ThreatMovement::Patrol, &[[0.0, 0.0], [10.0, 0.0]], 4.0);
+
+        let xs: Vec<f64> = walk(&mut threat, 7).into_iter().map(|(x, _)| x).collect();
+        // Out to 10 and 2 back in the third hour, home in the fifth
+        assert_eq!(xs, [4.0, 8.0, 8.0, 4.0, 0.0, 4.0, 8.0]);
+        assert_eq!(threat.y, 0.0);
+    }
+
+    #[test]
+    fn patrol_takes_several_waypoints_in_one_hour() {
+        let route = [[0.0, 0.0], [3.0, 0.0], [3.0, 4.0], [0.0, 4.0]];
+        let mut threat = fixtures::threat(ThreatMovement::Patrol, &route, 8.0);
+
+        assert_eq!(walk(&mut threat, 1), [(2.0, 4.0)]);
+        assert_eq!(threat.route_index, 3);
+    }
+
+    #[test]
+    fn approach_stops_at_the_last_waypoint() {
+        let mut threat =
+            fixtures::threat(ThreatMovement::Approach, &[[0.0, 0.0], [0.0, 10.0]], 6.0);
+
+        assert_eq!(walk(&mut threat, 3), [(0.0, 6.0), (0.0, 10.0), (0.0, 10.0)]);
+        assert!(!advance(&mut threat, &mut Rng::new(1)));
+    }
+
+    #[test]
+    fn only_active_mobile_threats_move() {
+        let route = [[0.0, 0.0], [10.0, 0.0]];
+        let mut rng = Rng::new(1);
+
+        let mut inactive = fixtures::threat(ThreatMovement::Patrol, &route, 4.0);
+        inactive.active = false;
+        let mut fixed = fixtures::threat(ThreatMovement::Patrol, &route, 4.0);
+        fixed.mobile = false;
+        let mut stationary = fixtures::threat(ThreatMovement::Stationary, &route, 4.0);
+        let mut still = fixtures::threat(ThreatMovement::Patrol, &route, 0.0);
+
+        for threat in [&mut inactive, &mut fixed, &mut stationary, &mut still] {
+            assert!(!advance(threat, &mut rng));
+            assert_eq!((threat.x, threat.y), (0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn wandering_stays_near_home() {
+        let mut threat = fixtures::threat(ThreatMovement::Wander, &[[100.0, 100.0]], 15.0);
+        threat.wander_radius = 40.0;
+
+        let positions = walk(&mut threat, 500);
+        assert!(positions
+            .iter()
+            .all(|&(x, y)| threats::distance(x, y, 100.0, 100.0) <= 40.0 + 1e-9));
+        assert!(positions.iter().any(|&pos| pos != (100.0, 100.0)));
+    }
+}
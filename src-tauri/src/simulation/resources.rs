@@ -0,0 +1,464 @@
+// Resource economy - port of ResourceManager, ResourceStorage and
+// PRODUCTION_RECIPES in src/resource-system.ts
+//
+// NPCs whose occupation has a recipe start a production task while they are
+// working. Inputs (`requires`) are taken from the storage when the task starts
+// and the output is delivered there `time_hours` later.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::db;
+use crate::models::{Npc, ProductionRecipe, ProductionTask, ResourceType, Storage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceCategory {
+    Raw,
+    Processed,
+    Crafted,
+}
+
+/// RESOURCE_INFO entry
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ResourceInfo {
+    pub name: &'static str,
+    pub emoji: &'static str,
+    pub description: &'static str,
+    pub category: ResourceCategory,
+}
+
+impl ResourceType {
+    pub fn info(self) -> ResourceInfo {
+        use ResourceCategory::*;
+        let (name, emoji, description, category) = match self {
+            ResourceType::Wood => ("Wood", "🪵", "Basic building material", Raw),
+            ResourceType::Stone => ("Stone", "🪨", "Sturdy construction material", Raw),
+            ResourceType::Iron => ("Iron", "⚙️", "Metal ore for tools and weapons", Raw),
+            ResourceType::Food => ("Food", "🍎", "Sustenance for NPCs", Processed),
+            ResourceType::Cloth => ("Cloth", "🧵", "Fabric for clothing", Processed),
+            ResourceType::Tools => ("Tools", "🔨", "Equipment for work", Crafted),
+            ResourceType::Weapons => ("Weapons", "⚔️", "Arms for defense", Crafted),
+            ResourceType::Medicine => ("Medicine", "💊", "Healing supplies", Crafted),
+        };
+        ResourceInfo {
+            name,
+            emoji,
+            description,
+            category,
+        }
+    }
+}
+
+/// (occupation, produces, amount, time_hours, requires)
+type RecipeRow = (
+    &'static str,
+    ResourceType,
+    i32,
+    i32,
+    &'static [(ResourceType, i32)],
+);
+
+/// PRODUCTION_RECIPES
+const PRODUCTION_RECIPES: [RecipeRow; 10] = {
+    use ResourceType::*;
+    [
+        // Raw resource gathering
+        ("lumberjack", Wood, 5, 2, &[]),
+        ("miner", Stone, 3, 3, &[]),
+        ("blacksmith", Iron, 2, 4, &[]),
+        ("farmer", Food, 8, 4, &[]),
+        // Food production: uses raw food to make more (baking)
+        ("baker", Food, 10, 3, &[(Food, 2)]),
+        // Processed goods
+        ("tailor", Cloth, 2, 2, &[(Food, 1)]),
+        // Medicine production
+        ("healer", Medicine, 2, 2, &[(Food, 2)]),
+        ("herbalist", Medicine, 3, 2, &[(Food, 1)]),
+        // Crafted items
+        ("carpenter", Tools, 1, 3, &[(Wood, 2), (Iron, 1)]),
+        ("weaponsmith", Weapons, 1, 4, &[(Iron, 3), (Wood, 1)]),
+    ]
+};
+
+/// PRODUCTION_RECIPES, for worlds that have no recipes saved yet
+pub fn default_recipes(world_id: &str) -> Vec<ProductionRecipe> {
+    let now = db::now_millis();
+    PRODUCTION_RECIPES
+        .iter()
+        .map(
+            |&(occupation, produces, amount, time_hours, requires)| ProductionRecipe {
+                id: db::new_id(),
+                world_id: world_id.to_string(),
+                occupation: occupation.to_string(),
+                produces,
+                amount,
+                time_hours,
+                requires: requires.iter().copied().collect(),
+                created_at: now,
+                updated_at: now,
+            },
+        )
+        .collect()
+}
+
+/// The recipe for `occupation`, whatever its case
+pub fn recipe_for<'a>(
+    recipes: &'a [ProductionRecipe],
+    occupation: &str,
+) -> Option<&'a ProductionRecipe> {
+    let occupation = occupation.to_lowercase();
+    recipes
+        .iter()
+        .find(|recipe| recipe.occupation == occupation)
+}
+
+impl Storage {
+    /// A new, empty storage of `world_id` (not yet saved)
+    pub fn new(
+        world_id: &str,
+        key: &str,
+        name: &str,
+        emoji: &str,
+        capacity: i32,
+        (x, y): (f64, f64),
+    ) -> Self {
+        let now = db::now_millis();
+        Self {
+            id: db::new_id(),
+            world_id: world_id.to_string(),
+            key: key.to_string(),
+            name: name.to_string(),
+            emoji: emoji.to_string(),
+            capacity,
+            x,
+            y,
+            coins: 0,
+            inventory: BTreeMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Add resources. Returns the amount actually added (less if full).
+    pub fn add(&mut self, resource: ResourceType, amount: i32) -> i32 {
+        let available = self.capacity - self.total_stored();
+        if available <= 0 || amount <= 0 {
+            return 0;
+        }
+
+        let added = amount.min(available);
+        *self.inventory.entry(resource).or_default() += added;
+        added
+    }
+
+    /// Remove resources. Returns the amount actually removed (less if short).
+    pub fn remove(&mut self, resource: ResourceType, amount: i32) -> i32 {
+        let current = self.get(resource);
+        let removed = amount.min(current).max(0);
+        if removed > 0 {
+            self.inventory.insert(resource, current - removed);
+        }
+        removed
+    }
+
+    pub fn has(&self, resource: ResourceType, amount: i32) -> bool {
+        self.get(resource) >= amount
+    }
+
+    pub fn get(&self, resource: ResourceType) -> i32 {
+        self.inventory.get(&resource).copied().unwrap_or(0)
+    }
+
+    pub fn total_stored(&self) -> i32 {
+        self.inventory.values().sum()
+    }
+}
+
+/// Key of the storage finished goods go to unless said otherwise
+pub const DEFAULT_STORAGE: &str = "warehouse";
+
+/// The storages and starting stock of initResourceSystem in src/main.ts, for
+/// worlds that have none saved yet
+pub fn default_storages(world_id: &str) -> Vec<Storage> {
+    let mut warehouse = Storage::new(
+        world_id,
+        DEFAULT_STORAGE,
+        "Warehouse",
+        "🏚️",
+        100,
+        (1200.0, 300.0),
+    );
+    // Plenty of initial resources so NPCs can work
+    warehouse.add(ResourceType::Wood, 30);
+    warehouse.add(ResourceType::Stone, 20);
+    warehouse.add(ResourceType::Iron, 15);
+    warehouse.add(ResourceType::Food, 40);
+
+    let mut market = Storage::new(world_id, "market", "Market", "🏪", 80, (1200.0, 500.0));
+    // Enough to buy stock from the warehouse
+    market.coins = 200;
+
+    vec![
+        warehouse,
+        market,
+        Storage::new(world_id, "armory", "Armory", "🏰", 50, (1200.0, 700.0)),
+    ]
+}
+
+/// Why an NPC could not start producing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProductionBlocked {
+    NoStorage,
+    MissingMaterials(Vec<(ResourceType, i32)>),
+}
+
+/// transferResources: move up to `amount` from one storage to another.
+/// Whatever does not fit goes back to the source. Returns the amount moved.
+pub fn transfer(from: &mut Storage, to: &mut Storage, resource: ResourceType, amount: i32) -> i32 {
+    let removed = from.remove(resource, amount);
+    if removed == 0 {
+        return 0;
+    }
+
+    let added = to.add(resource, removed);
+    // If couldn't add all, put back what couldn't be added
+    if added < removed {
+        from.add(resource, removed - added);
+    }
+    added
+}
+
+/// All storages, recipes and production tasks of a world
+#[derive(Debug, Clone, Default)]
+pub struct Economy {
+    pub storages: Vec<Storage>,
+    pub recipes: Vec<ProductionRecipe>,
+    /// Active tasks, plus tasks completed since the last diff
+    pub tasks: Vec<ProductionTask>,
+}
+
+impl Economy {
+    pub fn storage_by_key_mut(&mut self, key: &str) -> Option<&mut Storage> {
+        self.storages.iter_mut().find(|storage| storage.key == key)
+    }
+
+    /// Both storages of a transfer, mutably. None if either is unknown.
+    pub fn storage_pair_mut(
+        &mut self,
+        from_id: &str,
+        to_id: &str,
+    ) -> Option<(&mut Storage, &mut Storage)> {
+        let from = self.storages.iter().position(|s| s.id == from_id)?;
+        let to = self.storages.iter().position(|s| s.id == to_id)?;
+        if from == to {
+            return None;
+        }
+
+        let (low, high) = (from.min(to), from.max(to));
+        let (head, tail) = self.storages.split_at_mut(high);
+        let (first, second) = (&mut head[low], &mut tail[0]);
+        Some(if from < to {
+            (first, second)
+        } else {
+            (second, first)
+        })
+    }
+
+    pub fn active_task(&self, npc_id: &str) -> Option<&ProductionTask> {
+        self.tasks
+            .iter()
+            .find(|task| task.npc_id == npc_id && !task.completed)
+    }
+
+    /// startProduction: take the recipe's inputs from the storage and queue
+    /// the output. The caller checks that the NPC is working and has no task.
+    pub fn start_production(
+        &mut self,
+        id: String,
+        npc: &Npc,
+        recipe: &ProductionRecipe,
+        now: i64,
+        storage_key: &str,
+    ) -> Result<&ProductionTask, ProductionBlocked> {
+        let storage = self
+            .storage_by_key_mut(storage_key)
+            .ok_or(ProductionBlocked::NoStorage)?;
+
+        let missing: Vec<(ResourceType, i32)> = recipe
+            .requires
+            .iter()
+            .filter(|&(&resource, &amount)| !storage.has(resource, amount))
+            .map(|(&resource, &amount)| (resource, amount))
+            .collect();
+        if !missing.is_empty() {
+            return Err(ProductionBlocked::MissingMaterials(missing));
+        }
+
+        // Consume materials
+        for (&resource, &amount) in &recipe.requires {
+            storage.remove(resource, amount);
+        }
+
+        let storage_id = storage.id.clone();
+        let now_millis = db::now_millis();
+
+        self.tasks.push(ProductionTask {
+            id,
+            npc_id: npc.id.clone(),
+            npc_name: npc.name.clone(),
+            occupation: npc.occupation.clone(),
+            resource: recipe.produces,
+            amount: recipe.amount,
+            start_hour: now,
+            end_hour: now + recipe.time_hours as i64,
+            completed: false,
+            storage_id,
+            created_at: now_millis,
+            updated_at: now_millis,
+        });
+        Ok(self.tasks.last().unwrap())
+    }
+
+    /// updateProduction: finish due tasks and deliver their output. Returns
+    /// the finished tasks with the amount that fit in the storage.
+    pub fn update_production(&mut self, now: i64) -> Vec<(ProductionTask, i32)> {
+        // Drop tasks finished in earlier hours
+        self.tasks.retain(|task| !task.completed);
+
+        let mut finished = Vec::new();
+        for task in self.tasks.iter_mut().filter(|task| now >= task.end_hour) {
+            task.completed = true;
+            let stored = self
+                .storages
+                .iter_mut()
+                .find(|storage| storage.id == task.storage_id)
+                .map_or(0, |storage| storage.add(task.resource, task.amount));
+            finished.push((task.clone(), stored));
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    fn shed(capacity: i32) -> Storage {
+        Storage::new(fixtures::WORLD, "shed", "Shed", "📦", capacity, (0.0, 0.0))
+    }
+
+    #[test]
+    fn storages_hold_up_to_their_capacity() {
+        let mut storage = shed(10);
+        assert_eq!(storage.add(ResourceType::Wood, 6), 6);
+        assert_eq!(storage.add(ResourceType::Stone, 6), 4);
+        assert_eq!(storage.add(ResourceType::Food, 1), 0);
+        assert_eq!(storage.total_stored(), 10);
+        assert_eq!(storage.add(ResourceType::Wood, -3), 0);
+
+        // Removing more than is there takes what there is
+        assert_eq!(storage.remove(ResourceType::Wood, 8), 6);
+        assert_eq!(storage.get(ResourceType::Wood), 0);
+        assert_eq!(storage.remove(ResourceType::Iron, 1), 0);
+        assert_eq!(storage.remove(ResourceType::Stone, -1), 0);
+        assert!(storage.has(ResourceType::Stone, 4));
+        assert!(!storage.has(ResourceType::Stone, 5));
+    }
+
+    #[test]
+    fn transfers_move_what_fits() {
+        let mut from = shed(100);
+        from.add(ResourceType::Wood, 10);
+        let mut to = shed(5);
+        to.add(ResourceType::Stone, 2);
+
+        assert_eq!(transfer(&mut from, &mut to, ResourceType::Wood, 8), 3);
+        assert_eq!(from.get(ResourceType::Wood), 7);
+        assert_eq!(to.get(ResourceType::Wood), 3);
+    }
+
+    #[test]
+    fn failed_transfers_leave_both_storages_unchanged() {
+        let mut from = shed(100);
+        from.add(ResourceType::Wood, 10);
+        let mut full = shed(2);
+        full.add(ResourceType::Stone, 2);
+        let (before_from, before_to) = (from.clone(), full.clone());
+
+        // No room at all
+        assert_eq!(transfer(&mut from, &mut full, ResourceType::Wood, 5), 0);
+        // Nothing to send
+        assert_eq!(transfer(&mut from, &mut full, ResourceType::Iron, 5), 0);
+        assert_eq!(from, before_from);
+        assert_eq!(full, before_to);
+    }
+
+    fn economy() -> Economy {
+        Economy {
+            storages: default_storages(fixtures::WORLD),
+            recipes: default_recipes(fixtures::WORLD),
+            tasks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn production_takes_the_inputs_and_delivers_later() {
+        let mut economy = economy();
+        let mut npc = fixtures::npc("Marcus");
+        npc.occupation = "Carpenter".to_string();
+        let recipe = recipe_for(&economy.recipes, &npc.occupation)
+            .unwrap()
+            .clone();
+
+        let task = economy
+            .start_production("task".to_string(), &npc, &recipe, 10, DEFAULT_STORAGE)
+            .unwrap();
+        assert_eq!((task.start_hour, task.end_hour), (10, 13));
+        assert_eq!(task.resource, ResourceType::Tools);
+        let warehouse = economy.storage_by_key_mut(DEFAULT_STORAGE).unwrap();
+        assert_eq!(warehouse.get(ResourceType::Wood), 28);
+        assert_eq!(warehouse.get(ResourceType::Iron), 14);
+
+        assert!(economy.update_production(12).is_empty());
+        let finished = economy.update_production(13);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].1, 1);
+        let warehouse = economy.storage_by_key_mut(DEFAULT_STORAGE).unwrap();
+        assert_eq!(warehouse.get(ResourceType::Tools), 1);
+    }
+
+    #[test]
+    fn production_is_blocked_without_materials_or_storage() {
+        let mut economy = economy();
+        let mut npc = fixtures::npc("Marcus");
+        npc.occupation = "carpenter".to_string();
+        let recipe = recipe_for(&economy.recipes, &npc.occupation)
+            .unwrap()
+            .clone();
+        let warehouse = economy.storage_by_key_mut(DEFAULT_STORAGE).unwrap();
+        warehouse.remove(ResourceType::Iron, 15);
+        let before = warehouse.clone();
+
+        let blocked = economy
+            .start_production("task".to_string(), &npc, &recipe, 10, DEFAULT_STORAGE)
+            .unwrap_err();
+        assert_eq!(
+            blocked,
+            ProductionBlocked::MissingMaterials(vec![(ResourceType::Iron, 1)])
+        );
+        // Nothing taken, nothing queued
+        assert_eq!(
+            economy.storage_by_key_mut(DEFAULT_STORAGE).unwrap(),
+            &before
+        );
+        assert!(economy.tasks.is_empty());
+
+        let blocked = economy
+            .start_production("task".to_string(), &npc, &recipe, 10, "cellar")
+            .unwrap_err();
+        assert_eq!(blocked, ProductionBlocked::NoStorage);
+    }
+}
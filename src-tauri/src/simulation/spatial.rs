@@ -0,0 +1,680 @@
+// Spatial index over the map - the uniform grid of "Optimization: Spatial
+// Indexing" in HIERARCHICAL_SPATIAL_SAFETY_SYSTEM.md
+//
+// Threat sources, locations and NPCs are bucketed into square cells, so radius,
+// k-nearest and bounding-box queries only look at the cells they overlap
+// instead of scanning every entity. NPCs have no coordinates of their own and
+// sit at their location's position; NPCs at locations off the map are not
+// indexed. The map also keeps the towns and their cached safety (towns.rs).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::threats::{self, NearbyThreat, SafetyReport, ThreatImpact};
+use super::towns;
+use crate::models::{Location, Npc, ThreatSource, ThreatType, Town};
+
+/// Same default cell size as createSpatialIndex
+pub const DEFAULT_CELL_SIZE: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Threat,
+    Location,
+    Npc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntityKey {
+    kind: EntityKind,
+    id: String,
+}
+
+/// A box of the map, edges included
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+/// One entity found by a query
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpatialHit {
+    pub kind: EntityKind,
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    /// From the query point; 0 for bounding-box queries
+    pub distance: f64,
+}
+
+type Cell = (i64, i64);
+
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<EntityKey>>,
+    positions: HashMap<EntityKey, (f64, f64)>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, x: f64, y: f64) -> Cell {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Add an entity, or move it if it is already indexed
+    pub fn insert(&mut self, kind: EntityKind, id: &str, x: f64, y: f64) {
+        self.remove(kind, id);
+
+        let key = EntityKey {
+            kind,
+            id: id.to_string(),
+        };
+        let cell = self.cell(x, y);
+        self.cells.entry(cell).or_default().push(key.clone());
+        self.positions.insert(key, (x, y));
+    }
+
+    pub fn remove(&mut self, kind: EntityKind, id: &str) {
+        let key = EntityKey {
+            kind,
+            id: id.to_string(),
+        };
+        let Some((x, y)) = self.positions.remove(&key) else {
+            return;
+        };
+
+        let cell = self.cell(x, y);
+        if let Some(keys) = self.cells.get_mut(&cell) {
+            keys.retain(|k| *k != key);
+            if keys.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Entities of the cells in the given cell range. Wide ranges walk the
+    /// occupied cells instead of every cell in the range.
+    fn scan(
+        &self,
+        (min_cx, min_cy): Cell,
+        (max_cx, max_cy): Cell,
+        kind: Option<EntityKind>,
+    ) -> impl Iterator<Item = (&EntityKey, (f64, f64))> {
+        let span = (max_cx.saturating_sub(min_cx).saturating_add(1))
+            .saturating_mul(max_cy.saturating_sub(min_cy).saturating_add(1));
+        let cells: Vec<&Vec<EntityKey>> = if span > self.cells.len() as i64 {
+            self.cells
+                .iter()
+                .filter(|((cx, cy), _)| {
+                    (min_cx..=max_cx).contains(cx) && (min_cy..=max_cy).contains(cy)
+                })
+                .map(|(_, keys)| keys)
+                .collect()
+        } else {
+            (min_cx..=max_cx)
+                .flat_map(|cx| (min_cy..=max_cy).map(move |cy| (cx, cy)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .collect()
+        };
+
+        cells
+            .into_iter()
+            .flatten()
+            .filter(move |key| kind.is_none_or(|kind| key.kind == kind))
+            .map(|key| (key, self.positions[key]))
+    }
+
+    fn hit(key: &EntityKey, (x, y): (f64, f64), distance: f64) -> SpatialHit {
+        SpatialHit {
+            kind: key.kind,
+            id: key.id.clone(),
+            x,
+            y,
+            distance,
+        }
+    }
+
+    /// Everything within `radius` of (x, y), closest first
+    pub fn within_radius(
+        &self,
+        x: f64,
+        y: f64,
+        radius: f64,
+        kind: Option<EntityKind>,
+    ) -> Vec<SpatialHit> {
+        let min = self.cell(x - radius, y - radius);
+        let max = self.cell(x + radius, y + radius);
+
+        let mut hits: Vec<SpatialHit> = self
+            .scan(min, max, kind)
+            .filter_map(|(key, pos)| {
+                let distance = threats::distance(x, y, pos.0, pos.1);
+                (distance <= radius).then(|| Self::hit(key, pos, distance))
+            })
+            .collect();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// The `k` entities closest to (x, y). Searches the cells ring by ring
+    /// outwards and stops once no unsearched cell can hold anything closer.
+    pub fn nearest(&self, x: f64, y: f64, k: usize, kind: Option<EntityKind>) -> Vec<SpatialHit> {
+        if k == 0 || self.positions.is_empty() {
+            return Vec::new();
+        }
+
+        let centre = self.cell(x, y);
+        let ring_of = |(kx, ky): Cell| kx.abs_diff(centre.0).max(ky.abs_diff(centre.1));
+        // Nothing lies past the furthest occupied cell
+        let last = self
+            .cells
+            .keys()
+            .map(|&cell| ring_of(cell))
+            .max()
+            .unwrap_or(0);
+
+        let mut hits: Vec<SpatialHit> = Vec::new();
+        let mut ring: u64 = 0;
+        loop {
+            // Anything in this ring or further is at least this far away
+            let reached = ring.saturating_sub(1) as f64 * self.cell_size;
+            if hits.len() >= k && hits[k - 1].distance <= reached {
+                break;
+            }
+
+            // Once a ring has more cells than there are occupied ones, pick
+            // its cells from those, so a query far away from everything
+            // doesn't walk the empty rings in between
+            let sparse = ring.saturating_mul(8) > self.cells.len() as u64;
+            let cells: Vec<Cell> = if sparse {
+                self.cells
+                    .keys()
+                    .copied()
+                    .filter(|&cell| ring_of(cell) == ring)
+                    .collect()
+            } else {
+                ring_cells(centre, ring as i64)
+            };
+            for cell in cells {
+                hits.extend(
+                    self.scan(cell, cell, kind).map(|(key, pos)| {
+                        Self::hit(key, pos, threats::distance(x, y, pos.0, pos.1))
+                    }),
+                );
+            }
+            hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+            if ring >= last {
+                break;
+            }
+            ring = if sparse {
+                self.cells
+                    .keys()
+                    .map(|&cell| ring_of(cell))
+                    .filter(|&r| r > ring)
+                    .min()
+                    .unwrap_or(last)
+            } else {
+                ring + 1
+            };
+        }
+
+        hits.truncate(k);
+        hits
+    }
+
+    /// Everything inside the box, in no particular order
+    pub fn in_bbox(
+        &self,
+        (min_x, min_y): (f64, f64),
+        (max_x, max_y): (f64, f64),
+        kind: Option<EntityKind>,
+    ) -> Vec<SpatialHit> {
+        let min = self.cell(min_x, min_y);
+        let max = self.cell(max_x, max_y);
+
+        self.scan(min, max, kind)
+            .filter(|(_, (x, y))| (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y))
+            .map(|(key, pos)| Self::hit(key, pos, 0.0))
+            .collect()
+    }
+}
+
+/// The cells exactly `ring` cells away from `centre` in x or y, leaving out
+/// the ones past the ends of the grid
+fn ring_cells((cx, cy): Cell, ring: i64) -> Vec<Cell> {
+    if ring == 0 {
+        return vec![(cx, cy)];
+    }
+
+    let cell = |dx: i64, dy: i64| Some((cx.checked_add(dx)?, cy.checked_add(dy)?));
+    let rows = (-ring..=ring).flat_map(|d| [cell(d, -ring), cell(d, ring)]);
+    let columns = (1 - ring..ring).flat_map(|d| [cell(-ring, d), cell(ring, d)]);
+    rows.chain(columns).flatten().collect()
+}
+
+/// Safety an NPC can have at a location, and what takes it down
+#[derive(Debug, Clone)]
+pub struct LocalSafety<'a> {
+    /// 0-100
+    pub safety: i32,
+    /// The town the location is in, if any
+    pub town: Option<&'a Town>,
+    /// Threats reaching the location, highest impact first
+    pub threats: Vec<ThreatImpact>,
+}
+
+/// The world as placed on the map: its threats and towns plus an index over
+/// threats, locations and NPCs
+#[derive(Debug, Clone)]
+pub struct WorldMap {
+    threats: Vec<ThreatSource>,
+    towns: Vec<Town>,
+    /// Where each location is, for placing NPCs
+    location_positions: HashMap<String, (f64, f64)>,
+    index: SpatialIndex,
+    /// Furthest any threat reaches; bounds the search for threats at a point
+    max_threat_radius: f64,
+}
+
+impl WorldMap {
+    /// Towns keep the safety they were saved with; see `recalculate_towns`
+    pub fn new(
+        threats: Vec<ThreatSource>,
+        towns: Vec<Town>,
+        locations: &[Location],
+        npcs: &[Npc],
+    ) -> Self {
+        let mut map = Self {
+            threats: Vec::new(),
+            towns,
+            location_positions: HashMap::new(),
+            index: SpatialIndex::new(DEFAULT_CELL_SIZE),
+            max_threat_radius: 0.0,
+        };
+
+        for location in locations {
+            if let (Some(x), Some(y)) = (location.x, location.y) {
+                map.location_positions.insert(location.id.clone(), (x, y));
+                map.index.insert(EntityKind::Location, &location.id, x, y);
+            }
+        }
+        for npc in npcs {
+            map.place_npc(npc);
+        }
+        map.set_threats(threats);
+        map
+    }
+
+    pub fn index(&self) -> &SpatialIndex {
+        &self.index
+    }
+
+    pub fn threats(&self) -> &[ThreatSource] {
+        &self.threats
+    }
+
+    pub fn towns(&self) -> &[Town] {
+        &self.towns
+    }
+
+    /// Replace all threats; only active ones are indexed
+    fn set_threats(&mut self, threats: Vec<ThreatSource>) {
+        for threat in &self.threats {
+            self.index.remove(EntityKind::Threat, &threat.id);
+        }
+        for threat in threats.iter().filter(|threat| threat.active) {
+            self.index
+                .insert(EntityKind::Threat, &threat.id, threat.x, threat.y);
+        }
+        self.threats = threats;
+        self.update_max_threat_radius();
+    }
+
+    fn update_max_threat_radius(&mut self) {
+        self.max_threat_radius = self
+            .threats
+            .iter()
+            .filter(|threat| threat.active)
+            .map(|threat| threat.radius)
+            .fold(0.0, f64::max);
+    }
+
+    /// Add a threat or replace it with its moved / cleared version. Returns
+    /// the towns whose safety changed because of it.
+    pub fn update_threat(&mut self, threat: ThreatSource) -> Vec<Town> {
+        if threat.active {
+            self.index
+                .insert(EntityKind::Threat, &threat.id, threat.x, threat.y);
+        } else {
+            self.index.remove(EntityKind::Threat, &threat.id);
+        }
+
+        let affects_towns = threat.kind != ThreatType::Player;
+        match self.threats.iter_mut().find(|t| t.id == threat.id) {
+            Some(existing) => *existing = threat,
+            None => self.threats.push(threat),
+        }
+        self.update_max_threat_radius();
+
+        if affects_towns {
+            self.recalculate_towns()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// recalculateAllTownSafety: refresh every town's cached safety from the
+    /// current threats. Returns the towns whose safety changed.
+    pub fn recalculate_towns(&mut self) -> Vec<Town> {
+        let mut changed = Vec::new();
+        for index in 0..self.towns.len() {
+            let town = &self.towns[index];
+            let reach = town.radius + self.max_threat_radius;
+            let threats: Vec<&ThreatSource> = self
+                .index
+                .within_radius(town.x, town.y, reach, Some(EntityKind::Threat))
+                .iter()
+                .filter_map(|hit| self.threat(&hit.id))
+                .collect();
+            let safety = towns::town_safety(town, threats);
+
+            if safety != town.current_safety {
+                self.towns[index].current_safety = safety;
+                changed.push(self.towns[index].clone());
+            }
+        }
+        changed
+    }
+
+    /// The town whose boundary holds (x, y) (getNPCTown)
+    fn town_at(&self, x: f64, y: f64) -> Option<&Town> {
+        self.towns.iter().find(|town| towns::contains(town, x, y))
+    }
+
+    /// Re-index an NPC at its current location
+    pub fn place_npc(&mut self, npc: &Npc) {
+        match self.location_positions.get(&npc.location_id) {
+            Some(&(x, y)) if npc.state != "dead" => {
+                self.index.insert(EntityKind::Npc, &npc.id, x, y)
+            }
+            _ => self.index.remove(EntityKind::Npc, &npc.id),
+        }
+    }
+
+    pub fn threat(&self, id: &str) -> Option<&ThreatSource> {
+        self.threats.iter().find(|threat| threat.id == id)
+    }
+
+    /// Ids of the NPCs the threat reaches: near it, or in a town it affects
+    pub fn npcs_threatened_by(&self, threat: &ThreatSource) -> Vec<String> {
+        let near = self.index.within_radius(
+            threat.x,
+            threat.y,
+            threat.radius * towns::PROXIMITY_REACH,
+            Some(EntityKind::Npc),
+        );
+        let in_towns = self
+            .towns
+            .iter()
+            .filter(|town| {
+                threats::distance(town.x, town.y, threat.x, threat.y) < town.radius + threat.radius
+            })
+            .flat_map(|town| {
+                self.index
+                    .within_radius(town.x, town.y, town.radius, Some(EntityKind::Npc))
+            });
+
+        let mut ids: Vec<String> = near.into_iter().chain(in_towns).map(|hit| hit.id).collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Id of the location closest to (x, y)
+    pub fn nearest_location(&self, x: f64, y: f64) -> Option<String> {
+        self.index
+            .nearest(x, y, 1, Some(EntityKind::Location))
+            .into_iter()
+            .next()
+            .map(|hit| hit.id)
+    }
+
+    /// Active threats within `reach` of their radius from (x, y)
+    fn threats_near(&self, x: f64, y: f64, reach: f64) -> Vec<&ThreatSource> {
+        self.index
+            .within_radius(
+                x,
+                y,
+                self.max_threat_radius * reach,
+                Some(EntityKind::Threat),
+            )
+            .iter()
+            .filter_map(|hit| self.threat(&hit.id))
+            .collect()
+    }
+
+    /// calculatePositionSafety for a point of the map
+    pub fn position_safety(&self, x: f64, y: f64, base_safety: f64) -> SafetyReport {
+        threats::position_safety(x, y, base_safety, self.threats_near(x, y, 1.0))
+    }
+
+    /// findKNearestThreats
+    pub fn nearest_threats(&self, x: f64, y: f64, k: usize) -> Vec<NearbyThreat> {
+        self.index
+            .nearest(x, y, k, Some(EntityKind::Threat))
+            .into_iter()
+            .filter_map(|hit| {
+                Some(NearbyThreat {
+                    threat: self.threat(&hit.id)?.clone(),
+                    distance: hit.distance,
+                })
+            })
+            .collect()
+    }
+
+    /// Safety an NPC can have at `location`. Inside a town it is the town's
+    /// safety adjusted for the NPC's position (towns::npc_safety), capped by
+    /// the location's own danger level; elsewhere it is the threat field at
+    /// the location (threats::location_safety).
+    pub fn location_safety(&self, location: &Location) -> LocalSafety<'_> {
+        let (Some(x), Some(y)) = (location.x, location.y) else {
+            let (safety, threats) = threats::location_safety(location, []);
+            return LocalSafety {
+                safety,
+                town: None,
+                threats,
+            };
+        };
+
+        let nearby = self.threats_near(x, y, towns::PROXIMITY_REACH);
+        let (field_safety, threats) = threats::location_safety(location, nearby.iter().copied());
+        let Some(town) = self.town_at(x, y) else {
+            return LocalSafety {
+                safety: field_safety,
+                town: None,
+                threats,
+            };
+        };
+
+        let danger_cap = (100 - location.danger_level).clamp(0, 100);
+        let safety = (towns::npc_safety(town, x, y, nearby).round() as i32).min(danger_cap);
+        LocalSafety {
+            safety,
+            town: Some(town),
+            threats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::rng::Rng;
+
+    /// 200 entities of mixed kinds scattered over a 2000 x 2000 map
+    fn scattered() -> (SpatialIndex, Vec<(EntityKind, String, f64, f64)>) {
+        let mut rng = Rng::new(7);
+        let mut index = SpatialIndex::new(DEFAULT_CELL_SIZE);
+        let mut entities = Vec::new();
+        for i in 0..200 {
+            let kind = [EntityKind::Threat, EntityKind::Location, EntityKind::Npc][i % 3];
+            let (x, y) = (
+                rng.next_f64() * 2000.0 - 1000.0,
+                rng.next_f64() * 2000.0 - 1000.0,
+            );
+            index.insert(kind, &i.to_string(), x, y);
+            entities.push((kind, i.to_string(), x, y));
+        }
+        (index, entities)
+    }
+
+    fn brute_force(
+        entities: &[(EntityKind, String, f64, f64)],
+        (x, y): (f64, f64),
+        kind: Option<EntityKind>,
+    ) -> Vec<(f64, String)> {
+        let mut all: Vec<(f64, String)> = entities
+            .iter()
+            .filter(|(k, ..)| kind.is_none_or(|kind| *k == kind))
+            .map(|(_, id, ex, ey)| (threats::distance(x, y, *ex, *ey), id.clone()))
+            .collect();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        all
+    }
+
+    const QUERIES: [(f64, f64); 5] = [
+        (0.0, 0.0),
+        (-999.0, 999.0),
+        (450.5, -120.25),
+        (5000.0, 5000.0),
+        (-1e6, 3.0),
+    ];
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let (index, entities) = scattered();
+        for point in QUERIES {
+            for kind in [None, Some(EntityKind::Threat), Some(EntityKind::Npc)] {
+                let expected = brute_force(&entities, point, kind);
+                for k in [1, 5, 40] {
+                    let hits = index.nearest(point.0, point.1, k, kind);
+                    let distances: Vec<f64> = hits.iter().map(|hit| hit.distance).collect();
+                    let want: Vec<f64> = expected.iter().take(k).map(|(d, _)| *d).collect();
+                    assert_eq!(distances, want, "nearest {} to {:?}", k, point);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn within_radius_matches_brute_force() {
+        let (index, entities) = scattered();
+        for point in QUERIES {
+            for radius in [0.0, 50.0, 333.0, 5000.0] {
+                let mut ids: Vec<String> = index
+                    .within_radius(point.0, point.1, radius, None)
+                    .into_iter()
+                    .map(|hit| hit.id)
+                    .collect();
+                let mut want: Vec<String> = brute_force(&entities, point, None)
+                    .into_iter()
+                    .filter(|(d, _)| *d <= radius)
+                    .map(|(_, id)| id)
+                    .collect();
+                ids.sort();
+                want.sort();
+                assert_eq!(ids, want, "within {} of {:?}", radius, point);
+            }
+        }
+    }
+
+    #[test]
+    fn in_bbox_matches_brute_force() {
+        let (index, entities) = scattered();
+        let boxes = [
+            ((-100.0, -100.0), (100.0, 100.0)),
+            ((-1000.0, -1000.0), (1000.0, 1000.0)),
+            ((250.0, -800.0), (260.0, 900.0)),
+            ((3000.0, 3000.0), (4000.0, 4000.0)),
+        ];
+        for ((min_x, min_y), (max_x, max_y)) in boxes {
+            let mut ids: Vec<String> = index
+                .in_bbox((min_x, min_y), (max_x, max_y), Some(EntityKind::Location))
+                .into_iter()
+                .map(|hit| hit.id)
+                .collect();
+            let mut want: Vec<String> = entities
+                .iter()
+                .filter(|(kind, _, x, y)| {
+                    *kind == EntityKind::Location
+                        && (min_x..=max_x).contains(x)
+                        && (min_y..=max_y).contains(y)
+                })
+                .map(|(_, id, ..)| id.clone())
+                .collect();
+            ids.sort();
+            want.sort();
+            assert_eq!(ids, want);
+        }
+    }
+
+    #[test]
+    fn rings_hold_every_cell_at_their_distance_once() {
+        for ring in 0..5 {
+            let mut cells = ring_cells((3, -2), ring);
+            cells.sort_unstable();
+            cells.dedup();
+            assert_eq!(cells.len() as i64, (8 * ring).max(1));
+            assert!(cells
+                .iter()
+                .all(|&(x, y)| (x - 3).abs().max((y + 2).abs()) == ring));
+        }
+        // Only the inner half of a ring at the edge of the grid exists
+        assert_eq!(ring_cells((i64::MAX, 0), 1).len(), 5);
+    }
+
+    #[test]
+    fn nearest_survives_extreme_positions() {
+        let (mut index, _) = scattered();
+        index.insert(EntityKind::Threat, "far", 1e300, -1e300);
+
+        let hits = index.nearest(f64::MAX, f64::MIN, 1, None);
+        assert_eq!(hits[0].id, "far");
+        assert_eq!(index.nearest(-1e300, 1e300, 3, None).len(), 3);
+        assert_eq!(
+            index.within_radius(0.0, 0.0, f64::INFINITY, None).len(),
+            201
+        );
+    }
+
+    #[test]
+    fn moved_and_removed_entities_are_not_found_where_they_were() {
+        let mut index = SpatialIndex::new(DEFAULT_CELL_SIZE);
+        index.insert(EntityKind::Npc, "a", 10.0, 10.0);
+        index.insert(EntityKind::Npc, "a", 900.0, 900.0);
+        index.insert(EntityKind::Npc, "b", 20.0, 20.0);
+        index.remove(EntityKind::Npc, "b");
+
+        assert!(index.within_radius(0.0, 0.0, 100.0, None).is_empty());
+        assert_eq!(index.nearest(0.0, 0.0, 5, None).len(), 1);
+    }
+}
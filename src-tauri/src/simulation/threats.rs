@@ -0,0 +1,311 @@
+// Distance-based threat field - port of src/threat-system.ts
+//
+// Every active threat lowers safety within its radius, following an inverse
+// square falloff: standing right next to a wolf den costs its full severity,
+// standing at the edge of its radius costs nothing. Impacts of overlapping
+// threats add up. Callers pick the candidate threats, usually through the
+// spatial index (spatial.rs).
+
+use serde::Serialize;
+
+use crate::db;
+use crate::models::{Location, ThreatMovement, ThreatSource, ThreatType};
+
+impl ThreatType {
+    /// getThreatEmoji
+    pub fn emoji(self) -> &'static str {
+        match self {
+            ThreatType::WolfDen => "🐺",
+            ThreatType::BanditCamp => "⚔️",
+            ThreatType::MonsterLair => "👹",
+            ThreatType::PlagueZone => "☠️",
+            ThreatType::HauntedRuins => "👻",
+            ThreatType::Player => "👤",
+        }
+    }
+}
+
+/// A threat affecting a position
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreatImpact {
+    pub threat: ThreatSource,
+    pub distance: f64,
+    /// Safety points it takes away
+    pub impact: f64,
+}
+
+/// A threat near a position, whether or not it reaches it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NearbyThreat {
+    pub threat: ThreatSource,
+    pub distance: f64,
+}
+
+/// Safety at a point of the map and what takes it down
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyReport {
+    pub x: f64,
+    pub y: f64,
+    /// 0-100
+    pub safety: f64,
+    /// Highest impact first
+    pub threats: Vec<ThreatImpact>,
+}
+
+/// THREAT_SOURCES, for worlds that have no threats saved yet. Threats that
+/// move on their own come from world definitions (data/worlds).
+pub fn default_threats(world_id: &str) -> Vec<ThreatSource> {
+    use ThreatType::*;
+    let now = db::now_millis();
+    let threat = |key: &str,
+                  kind: ThreatType,
+                  name: &str,
+                  (x, y): (f64, f64),
+                  radius: f64,
+                  severity: i32,
+                  description: &str| ThreatSource {
+        id: db::new_id(),
+        world_id: world_id.to_string(),
+        key: key.to_string(),
+        kind,
+        name: name.to_string(),
+        description: description.to_string(),
+        x,
+        y,
+        radius,
+        severity,
+        active: true,
+        // The player is moved by the frontend
+        mobile: kind == Player,
+        movement: ThreatMovement::Stationary,
+        route: Vec::new(),
+        route_index: 0,
+        speed: 0.0,
+        wander_radius: 0.0,
+        created_at: now,
+        updated_at: now,
+    };
+
+    vec![
+        threat(
+            "threat_1",
+            BanditCamp,
+            "North Bandit Camp",
+            (450.0, 150.0),
+            100.0,
+            75,
+            "A dangerous bandit hideout controlling the northern roads",
+        ),
+        threat(
+            "threat_2",
+            PlagueZone,
+            "Abandoned Village",
+            (900.0, 200.0),
+            80.0,
+            60,
+            "Disease-ridden ruins of a once thriving village",
+        ),
+        threat(
+            "threat_3",
+            HauntedRuins,
+            "Old Cemetery",
+            (150.0, 800.0),
+            60.0,
+            45,
+            "Ancient burial grounds with restless spirits",
+        ),
+        threat(
+            "threat_4",
+            WolfDen,
+            "Western Wolf Den",
+            (100.0, 400.0),
+            120.0,
+            80,
+            "Large wolf pack terrorizing the western approaches",
+        ),
+        threat(
+            "threat_5",
+            MonsterLair,
+            "Cave of Despair",
+            (1400.0, 600.0),
+            90.0,
+            70,
+            "Unknown creatures lurk in these dark caverns",
+        ),
+        threat(
+            "player_threat",
+            Player,
+            "Unknown Stranger",
+            // Updated as the player moves
+            (400.0, 300.0),
+            80.0,
+            50,
+            "A mysterious stranger whose intentions are unknown",
+        ),
+    ]
+}
+
+pub fn distance(x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+    ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt()
+}
+
+/// calculateThreatImpact: closer threats have much worse impact
+pub fn threat_impact(distance: f64, radius: f64, severity: i32) -> f64 {
+    // No impact if outside threat radius
+    if distance > radius {
+        return 0.0;
+    }
+
+    let proximity = 1.0 - distance / radius;
+    (severity as f64 * proximity.powi(2)).min(100.0)
+}
+
+/// getThreatsAtPosition: active threats reaching (x, y), highest impact first
+pub fn threats_at<'a>(
+    x: f64,
+    y: f64,
+    threats: impl IntoIterator<Item = &'a ThreatSource>,
+) -> Vec<ThreatImpact> {
+    let mut affecting: Vec<ThreatImpact> = threats
+        .into_iter()
+        .filter(|threat| threat.active)
+        .filter_map(|threat| {
+            let distance = distance(x, y, threat.x, threat.y);
+            let impact = threat_impact(distance, threat.radius, threat.severity);
+            (impact > 0.0).then(|| ThreatImpact {
+                threat: threat.clone(),
+                distance,
+                impact,
+            })
+        })
+        .collect();
+
+    affecting.sort_by(|a, b| b.impact.total_cmp(&a.impact));
+    affecting
+}
+
+/// calculatePositionSafety: `base_safety` minus the impact of every threat
+/// reaching (x, y)
+pub fn position_safety<'a>(
+    x: f64,
+    y: f64,
+    base_safety: f64,
+    threats: impl IntoIterator<Item = &'a ThreatSource>,
+) -> SafetyReport {
+    let threats = threats_at(x, y, threats);
+    let total_impact: f64 = threats.iter().map(|t| t.impact).sum();
+
+    SafetyReport {
+        x,
+        y,
+        safety: (base_safety - total_impact.min(base_safety)).max(0.0),
+        threats,
+    }
+}
+
+/// Safety an NPC can have at `location`: its danger level, lowered further by
+/// the threats around it if it is on the map. Returns the safety and the
+/// threats reaching it, highest impact first.
+pub fn location_safety<'a>(
+    location: &Location,
+    threats: impl IntoIterator<Item = &'a ThreatSource>,
+) -> (i32, Vec<ThreatImpact>) {
+    let base_safety = (100 - location.danger_level).clamp(0, 100) as f64;
+    let (Some(x), Some(y)) = (location.x, location.y) else {
+        return (base_safety as i32, Vec::new());
+    };
+
+    let report = position_safety(x, y, base_safety, threats);
+    (report.safety.round() as i32, report.threats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::fixtures;
+
+    /// Wolves at (100, 100): radius 50, severity 50
+    fn wolves() -> ThreatSource {
+        fixtures::threat(ThreatMovement::Stationary, &[[100.0, 100.0]], 0.0)
+    }
+
+    #[test]
+    fn impact_falls_off_towards_the_edge() {
+        assert_eq!(threat_impact(0.0, 50.0, 50), 50.0);
+        assert_eq!(threat_impact(25.0, 50.0, 50), 12.5);
+        assert_eq!(threat_impact(50.0, 50.0, 50), 0.0);
+        assert_eq!(threat_impact(50.1, 50.0, 50), 0.0);
+        // Never more than all the safety there is
+        assert_eq!(threat_impact(0.0, 50.0, 150), 100.0);
+    }
+
+    #[test]
+    fn position_safety_at_the_centre_edge_and_outside() {
+        let threats = [wolves()];
+
+        let centre = position_safety(100.0, 100.0, 80.0, &threats);
+        assert_eq!(centre.safety, 30.0);
+        assert_eq!(centre.threats.len(), 1);
+        assert_eq!(centre.threats[0].distance, 0.0);
+
+        let edge = position_safety(150.0, 100.0, 80.0, &threats);
+        assert_eq!(edge.safety, 80.0);
+        assert!(edge.threats.is_empty());
+
+        let outside = position_safety(200.0, 200.0, 80.0, &threats);
+        assert_eq!(outside.safety, 80.0);
+        assert!(outside.threats.is_empty());
+    }
+
+    #[test]
+    fn overlapping_threats_add_up_and_inactive_ones_dont_count() {
+        let mut near = wolves();
+        near.id = "near".to_string();
+        let mut far = wolves();
+        far.id = "far".to_string();
+        far.x = 125.0;
+        let mut gone = wolves();
+        gone.active = false;
+
+        let report = position_safety(100.0, 100.0, 100.0, &[far, gone, near]);
+        assert_eq!(report.safety, 100.0 - 50.0 - 12.5);
+        let ids: Vec<&str> = report
+            .threats
+            .iter()
+            .map(|t| t.threat.id.as_str())
+            .collect();
+        assert_eq!(ids, ["near", "far"]);
+
+        // No lower than 0
+        let report = position_safety(100.0, 100.0, 60.0, &[wolves(), wolves()]);
+        assert_eq!(report.safety, 0.0);
+    }
+
+    #[test]
+    fn location_safety_starts_from_the_danger_level() {
+        let threats = [wolves()];
+        let mut location = fixtures::location("camp", 100.0, 100.0);
+        location.danger_level = 20;
+        assert_eq!(location_safety(&location, &threats).0, 30);
+
+        // 80 - 12.5, rounded
+        location.x = Some(125.0);
+        let (safety, reaching) = location_safety(&location, &threats);
+        assert_eq!(safety, 68);
+        assert_eq!(reaching.len(), 1);
+
+        location.x = Some(150.0);
+        assert_eq!(location_safety(&location, &threats).0, 80);
+        location.x = Some(400.0);
+        assert_eq!(location_safety(&location, &threats).0, 80);
+
+        // Off the map, only the danger level counts
+        location.x = None;
+        let (safety, reaching) = location_safety(&location, &threats);
+        assert_eq!(safety, 80);
+        assert!(reaching.is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+// Two-tier safety - port of src/town-safety-system.ts
+//
+// Tier 1: a town's safety comes from the map-level threats around it. It is
+// cached in `Town::current_safety` and only recalculated when a threat is
+// added, moved or cleared (see WorldMap::update_threat), not every tick.
+// Tier 2: an NPC in town starts from that value and loses a little more for
+// threats it is closer to than the town center is.
+
+use super::threats;
+use crate::db;
+use crate::models::{ThreatSource, ThreatType, Town};
+
+/// Threats this close (in radii) to an NPC can add a personal penalty
+pub const PROXIMITY_REACH: f64 = 1.5;
+/// Only the nearest few threats count for the personal penalty
+const PROXIMITY_THREATS: usize = 3;
+/// Share of a threat's severity an NPC feels on top of the town's safety
+const PROXIMITY_SEVERITY: f64 = 0.3;
+
+/// TOWNS, for worlds that have no towns saved yet
+pub fn default_towns(world_id: &str) -> Vec<Town> {
+    let now = db::now_millis();
+    vec![Town {
+        id: db::new_id(),
+        world_id: world_id.to_string(),
+        key: "town_1".to_string(),
+        name: "Millhaven".to_string(),
+        x: 600.0,
+        y: 400.0,
+        radius: 150.0,
+        // Gareth, Sarah, Emma
+        population: 3,
+        base_safety: 80.0,
+        current_safety: 80.0,
+        created_at: now,
+        updated_at: now,
+    }]
+}
+
+/// calculateTownSafety: base safety minus the impact of every active threat
+/// within reach of the town. The player is a threat to people, not to towns.
+pub fn town_safety<'a>(town: &Town, threats: impl IntoIterator<Item = &'a ThreatSource>) -> f64 {
+    let impact: f64 = threats
+        .into_iter()
+        .filter(|threat| threat.active && threat.kind != ThreatType::Player)
+        .map(|threat| {
+            let distance = threats::distance(town.x, town.y, threat.x, threat.y);
+            // Threats within the combined radius affect the town
+            threats::threat_impact(distance, town.radius + threat.radius, threat.severity)
+        })
+        .sum();
+
+    // Cap total impact at the town's base safety
+    (town.base_safety - impact.min(town.base_safety)).max(0.0)
+}
+
+/// Whether (x, y) is inside the town's boundary (getNPCTown)
+pub fn contains(town: &Town, x: f64, y: f64) -> bool {
+    threats::distance(x, y, town.x, town.y) <= town.radius
+}
+
+/// calculateNPCSafetyFromTown: the town's safety, lowered for each of the
+/// nearest threats that is closer to (x, y) than to the town center
+pub fn npc_safety<'a>(
+    town: &Town,
+    x: f64,
+    y: f64,
+    threats: impl IntoIterator<Item = &'a ThreatSource>,
+) -> f64 {
+    let mut nearby: Vec<(&ThreatSource, f64)> = threats
+        .into_iter()
+        .filter(|threat| threat.active)
+        .map(|threat| (threat, threats::distance(x, y, threat.x, threat.y)))
+        .filter(|&(threat, distance)| distance < threat.radius * PROXIMITY_REACH)
+        .collect();
+    nearby.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let penalty: f64 = nearby
+        .into_iter()
+        .take(PROXIMITY_THREATS)
+        .filter(|&(threat, distance)| {
+            distance < threats::distance(town.x, town.y, threat.x, threat.y)
+        })
+        .map(|(threat, distance)| {
+            threats::threat_impact(distance, threat.radius, threat.severity) * PROXIMITY_SEVERITY
+        })
+        .sum();
+
+    (town.current_safety - penalty).clamp(0.0, 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ThreatMovement;
+    use crate::simulation::fixtures;
+
+    /// A town at the origin, radius 100, base safety 80
+    fn town() -> Town {
+        let mut town = default_towns(fixtures::WORLD).remove(0);
+        town.x = 0.0;
+        town.y = 0.0;
+        town.radius = 100.0;
+        town
+    }
+
+    /// Radius 50, severity 50
+    fn wolves(x: f64) -> ThreatSource {
+        fixtures::threat(ThreatMovement::Stationary, &[[x, 0.0]], 0.0)
+    }
+
+    #[test]
+    fn towns_feel_threats_within_both_radii() {
+        let town = town();
+        assert_eq!(town_safety(&town, &[]), 80.0);
+        // Half way into the combined radius of 150
+        assert_eq!(town_safety(&town, &[wolves(75.0)]), 80.0 - 12.5);
+        assert_eq!(town_safety(&town, &[wolves(150.0)]), 80.0);
+
+        let mut gone = wolves(0.0);
+        gone.active = false;
+        assert_eq!(town_safety(&town, &[gone]), 80.0);
+
+        let threats = [wolves(0.0), wolves(0.0)];
+        assert_eq!(town_safety(&town, &threats), 0.0);
+    }
+
+    #[test]
+    fn the_player_does_not_lower_town_safety() {
+        let town = town();
+        let mut player = wolves(60.0);
+        player.kind = ThreatType::Player;
+        assert_eq!(town_safety(&town, &[player.clone()]), 80.0);
+
+        // But people standing next to the player still feel it
+        assert_eq!(npc_safety(&town, 60.0, 0.0, &[player]), 80.0 - 15.0);
+    }
+
+    #[test]
+    fn npcs_start_from_the_towns_cached_safety() {
+        let mut town = town();
+        town.current_safety = 60.0;
+        let threats = [wolves(75.0)];
+
+        // Closer to the wolves than the town centre is: 24.5 impact, 30% felt
+        let safety = npc_safety(&town, 60.0, 0.0, &threats);
+        assert!((safety - (60.0 - 7.35)).abs() < 1e-9);
+        // Out of reach, or no closer than the centre
+        assert_eq!(npc_safety(&town, -60.0, 0.0, &threats), 60.0);
+        assert_eq!(npc_safety(&town, 0.0, 0.0, &threats), 60.0);
+    }
+
+    #[test]
+    fn only_the_nearest_threats_add_a_penalty() {
+        let town = town();
+        let threats = [wolves(60.0), wolves(60.0), wolves(60.0), wolves(60.0)];
+        assert_eq!(
+            npc_safety(&town, 60.0, 0.0, &threats),
+            80.0 - PROXIMITY_THREATS as f64 * 15.0
+        );
+    }
+
+    #[test]
+    fn towns_contain_their_boundary() {
+        let town = town();
+        assert!(contains(&town, 100.0, 0.0));
+        assert!(!contains(&town, 100.1, 0.0));
+    }
+}
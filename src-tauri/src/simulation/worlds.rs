@@ -0,0 +1,928 @@
+// World definition files - data/worlds/*.json, described by data/world.schema.json
+//
+// A definition lists what the TS prototype hard-codes: locations
+// (initializeLocations), NPCs with their personality and schedule
+// (NPC_PERSONALITIES), threats (THREAT_SOURCES), towns (TOWNS), storages and
+// recipes (PRODUCTION_RECIPES). Importing it checks the whole file first and
+// reports every problem at once, then creates a new World with all of it in
+// one transaction. Locations are referred to by their `key`, which only
+// exists in the file; the database gives them ids.
+//
+// The world is marked as imported and has exactly what the file lists: threats,
+// towns, storages or recipes that are left out stay empty instead of being
+// filled with the defaults other worlds get.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use super::resources::DEFAULT_STORAGE;
+use super::towns;
+use crate::data;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Location, Npc, ProductionRecipe, ResourceType, Schedule, Storage, ThreatMovement, ThreatSource,
+    ThreatType, Town, World, WorldState,
+};
+
+const WORLDS_DIR: &str = "data/worlds";
+const NPC_STATES: [&str; 4] = ["alive", "injured", "kidnapped", "dead"];
+/// Schedule activities the simulation knows (see Activity::from_schedule)
+const SCHEDULE_ACTIVITIES: [&str; 6] = ["work", "eat", "socialize", "sleep", "rest", "idle"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct WorldDefinition {
+    /// Editors use it to find the schema
+    #[serde(rename = "$schema", default)]
+    _schema: Option<IgnoredAny>,
+    name: String,
+    #[serde(default = "first_day")]
+    start_day: i32,
+    #[serde(default = "first_hour")]
+    start_hour: i32,
+    locations: Vec<LocationDef>,
+    npcs: Vec<NpcDef>,
+    #[serde(default)]
+    threats: Vec<ThreatDef>,
+    #[serde(default)]
+    towns: Vec<TownDef>,
+    #[serde(default)]
+    storages: Vec<StorageDef>,
+    /// By lowercase occupation
+    #[serde(default)]
+    recipes: BTreeMap<String, RecipeDef>,
+}
+
+/// Defaults are the ones of prisma/schema.prisma
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct LocationDef {
+    key: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "type", default = "building")]
+    kind: String,
+    #[serde(default)]
+    has_food: bool,
+    #[serde(default = "yes")]
+    has_shelter: bool,
+    #[serde(default = "yes")]
+    is_public: bool,
+    #[serde(default)]
+    is_dangerous: bool,
+    #[serde(default)]
+    danger_level: i32,
+    x: Option<f64>,
+    y: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct NpcDef {
+    name: String,
+    age: i32,
+    occupation: String,
+    #[serde(default = "alive")]
+    state: String,
+    /// Key of the location it starts at
+    location: String,
+    #[serde(default)]
+    personality: Personality,
+    #[serde(default)]
+    values: Vec<String>,
+    #[serde(default)]
+    fears: Vec<String>,
+    #[serde(default)]
+    speech: Speech,
+    #[serde(default)]
+    needs: Needs,
+    #[serde(default)]
+    emotions: Emotions,
+    #[serde(default)]
+    schedule: Vec<ScheduleDef>,
+}
+
+/// Big Five traits, 0-100
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Personality {
+    openness: i32,
+    conscientiousness: i32,
+    extraversion: i32,
+    agreeableness: i32,
+    neuroticism: i32,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Self {
+            openness: 50,
+            conscientiousness: 50,
+            extraversion: 50,
+            agreeableness: 50,
+            neuroticism: 50,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct Speech {
+    formality: i32,
+    verbosity: i32,
+    emotional_expression: i32,
+    dialect: String,
+    quirks: Vec<String>,
+}
+
+impl Default for Speech {
+    fn default() -> Self {
+        Self {
+            formality: 50,
+            verbosity: 50,
+            emotional_expression: 50,
+            dialect: String::new(),
+            quirks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Needs {
+    food: i32,
+    safety: i32,
+    wealth: i32,
+    social: i32,
+    purpose: i32,
+    rest: i32,
+}
+
+impl Default for Needs {
+    fn default() -> Self {
+        Self {
+            food: 100,
+            safety: 100,
+            wealth: 50,
+            social: 50,
+            purpose: 50,
+            rest: 100,
+        }
+    }
+}
+
+/// Love, desperation and grief are derived by the simulation
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Emotions {
+    happiness: i32,
+    anger: i32,
+    fear: i32,
+    sadness: i32,
+    trust: i32,
+    anticipation: i32,
+}
+
+impl Default for Emotions {
+    fn default() -> Self {
+        Self {
+            happiness: 50,
+            anger: 0,
+            fear: 0,
+            sadness: 0,
+            trust: 50,
+            anticipation: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleDef {
+    hour: i32,
+    activity: String,
+    /// Location key
+    location: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ThreatDef {
+    key: String,
+    #[serde(rename = "type")]
+    kind: ThreatType,
+    name: String,
+    #[serde(default)]
+    description: String,
+    x: f64,
+    y: f64,
+    radius: f64,
+    severity: i32,
+    #[serde(default)]
+    movement: ThreatMovement,
+    #[serde(default)]
+    route: Vec<[f64; 2]>,
+    #[serde(default)]
+    speed: f64,
+    #[serde(default)]
+    wander_radius: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TownDef {
+    key: String,
+    name: String,
+    x: f64,
+    y: f64,
+    radius: f64,
+    #[serde(default)]
+    population: i32,
+    #[serde(default = "base_safety")]
+    base_safety: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct StorageDef {
+    key: String,
+    name: String,
+    #[serde(default = "crate_emoji")]
+    emoji: String,
+    #[serde(default = "storage_capacity")]
+    capacity: i32,
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default)]
+    coins: i32,
+    /// Starting stock
+    #[serde(default)]
+    inventory: BTreeMap<ResourceType, i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RecipeDef {
+    produces: ResourceType,
+    amount: i32,
+    time_hours: i32,
+    #[serde(default)]
+    requires: BTreeMap<ResourceType, i32>,
+}
+
+fn first_day() -> i32 {
+    1
+}
+
+fn first_hour() -> i32 {
+    8
+}
+
+fn building() -> String {
+    "building".to_string()
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn alive() -> String {
+    "alive".to_string()
+}
+
+fn base_safety() -> f64 {
+    80.0
+}
+
+fn crate_emoji() -> String {
+    "📦".to_string()
+}
+
+fn storage_capacity() -> i32 {
+    100
+}
+
+/// Collects what is wrong with a definition
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn add(&mut self, problem: String) {
+        self.0.push(problem);
+    }
+
+    fn require(&mut self, ok: bool, problem: impl FnOnce() -> String) {
+        if !ok {
+            self.0.push(problem());
+        }
+    }
+
+    fn percent(&mut self, what: &str, stat: &str, value: i32) {
+        self.require((0..=100).contains(&value), || {
+            format!("{}: {} is {}, must be between 0 and 100", what, stat, value)
+        });
+    }
+
+    /// Keys must be filled in and used once
+    fn keys<'a>(&mut self, what: &str, keys: impl IntoIterator<Item = &'a str>) {
+        let mut seen = HashSet::new();
+        for key in keys {
+            if key.trim().is_empty() {
+                self.add(format!("{} without a key", what));
+            } else if !seen.insert(key) {
+                self.add(format!("{} \"{}\" is defined twice", what, key));
+            }
+        }
+    }
+}
+
+/// The rows of a new world, ready to insert
+struct NewWorld {
+    world: World,
+    locations: Vec<Location>,
+    npcs: Vec<Npc>,
+    schedules: Vec<Schedule>,
+    threats: Vec<ThreatSource>,
+    towns: Vec<Town>,
+    storages: Vec<Storage>,
+    recipes: Vec<ProductionRecipe>,
+}
+
+impl WorldDefinition {
+    /// Every problem with the definition; empty if it can be imported
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Problems::default();
+
+        problems.require(!self.name.trim().is_empty(), || {
+            "the world needs a name".to_string()
+        });
+        problems.require(self.start_day >= 0, || {
+            format!("startDay is {}, must not be negative", self.start_day)
+        });
+        problems.require((0..24).contains(&self.start_hour), || {
+            format!("startHour is {}, must be between 0 and 23", self.start_hour)
+        });
+
+        problems.require(!self.locations.is_empty(), || {
+            "the world needs at least one location".to_string()
+        });
+        problems.keys("location", self.locations.iter().map(|l| l.key.as_str()));
+        for location in &self.locations {
+            let what = format!("location \"{}\"", location.key);
+            problems.require(!location.name.trim().is_empty(), || {
+                format!("{}: name is empty", what)
+            });
+            problems.require(!location.kind.trim().is_empty(), || {
+                format!("{}: type is empty", what)
+            });
+            problems.percent(&what, "dangerLevel", location.danger_level);
+            problems.require(location.x.is_some() == location.y.is_some(), || {
+                format!("{}: x and y go together", what)
+            });
+        }
+        let locations: HashSet<&str> = self.locations.iter().map(|l| l.key.as_str()).collect();
+
+        problems.keys("NPC", self.npcs.iter().map(|npc| npc.name.as_str()));
+        for npc in &self.npcs {
+            npc.check(&locations, &mut problems);
+        }
+
+        problems.keys("threat", self.threats.iter().map(|t| t.key.as_str()));
+        for threat in &self.threats {
+            let what = format!("threat \"{}\"", threat.key);
+            problems.require(threat.radius > 0.0, || {
+                format!("{}: radius must be positive", what)
+            });
+            problems.percent(&what, "severity", threat.severity);
+            if threat.movement != ThreatMovement::Stationary {
+                problems.require(!threat.route.is_empty(), || {
+                    format!("{}: a {} threat needs a route", what, threat.movement)
+                });
+                problems.require(threat.speed > 0.0, || {
+                    format!("{}: a {} threat needs a speed", what, threat.movement)
+                });
+            }
+            problems.require(threat.wander_radius >= 0.0, || {
+                format!("{}: wanderRadius must not be negative", what)
+            });
+        }
+
+        problems.keys("town", self.towns.iter().map(|t| t.key.as_str()));
+        for town in &self.towns {
+            let what = format!("town \"{}\"", town.key);
+            problems.require(town.radius > 0.0, || {
+                format!("{}: radius must be positive", what)
+            });
+            problems.require(town.population >= 0, || {
+                format!("{}: population must not be negative", what)
+            });
+            problems.require((0.0..=100.0).contains(&town.base_safety), || {
+                format!(
+                    "{}: baseSafety is {}, must be between 0 and 100",
+                    what, town.base_safety
+                )
+            });
+        }
+
+        problems.keys("storage", self.storages.iter().map(|s| s.key.as_str()));
+        problems.require(
+            self.storages.is_empty() || self.storages.iter().any(|s| s.key == DEFAULT_STORAGE),
+            || {
+                format!(
+                    "storages need a \"{}\", where goods are made",
+                    DEFAULT_STORAGE
+                )
+            },
+        );
+        for storage in &self.storages {
+            let what = format!("storage \"{}\"", storage.key);
+            problems.require(storage.capacity > 0, || {
+                format!("{}: capacity must be positive", what)
+            });
+            problems.require(storage.coins >= 0, || {
+                format!("{}: coins must not be negative", what)
+            });
+            for (resource, &amount) in &storage.inventory {
+                problems.require(amount >= 0, || {
+                    format!("{}: {} must not be negative", what, resource)
+                });
+            }
+            // i64 so that huge amounts are reported instead of overflowing
+            let stored: i64 = storage
+                .inventory
+                .values()
+                .map(|&amount| amount as i64)
+                .sum();
+            problems.require(stored <= storage.capacity as i64, || {
+                format!(
+                    "{}: holds {} but has room for {}",
+                    what, stored, storage.capacity
+                )
+            });
+        }
+
+        for (occupation, recipe) in &self.recipes {
+            let what = format!("recipe \"{}\"", occupation);
+            problems.require(
+                !occupation.trim().is_empty() && *occupation == occupation.to_lowercase(),
+                || format!("{}: occupations are lowercase", what),
+            );
+            problems.require(recipe.amount > 0, || {
+                format!("{}: amount must be positive", what)
+            });
+            problems.require(recipe.time_hours > 0, || {
+                format!("{}: timeHours must be positive", what)
+            });
+            for (resource, &amount) in &recipe.requires {
+                problems.require(amount > 0, || {
+                    format!("{}: {} must be positive", what, resource)
+                });
+            }
+        }
+
+        problems.0
+    }
+
+    fn build(&self) -> NewWorld {
+        let now = db::now_millis();
+        let world = World {
+            id: db::new_id(),
+            name: self.name.clone(),
+            current_day: self.start_day,
+            current_hour: self.start_hour,
+            imported: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let world_id = world.id.as_str();
+
+        let locations: Vec<Location> = self
+            .locations
+            .iter()
+            .map(|def| Location {
+                id: db::new_id(),
+                world_id: world_id.to_string(),
+                name: def.name.clone(),
+                description: def.description.clone(),
+                kind: def.kind.clone(),
+                has_food: def.has_food,
+                has_shelter: def.has_shelter,
+                is_public: def.is_public,
+                is_dangerous: def.is_dangerous,
+                danger_level: def.danger_level,
+                x: def.x,
+                y: def.y,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+        // Checked by problems()
+        let location_id = |key: &str| {
+            let index = self.locations.iter().position(|l| l.key == key);
+            index.map(|i| locations[i].id.clone()).unwrap_or_default()
+        };
+
+        let mut npcs = Vec::new();
+        let mut schedules = Vec::new();
+        for def in &self.npcs {
+            let npc = def.build(world_id, location_id(&def.location), now);
+            schedules.extend(def.schedule.iter().map(|entry| Schedule {
+                id: db::new_id(),
+                npc_id: npc.id.clone(),
+                hour: entry.hour,
+                activity: entry.activity.clone(),
+                location_id: location_id(&entry.location),
+                created_at: now,
+            }));
+            npcs.push(npc);
+        }
+
+        let threats: Vec<ThreatSource> = self
+            .threats
+            .iter()
+            .map(|def| ThreatSource {
+                id: db::new_id(),
+                world_id: world_id.to_string(),
+                key: def.key.clone(),
+                kind: def.kind,
+                name: def.name.clone(),
+                description: def.description.clone(),
+                x: def.x,
+                y: def.y,
+                radius: def.radius,
+                severity: def.severity,
+                active: true,
+                // The player is moved by the frontend
+                mobile: def.kind == ThreatType::Player
+                    || def.movement != ThreatMovement::Stationary,
+                movement: def.movement,
+                route: def.route.clone(),
+                route_index: 0,
+                speed: def.speed,
+                wander_radius: def.wander_radius,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+        let towns = self
+            .towns
+            .iter()
+            .map(|def| {
+                let mut town = Town {
+                    id: db::new_id(),
+                    world_id: world_id.to_string(),
+                    key: def.key.clone(),
+                    name: def.name.clone(),
+                    x: def.x,
+                    y: def.y,
+                    radius: def.radius,
+                    population: def.population,
+                    base_safety: def.base_safety,
+                    current_safety: def.base_safety,
+                    created_at: now,
+                    updated_at: now,
+                };
+                town.current_safety = towns::town_safety(&town, &threats);
+                town
+            })
+            .collect();
+
+        let storages = self
+            .storages
+            .iter()
+            .map(|def| {
+                let mut storage = Storage::new(
+                    world_id,
+                    &def.key,
+                    &def.name,
+                    &def.emoji,
+                    def.capacity,
+                    (def.x, def.y),
+                );
+                storage.coins = def.coins;
+                storage.inventory = def.inventory.clone();
+                storage
+            })
+            .collect();
+
+        let recipes = self
+            .recipes
+            .iter()
+            .map(|(occupation, def)| ProductionRecipe {
+                id: db::new_id(),
+                world_id: world_id.to_string(),
+                occupation: occupation.clone(),
+                produces: def.produces,
+                amount: def.amount,
+                time_hours: def.time_hours,
+                requires: def.requires.clone(),
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+        NewWorld {
+            world,
+            locations,
+            npcs,
+            schedules,
+            threats,
+            towns,
+            storages,
+            recipes,
+        }
+    }
+}
+
+impl NpcDef {
+    fn check(&self, locations: &HashSet<&str>, problems: &mut Problems) {
+        let what = format!("NPC \"{}\"", self.name);
+        problems.require(self.age >= 0, || {
+            format!("{}: age must not be negative", what)
+        });
+        problems.require(!self.occupation.trim().is_empty(), || {
+            format!("{}: occupation is empty", what)
+        });
+        problems.require(NPC_STATES.contains(&self.state.as_str()), || {
+            format!(
+                "{}: state \"{}\" is not one of {}",
+                what,
+                self.state,
+                NPC_STATES.join(", ")
+            )
+        });
+        problems.require(locations.contains(self.location.as_str()), || {
+            format!("{}: unknown location \"{}\"", what, self.location)
+        });
+
+        let p = &self.personality;
+        let s = &self.speech;
+        let n = &self.needs;
+        let e = &self.emotions;
+        let stats = [
+            ("openness", p.openness),
+            ("conscientiousness", p.conscientiousness),
+            ("extraversion", p.extraversion),
+            ("agreeableness", p.agreeableness),
+            ("neuroticism", p.neuroticism),
+            ("formality", s.formality),
+            ("verbosity", s.verbosity),
+            ("emotionalExpression", s.emotional_expression),
+            ("needs.food", n.food),
+            ("needs.safety", n.safety),
+            ("needs.wealth", n.wealth),
+            ("needs.social", n.social),
+            ("needs.purpose", n.purpose),
+            ("needs.rest", n.rest),
+            ("emotions.happiness", e.happiness),
+            ("emotions.anger", e.anger),
+            ("emotions.fear", e.fear),
+            ("emotions.sadness", e.sadness),
+            ("emotions.trust", e.trust),
+            ("emotions.anticipation", e.anticipation),
+        ];
+        for (stat, value) in stats {
+            problems.percent(&what, stat, value);
+        }
+
+        let mut hours = HashSet::new();
+        for entry in &self.schedule {
+            problems.require((0..24).contains(&entry.hour), || {
+                format!("{}: schedule hour {} is not 0-23", what, entry.hour)
+            });
+            problems.require(hours.insert(entry.hour), || {
+                format!("{}: schedule has hour {} twice", what, entry.hour)
+            });
+            problems.require(
+                SCHEDULE_ACTIVITIES.contains(&entry.activity.as_str()),
+                || {
+                    format!(
+                        "{}: activity \"{}\" is not one of {}",
+                        what,
+                        entry.activity,
+                        SCHEDULE_ACTIVITIES.join(", ")
+                    )
+                },
+            );
+            problems.require(locations.contains(entry.location.as_str()), || {
+                format!(
+                    "{}: schedule at {} goes to unknown location \"{}\"",
+                    what, entry.hour, entry.location
+                )
+            });
+        }
+    }
+
+    fn build(&self, world_id: &str, location_id: String, now: i64) -> Npc {
+        let (p, s, n, e) = (&self.personality, &self.speech, &self.needs, &self.emotions);
+        Npc {
+            id: db::new_id(),
+            world_id: world_id.to_string(),
+            name: self.name.clone(),
+            age: self.age,
+            occupation: self.occupation.clone(),
+            state: self.state.clone(),
+            location_id,
+            openness: p.openness,
+            conscientiousness: p.conscientiousness,
+            extraversion: p.extraversion,
+            agreeableness: p.agreeableness,
+            neuroticism: p.neuroticism,
+            values: self.values.clone(),
+            fears: self.fears.clone(),
+            formality: s.formality,
+            verbosity: s.verbosity,
+            emotional_expression: s.emotional_expression,
+            dialect: s.dialect.clone(),
+            speech_quirks: s.quirks.clone(),
+            need_food: n.food,
+            need_safety: n.safety,
+            need_wealth: n.wealth,
+            need_social: n.social,
+            need_purpose: n.purpose,
+            need_rest: n.rest,
+            emotion_happiness: e.happiness,
+            emotion_anger: e.anger,
+            emotion_fear: e.fear,
+            emotion_sadness: e.sadness,
+            emotion_trust: e.trust,
+            emotion_anticipation: e.anticipation,
+            emotion_love: 0,
+            emotion_desperation: 0,
+            emotion_grief: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// `name_or_path` is either the name of a file in data/worlds ("riverside")
+/// or the path to a definition file anywhere
+fn resolve(name_or_path: &str) -> PathBuf {
+    let path = Path::new(name_or_path);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let relative = if path.extension().is_none() && path.components().count() == 1 {
+        format!("{}/{}.json", WORLDS_DIR, name_or_path)
+    } else {
+        name_or_path.to_string()
+    };
+    data::find(&relative).unwrap_or_else(|| PathBuf::from(relative))
+}
+
+/// Validate the world definition and import it as a new world. Nothing is
+/// written unless the whole file is valid.
+pub fn import_world(db: &Database, name_or_path: &str) -> AppResult<WorldState> {
+    let path = resolve(name_or_path);
+    let definition: WorldDefinition = data::read(&path)?;
+    let problems = definition.problems();
+    if !problems.is_empty() {
+        return Err(AppError::InvalidData {
+            path,
+            reason: problems.join("; "),
+        });
+    }
+
+    let new = definition.build();
+    let mut conn = db.connect()?;
+    db::in_transaction(&mut conn, |conn| {
+        db::insert_world(conn, &new.world)?;
+        db::insert_locations(conn, &new.locations)?;
+        db::insert_npcs(conn, &new.npcs)?;
+        db::insert_schedules(conn, &new.schedules)?;
+        db::save_threats(conn, &new.threats.iter().collect::<Vec<_>>())?;
+        db::save_towns(conn, &new.towns.iter().collect::<Vec<_>>())?;
+        db::save_storages(conn, &new.storages.iter().collect::<Vec<_>>())?;
+        db::save_production_recipes(conn, &new.recipes.iter().collect::<Vec<_>>())
+    })?;
+
+    Ok(db::load_world_state(&conn, new.world)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> WorldDefinition {
+        serde_json::from_str(json).unwrap()
+    }
+
+    const MINIMAL: &str = r#"{
+        "name": "Tiny",
+        "locations": [{ "key": "hut", "name": "Hut" }],
+        "npcs": [{ "name": "Ann", "age": 30, "occupation": "farmer", "location": "hut" }]
+    }"#;
+
+    #[test]
+    fn shipped_worlds_are_valid() {
+        let riverside = parse(include_str!("../../../data/worlds/riverside.json"));
+        assert_eq!(riverside.problems(), Vec::<String>::new());
+
+        let new = riverside.build();
+        assert_eq!(new.locations.len(), riverside.locations.len());
+        assert_eq!(new.npcs.len(), riverside.npcs.len());
+        assert!(new
+            .npcs
+            .iter()
+            .all(|npc| new.locations.iter().any(|l| l.id == npc.location_id)));
+        assert!(new.threats.iter().any(|threat| threat.mobile));
+    }
+
+    #[test]
+    fn left_out_content_stays_empty() {
+        let tiny = parse(MINIMAL);
+        assert_eq!(tiny.problems(), Vec::<String>::new());
+
+        let new = tiny.build();
+        assert!(new.world.imported);
+        assert_eq!((new.world.current_day, new.world.current_hour), (1, 8));
+        assert!(new.threats.is_empty());
+        assert!(new.towns.is_empty());
+        assert!(new.storages.is_empty());
+        assert!(new.recipes.is_empty());
+
+        let ann = &new.npcs[0];
+        assert_eq!(ann.location_id, new.locations[0].id);
+        assert_eq!((ann.state.as_str(), ann.openness), ("alive", 50));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let bad = parse(
+            r#"{
+            "name": " ",
+            "startHour": 30,
+            "locations": [
+                { "key": "a", "name": "A", "dangerLevel": 120, "x": 1 },
+                { "key": "a", "name": "B" }
+            ],
+            "npcs": [{
+                "name": "Bob", "age": -1, "occupation": "x", "state": "sleepy", "location": "nowhere",
+                "personality": { "openness": 200 },
+                "schedule": [
+                    { "hour": 3, "activity": "dance", "location": "zz" },
+                    { "hour": 3, "activity": "work", "location": "a" }
+                ]
+            }],
+            "threats": [{ "key": "t", "type": "wolf_den", "name": "W", "x": 0, "y": 0, "radius": 0, "severity": 50, "movement": "patrol" }],
+            "storages": [
+                { "key": "shed", "name": "Shed", "capacity": 5, "inventory": { "wood": 10 } },
+                { "key": "silo", "name": "Silo", "inventory": { "wood": 2147483647, "stone": 2147483647 } }
+            ],
+            "recipes": { "Baker": { "produces": "food", "amount": 0, "timeHours": 1, "requires": { "food": 0 } } }
+        }"#,
+        );
+
+        let problems = bad.problems();
+        let expected = [
+            "the world needs a name",
+            "startHour is 30, must be between 0 and 23",
+            "location \"a\" is defined twice",
+            "location \"a\": dangerLevel is 120, must be between 0 and 100",
+            "location \"a\": x and y go together",
+            "NPC \"Bob\": age must not be negative",
+            "NPC \"Bob\": state \"sleepy\" is not one of alive, injured, kidnapped, dead",
+            "NPC \"Bob\": unknown location \"nowhere\"",
+            "NPC \"Bob\": openness is 200, must be between 0 and 100",
+            "NPC \"Bob\": schedule has hour 3 twice",
+            "NPC \"Bob\": schedule at 3 goes to unknown location \"zz\"",
+            "threat \"t\": radius must be positive",
+            "threat \"t\": a patrol threat needs a route",
+            "threat \"t\": a patrol threat needs a speed",
+            "storages need a \"warehouse\", where goods are made",
+            "storage \"shed\": holds 10 but has room for 5",
+            "storage \"silo\": holds 4294967294 but has room for 100",
+            "recipe \"Baker\": occupations are lowercase",
+            "recipe \"Baker\": amount must be positive",
+            "recipe \"Baker\": food must be positive",
+        ];
+        for problem in expected {
+            assert!(
+                problems.iter().any(|p| p == problem),
+                "missing {:?} in {:#?}",
+                problem,
+                problems
+            );
+        }
+        assert!(problems.iter().any(|p| p.contains("activity \"dance\"")));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let error = serde_json::from_str::<WorldDefinition>(
+            r#"{ "name": "x", "locations": [], "npcs": [], "wizards": [] }"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown field `wizards`"));
+    }
+}
@@ -6,6 +6,7 @@ import {
 } from "./sprite-config";
 import {
   ResourceManager,
+  ResourceType,
   PRODUCTION_RECIPES,
 } from "./resource-system-with-logging";
 import {
@@ -54,7 +55,10 @@ const isTauri =
 const DEV_SERVER_URL = "http://localhost:3001";
 
 // Helper function to safely call Tauri commands
-async function callTauriCommand(command: string): Promise<string> {
+async function callTauriCommand<T = any>(
+  command: string,
+  args?: Record<string, unknown>
+): Promise<T> {
   if (!isTauri) {
     throw new Error("Tauri commands only available in desktop mode");
   }
@@ -62,14 +66,29 @@ async function callTauriCommand(command: string): Promise<string> {
   try {
     const tauri = (window as any).__TAURI__;
     if (tauri?.tauri?.invoke) {
-      return await tauri.tauri.invoke(command);
+      return await tauri.tauri.invoke(command, args);
     }
     throw new Error("Tauri invoke not available");
-  } catch (e) {
+  } catch (e: any) {
+    // Rust commands reject with { code, message, details? }
+    if (e && typeof e === "object" && "code" in e) {
+      console.error(`Tauri command ${command} failed [${e.code}]: ${e.message}`, e.details);
+      throw e;
+    }
     throw new Error(`Failed to call Tauri command: ${e}`);
   }
 }
 
+// Subscribe to events pushed by the Rust simulation (world://tick, ...)
+async function listenTauriEvent<T = any>(
+  event: string,
+  handler: (payload: T) => void
+): Promise<void> {
+  const tauri = (window as any).__TAURI__;
+  if (!isTauri || !tauri?.event?.listen) return;
+  await tauri.event.listen(event, (e: { payload: T }) => handler(e.payload));
+}
+
 // Helper function to fetch from dev server (browser mode)
 async function fetchFromDevServer(endpoint: string): Promise<any> {
   const response = await fetch(`${DEV_SERVER_URL}${endpoint}`);
@@ -155,6 +174,8 @@ class GameScene extends Phaser.Scene {
   // Location crisis system
   private locationCrisisSystem!: LocationCrisisSystem;
   private crisisZoneGraphics: Phaser.GameObjects.Graphics[] = [];
+  // Desktop mode: fires, floods and famines run by the Rust simulation, by crisis id
+  private locationCrisisVisuals: Map<string, Phaser.GameObjects.Container> = new Map();
   
   // Threat system visuals
   private threatVisuals: Map<string, Phaser.GameObjects.Container> = new Map();
@@ -483,14 +504,23 @@ class GameScene extends Phaser.Scene {
     console.log(`🕐 HOUR ${currentHour} UPDATE`);
     console.log("=".repeat(60));
 
-    // Update daily cycle system (handles checkpoints at 6, 12, 18, 22) - but only if not in testing mode
-    if (!this.testingMode) {
+    // Update daily cycle system (handles checkpoints at 6, 12, 18, 22) - but only if not in testing mode.
+    // In desktop mode the Rust tick loop runs the checkpoints instead.
+    if (!this.testingMode && !isTauri) {
       this.cycleSystem.onHourChange(currentHour);
       
       // Update NPC needs every hour (building effects + activity recovery)
       this.cycleSystem.updateNPCNeeds();
     }
 
+    // In desktop mode the Rust tick runs production and town safety; show what it saved
+    if (isTauri) {
+      this.loadSavedStorages();
+      this.loadSavedTowns();
+      this.lastHour = currentHour;
+      return;
+    }
+
     // First, check what NPCs are thinking
     if (this.worldData?.npcs) {
       this.resourceManager.logNPCThoughts(this.worldData.npcs, currentHour);
@@ -565,6 +595,60 @@ class GameScene extends Phaser.Scene {
     console.log(`\n${"=".repeat(60)}\n`);
   }
 
+  /**
+   * Desktop mode: copy the storages saved by the Rust economy into the
+   * resource manager so the panel shows the persisted stock
+   */
+  async loadSavedStorages(): Promise<void> {
+    try {
+      const storages = await callTauriCommand("list_storages");
+      for (const saved of storages) {
+        const storage =
+          this.resourceManager.getStorage(saved.key) ??
+          this.resourceManager.createStorage(
+            saved.key,
+            saved.name,
+            saved.emoji,
+            saved.capacity,
+            saved.x,
+            saved.y
+          );
+        storage.inventory = new Map(
+          Object.entries(saved.inventory) as [ResourceType, number][]
+        );
+      }
+      this.resourcePanel.update(this.resourceManager);
+    } catch (e) {
+      console.error("Failed to load storages:", e);
+    }
+  }
+
+  /**
+   * Desktop mode: replace TOWNS with the towns saved by the Rust safety
+   * service, keeping the safety it last calculated
+   */
+  async loadSavedTowns(): Promise<void> {
+    try {
+      const towns = await callTauriCommand("list_towns");
+      TOWNS.splice(
+        0,
+        TOWNS.length,
+        ...towns.map((town: any) => ({
+          id: town.key,
+          name: town.name,
+          x: town.x,
+          y: town.y,
+          radius: town.radius,
+          population: town.population,
+          baseSafety: town.baseSafety,
+          currentSafety: town.currentSafety,
+        }))
+      );
+    } catch (e) {
+      console.error("Failed to load towns:", e);
+    }
+  }
+
   async create() {
     console.log("🎬 NEW CODE: Starting create() method with player support...");
     console.log("🔍 Tauri detection: isTauri =", isTauri);
@@ -697,6 +781,20 @@ class GameScene extends Phaser.Scene {
 
     // Initial load
     this.updateWorld(); // This loads the initial world data
+
+    // Apply per-tick diffs pushed by the Rust simulation
+    listenTauriEvent("world://tick", (diff) => this.applyWorldDiff(diff));
+
+    // The Rust daily cycle replaces the cycle system's callbacks in desktop mode
+    listenTauriEvent("world://checkpoint", (checkpoint) => {
+      console.log(`🌅 ${checkpoint.name.toUpperCase()} CHECKPOINT (Day ${checkpoint.day}, Hour ${checkpoint.hour})`);
+      if (checkpoint.fleeing.length > 0) {
+        console.log(`⚠️  ${checkpoint.fleeing.length} NPCs have critical security and are fleeing!`);
+      }
+    });
+    listenTauriEvent("world://npc-message", (msg) => this.addNPCMessage(msg.npcName, msg.message));
+    listenTauriEvent("world://npc-moved", (move) => this.moveNPCToLocation(move.npcName, move.toLocationId));
+    listenTauriEvent("world://threat-moved", (moved) => this.onThreatMoved(moved));
   }
 
   createBackground() {
@@ -772,8 +870,10 @@ class GameScene extends Phaser.Scene {
       playPauseBtn.setText(this.gameRunning ? "⏸️ Pause" : "▶️ Start");
       playPauseBtn.setColor(this.gameRunning ? "#ff0" : "#0f0");
       
-      // Control the hourly timer
-      if (this.hourlyTimer) {
+      // Control the clock: the Rust tick loop in desktop mode, the hourly timer otherwise
+      if (isTauri) {
+        this.setSimulationRunning(this.gameRunning);
+      } else if (this.hourlyTimer) {
         this.hourlyTimer.paused = !this.gameRunning;
       }
       
@@ -781,11 +881,32 @@ class GameScene extends Phaser.Scene {
     });
   }
 
+  /**
+   * Start / pause / resume the Rust tick loop (desktop mode)
+   */
+  async setSimulationRunning(running: boolean): Promise<void> {
+    try {
+      if (!running) {
+        await callTauriCommand("pause_simulation");
+        return;
+      }
+      const status = await callTauriCommand("get_simulation_status");
+      if (status.state === "paused") {
+        await callTauriCommand("resume_simulation");
+      } else if (status.state === "stopped") {
+        await callTauriCommand("start_simulation");
+      }
+    } catch (e) {
+      console.error("Failed to control simulation:", e);
+    }
+  }
+
   /**
    * Hourly tick - advances game time by 1 hour
    */
   hourlyTick() {
-    if (!this.gameRunning || !this.worldData) return;
+    // Desktop mode: the Rust tick loop owns the clock (see world://tick)
+    if (isTauri || !this.gameRunning || !this.worldData) return;
 
     console.log(`\n⏰ === HOURLY TICK === Hour ${this.worldData.currentHour || 0} → ${(this.worldData.currentHour || 0) + 1}`);
     
@@ -938,37 +1059,7 @@ class GameScene extends Phaser.Scene {
     // Visualize threat sources
     for (const threat of THREAT_SOURCES) {
       if (!threat.active) continue;
-
-      const container = this.add.container(threat.x, threat.y);
-      
-      // Threat radius circle
-      const graphics = this.add.graphics();
-      graphics.lineStyle(2, getThreatColor(threat.type), 0.5);
-      graphics.fillStyle(getThreatColor(threat.type), 0.1);
-      graphics.strokeCircle(0, 0, threat.radius);
-      graphics.fillCircle(0, 0, threat.radius);
-      container.add(graphics);
-
-      // Threat icon (skip for player - they have their own sprite)
-      if (threat.type !== 'player') {
-        const icon = this.add.text(0, 0, getThreatEmoji(threat.type), {
-          fontSize: '24px'
-        }).setOrigin(0.5);
-        container.add(icon);
-      }
-
-      // Threat name (skip for player)
-      if (threat.type !== 'player') {
-        const label = this.add.text(0, threat.radius + 10, threat.name, {
-          fontSize: '12px',
-          color: '#ff6666',
-          stroke: '#000',
-          strokeThickness: 2
-        }).setOrigin(0.5);
-        container.add(label);
-      }
-
-      this.threatVisuals.set(threat.id, container);
+      this.addThreatVisual(threat);
     }
 
     // Visualize town boundaries
@@ -992,6 +1083,99 @@ class GameScene extends Phaser.Scene {
     }
   }
 
+  /**
+   * Draw a threat's radius, icon and name
+   */
+  addThreatVisual(threat: ThreatSource): void {
+    const container = this.add.container(threat.x, threat.y);
+
+    // Threat radius circle
+    const graphics = this.add.graphics();
+    graphics.lineStyle(2, getThreatColor(threat.type), 0.5);
+    graphics.fillStyle(getThreatColor(threat.type), 0.1);
+    graphics.strokeCircle(0, 0, threat.radius);
+    graphics.fillCircle(0, 0, threat.radius);
+    container.add(graphics);
+
+    // Threat icon (skip for player - they have their own sprite)
+    if (threat.type !== 'player') {
+      const icon = this.add.text(0, 0, getThreatEmoji(threat.type), {
+        fontSize: '24px'
+      }).setOrigin(0.5);
+      container.add(icon);
+    }
+
+    // Threat name (skip for player)
+    if (threat.type !== 'player') {
+      const label = this.add.text(0, threat.radius + 10, threat.name, {
+        fontSize: '12px',
+        color: '#ff6666',
+        stroke: '#000',
+        strokeThickness: 2
+      }).setOrigin(0.5);
+      container.add(label);
+    }
+
+    this.threatVisuals.set(threat.id, container);
+  }
+
+  /**
+   * Desktop mode: follow a mobile threat moved by the Rust tick, adding it
+   * first if the frontend does not know it yet (raids, wolf packs)
+   */
+  onThreatMoved(moved: any): void {
+    let threat = THREAT_SOURCES.find((t) => t.id === moved.threat.key);
+    if (!threat) {
+      threat = { ...moved.threat, id: moved.threat.key } as ThreatSource;
+      THREAT_SOURCES.push(threat);
+      this.addThreatVisual(threat);
+    }
+    threat.x = moved.threat.x;
+    threat.y = moved.threat.y;
+
+    const visual = this.threatVisuals.get(threat.id);
+    if (visual) {
+      this.tweens.add({ targets: visual, x: threat.x, y: threat.y, duration: 500 });
+    }
+  }
+
+  /**
+   * Desktop mode: show a fire, flood or famine at its location, or take it
+   * off the map once it is resolved
+   */
+  onLocationCrisis(crisis: any): void {
+    const existing = this.locationCrisisVisuals.get(crisis.id);
+    if (crisis.resolved) {
+      existing?.destroy();
+      this.locationCrisisVisuals.delete(crisis.id);
+      return;
+    }
+
+    const location = this.worldData?.locations?.find((l: any) => l.id === crisis.locationId);
+    if (!location || location.x == null || location.y == null) return;
+
+    const color = { fire: 0xff4500, flood: 0x1e90ff, famine: 0xdaa520 }[crisis.type as string] ?? 0xff0000;
+    const emoji = { fire: "🔥", flood: "🌊", famine: "🌾" }[crisis.type as string] ?? "⚠️";
+    // Grows and shrinks with severity
+    const radius = 20 + crisis.severity * 0.6;
+
+    existing?.destroy();
+    const container = this.add.container(location.x, location.y);
+    const graphics = this.add.graphics();
+    graphics.lineStyle(2, color, 0.8);
+    graphics.fillStyle(color, 0.15 + crisis.severity / 400);
+    graphics.strokeCircle(0, 0, radius);
+    graphics.fillCircle(0, 0, radius);
+    container.add(graphics);
+    container.add(this.add.text(0, -radius - 12, `${emoji} ${Math.round(crisis.severity)}`, {
+      fontSize: '14px',
+      color: '#fff',
+      stroke: '#000',
+      strokeThickness: 2
+    }).setOrigin(0.5));
+    this.locationCrisisVisuals.set(crisis.id, container);
+  }
+
   /**
    * Move NPC to a new location with walking animation
    */
@@ -1173,8 +1357,7 @@ class GameScene extends Phaser.Scene {
   async updateWorld() {
     try {
       if (isTauri) {
-        const worldDataStr = await callTauriCommand("get_world_state");
-        this.worldData = JSON.parse(worldDataStr);
+        this.worldData = await callTauriCommand("get_world_state");
       } else {
         if (this.devServerConnected) {
           try {
@@ -1189,6 +1372,9 @@ class GameScene extends Phaser.Scene {
       }
 
       if (this.worldData) {
+        for (const crisis of this.worldData.locationCrises ?? []) {
+          this.onLocationCrisis(crisis);
+        }
         this.updateResourceSystem(this.worldData.currentHour);
         this.renderWorld();
       }
@@ -1197,6 +1383,68 @@ class GameScene extends Phaser.Scene {
     }
   }
 
+  /**
+   * Merge a world://tick diff (changed NPCs only) into the loaded world
+   */
+  applyWorldDiff(diff: any) {
+    if (!this.worldData) return;
+
+    this.worldData.currentDay = diff.day;
+    this.worldData.currentHour = diff.hour;
+
+    for (const changed of diff.npcs) {
+      const npc = this.worldData.npcs.find((n: any) => n.id === changed.id);
+      if (npc) {
+        Object.assign(npc, changed);
+      } else {
+        this.worldData.npcs.push({ ...changed, goals: [] });
+      }
+    }
+
+    // Goals: replace by id, drop completed / failed ones
+    for (const goal of diff.goals ?? []) {
+      const npc = this.worldData.npcs.find((n: any) => n.id === goal.npcId);
+      if (!npc) continue;
+
+      const goals = (npc.goals ?? []).filter((g: any) => g.id !== goal.id);
+      if (!goal.completed && !goal.failed) goals.push(goal);
+      npc.goals = goals.sort((a: any, b: any) => b.priority - a.priority);
+    }
+
+    // Production moved goods in or out of a storage
+    if (diff.production?.length) {
+      this.loadSavedStorages();
+    }
+
+    // The player or a party of NPCs cleared a threat
+    for (const cleared of diff.clearedThreats ?? []) {
+      const threat = THREAT_SOURCES.find((t) => t.id === cleared.key);
+      if (threat) threat.active = false;
+      this.threatVisuals.get(cleared.key)?.destroy();
+      this.threatVisuals.delete(cleared.key);
+    }
+
+    // Location crises changed food, shelter or danger of a location
+    for (const changed of diff.locations ?? []) {
+      const location = this.worldData.locations?.find((l: any) => l.id === changed.id);
+      if (location) Object.assign(location, changed);
+    }
+    for (const crisis of diff.locationCrises ?? []) {
+      this.onLocationCrisis(crisis);
+    }
+
+    // A threat was added, moved or cleared and town safety was recalculated
+    for (const changed of diff.towns ?? []) {
+      const town = TOWNS.find((t) => t.id === changed.key);
+      if (town) town.currentSafety = changed.currentSafety;
+    }
+    if (diff.towns?.length) {
+      console.log(`🏘️ Town Safety: ${getTownSafetySummary()}`);
+    }
+
+    this.renderWorld();
+  }
+
   getMockData() {
     // Cycle through hours every minute for testing
     const currentMinute = Math.floor(Date.now() / 60000);
@@ -31,7 +31,9 @@ async function seed() {
       hasShelter: true,
       isPublic: true,
       isDangerous: false,
-      dangerLevel: 0
+      dangerLevel: 0,
+      x: 600,
+      y: 400
     }
   });
 
@@ -45,7 +47,9 @@ async function seed() {
       hasShelter: true,
       isPublic: false,
       isDangerous: true,
-      dangerLevel: 20
+      dangerLevel: 20,
+      x: 400,
+      y: 450
     }
   });
 
@@ -59,7 +63,9 @@ async function seed() {
       hasShelter: false,
       isPublic: true,
       isDangerous: true,
-      dangerLevel: 60
+      dangerLevel: 60,
+      x: 180,
+      y: 430
     }
   });
 